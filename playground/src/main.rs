@@ -1,5 +1,12 @@
-use rupl::{command::Command, error::ReplResult, Repl};
+use std::env;
 
+use rupl::{
+    command::{Command, FnContext},
+    error::ReplResult,
+    Repl,
+};
+
+#[allow(dead_code)]
 struct Ctx {
     counter: usize,
 }
@@ -10,8 +17,9 @@ fn main() -> ReplResult<()> {
     let mut repl = Repl::<_>::builder(&mut state)
         .with_prompt(">>")
         .with_output_prompt("#")
+        .with_command(counter())
         .with_command(
-            Command::new("service", service).with_subcommand(
+            Command::namespace("service").with_subcommand(
                 Command::new("dns", service_dns)
                     .with_subcommand(Command::new("status", service_dns))
                     .with_arg("port", false)
@@ -20,13 +28,30 @@ fn main() -> ReplResult<()> {
         )
         .build();
 
+    let args: Vec<String> = env::args().collect();
+    if let [_, flag, line] = args.as_slice() {
+        if flag == "-c" {
+            repl.exec_and_exit(line)?;
+            return Ok(());
+        }
+    }
+
     repl.run()
 }
 
-fn service(ctx: &mut ()) -> String {
-    "Hello from service".into()
+/// A command which counts its invocations in a captured variable.
+fn counter() -> Command<()> {
+    let mut count = 0;
+
+    Command::new("counter", move |_| {
+        count += 1;
+        Ok(Some(format!("Called {} times", count)))
+    })
 }
 
-fn service_dns(ctx: &mut ()) -> String {
-    "Hello from service_dns".into()
+fn service_dns(ctx: FnContext<()>) -> ReplResult<Option<String>> {
+    match ctx.args().get::<u16>("port") {
+        Ok(port) => Ok(Some(format!("Hello from service_dns on port {}", port))),
+        Err(_) => Ok(Some("Hello from service_dns".into())),
+    }
 }
@@ -0,0 +1,115 @@
+use unicode_width::UnicodeWidthChar;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+const RESET: &str = "\x1b[0m";
+
+/// The OSC 133 shell integration marks, see
+/// [`ReplBuilder::with_prompt_markers`](crate::builder::ReplBuilder::with_prompt_markers).
+pub(crate) const PROMPT_START: &str = "\x1b]133;A\x07";
+pub(crate) const COMMAND_START: &str = "\x1b]133;B\x07";
+pub(crate) const COMMAND_EXECUTED: &str = "\x1b]133;C\x07";
+
+/// Returns the OSC 133 mark for a finished command with its exit code.
+pub(crate) fn command_finished(exit_code: u8) -> String {
+    format!("\x1b]133;D;{}\x07", exit_code)
+}
+
+/// Removes all ANSI escape sequences, like colors or cursor movements, from
+/// the input. Both CSI (`ESC [`) and OSC (`ESC ]`) sequences are supported.
+pub fn strip(input: &str) -> String {
+    let mut stripped = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            ESC => sequence_len(rest),
+            c => {
+                stripped.push(c);
+                c.len_utf8()
+            }
+        };
+
+        rest = &rest[len..];
+    }
+
+    stripped
+}
+
+/// Truncates the input to the number of terminal columns. Only visible chars
+/// are counted and escape sequences are never cut. If the input is cut and
+/// contains escape sequences, a reset (`ESC [0m`) is appended, so colors
+/// don't leak into the following output. Input which fits is returned as it
+/// is.
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::ansi::truncate;
+/// assert_eq!(truncate("\x1b[31mservice\x1b[0m", 3), "\x1b[31mser\x1b[0m");
+/// assert_eq!(truncate("dns", 3), "dns");
+/// ```
+pub fn truncate(input: &str, width: usize) -> String {
+    let mut truncated = String::with_capacity(input.len());
+    let mut has_sequences = false;
+    let mut columns = 0;
+    let mut rest = input;
+
+    while let Some(c) = rest.chars().next() {
+        if c == ESC {
+            let len = sequence_len(rest);
+            truncated.push_str(&rest[..len]);
+            has_sequences = true;
+            rest = &rest[len..];
+            continue;
+        }
+
+        columns += c.width().unwrap_or(0);
+        if columns > width {
+            if has_sequences {
+                truncated.push_str(RESET);
+            }
+            return truncated;
+        }
+
+        truncated.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    truncated
+}
+
+/// Returns the length in bytes of the escape sequence at the start of the
+/// input, which starts with ESC. Unterminated sequences span the rest of
+/// the input.
+fn sequence_len(input: &str) -> usize {
+    let mut chars = input.char_indices().skip(1).peekable();
+
+    let end = match chars.next() {
+        // CSI sequences end with a byte in the range 0x40..=0x7e
+        Some((_, '[')) => chars.find(|(_, c)| ('\x40'..='\x7e').contains(c)),
+        // OSC sequences end with BEL or ST (ESC \)
+        Some((_, ']')) => loop {
+            match chars.next() {
+                Some((_, ESC)) if matches!(chars.peek(), Some((_, '\\'))) => break chars.next(),
+                Some((i, BEL)) => break Some((i, BEL)),
+                Some(_) => continue,
+                None => break None,
+            }
+        },
+        // All other sequences consist of a single char after ESC
+        next => next,
+    };
+
+    match end {
+        Some((i, c)) => i + c.len_utf8(),
+        None => input.len(),
+    }
+}
+
+/// Returns the number of columns the input occupies in the terminal. Escape
+/// sequences are not visible and wide chars, like CJK characters, occupy two
+/// columns.
+pub fn visible_width(input: &str) -> usize {
+    strip(input).chars().map(|c| c.width().unwrap_or(0)).sum()
+}
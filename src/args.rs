@@ -1,18 +1,58 @@
-#[derive(Debug, PartialEq)]
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::error::{ReplError, ReplResult};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ParameterError {
+    #[error("No such parameter: {0}")]
+    NoSuchParameter(String),
+
+    #[error("Failed to parse value '{value}' of parameter {name} as {ty}")]
+    ParseError {
+        name: String,
+        value: String,
+        ty: &'static str,
+    },
+
+    #[error("Invalid value '{value}' for {ty}")]
+    InvalidValue { value: String, ty: &'static str },
+
+    /// A custom conversion failed with a message for the user, see
+    /// [`ConvertFrom`].
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("Invalid value '{value}' of parameter {name}: {message}")]
+    InvalidParameter {
+        name: String,
+        value: String,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Arg {
+    short: Option<char>,
     standalone: bool,
+    choices: Vec<String>,
     name: String,
 }
 
 impl PartialEq<String> for Arg {
     fn eq(&self, other: &String) -> bool {
-        &self.name == other
+        self.name == *other
     }
 }
 
 impl PartialEq<str> for Arg {
     fn eq(&self, other: &str) -> bool {
-        &self.name == other
+        self.name == other
     }
 }
 
@@ -23,15 +63,435 @@ impl Arg {
     {
         Self {
             name: name.into(),
+            short: None,
             standalone,
+            choices: Vec::new(),
         }
     }
 
+    /// Adds a single char alias for the argument, like `-v` for `--verbose`.
+    /// The alias uses the first char of the argument prefix, see
+    /// [`ReplBuilder::with_arg_prefix`](crate::builder::ReplBuilder::with_arg_prefix).
+    /// Values are always stored under the long name. Bundled aliases, like
+    /// `-abc`, are not supported.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::args::Arg;
+    /// let arg = Arg::new("verbose", true).with_short('v');
+    /// assert_eq!(arg.short(), Some('v'));
+    /// ```
+    pub fn with_short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    /// Restricts the value of the argument to one of the choices, like
+    /// `tcp` or `udp`. Other values are rejected with
+    /// [`ParserError::InvalidChoice`](crate::ParserError::InvalidChoice),
+    /// which lists the choices, and Tab completes the choices after the
+    /// argument. The usage line shows the choices instead of the name, like
+    /// `[--mode <tcp|udp>]`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::args::Arg;
+    /// let arg = Arg::new("mode", false).one_of(&["tcp", "udp"]);
+    /// assert_eq!(arg.choices(), ["tcp", "udp"]);
+    /// ```
+    pub fn one_of(mut self, choices: &[&str]) -> Self {
+        self.choices = choices.iter().map(|choice| choice.to_string()).collect();
+        self
+    }
+
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    /// Returns the allowed values, see [`Arg::one_of`]. Every value is
+    /// allowed if there are no choices.
+    pub fn choices(&self) -> &[String] {
+        &self.choices
+    }
+
+    /// Returns the placeholder for the value in usage lines, which are the
+    /// choices, like `tcp|udp`, or the name.
+    pub(crate) fn value_name(&self) -> String {
+        match self.choices.is_empty() {
+            true => self.name.clone(),
+            false => self.choices.join("|"),
+        }
+    }
+
+    pub fn short(&self) -> Option<char> {
+        self.short
+    }
+
     pub fn is_standalone(&self) -> bool {
         self.standalone
     }
 }
+
+/// The parameters provided to a command, which are the raw values of the
+/// arguments by name. Values are converted into the requested type with
+/// [`Parameters::get`].
+#[derive(Debug, Default)]
+pub struct Parameters {
+    inner: Vec<(String, String)>,
+    declared: Vec<String>,
+    unknown: Vec<(String, String)>,
+    remainder: usize,
+    input: String,
+}
+
+impl Parameters {
+    pub fn new(args: Vec<(&str, &str)>) -> Self {
+        Self::with_input(args, "", 0)
+    }
+
+    /// Creates the parameters parsed from the input. The remainder of the
+    /// input starts at the byte index `remainder`.
+    pub(crate) fn with_input(args: Vec<(&str, &str)>, input: &str, remainder: usize) -> Self {
+        Self {
+            inner: args
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+            declared: Vec::new(),
+            unknown: Vec::new(),
+            input: input.into(),
+            remainder,
+        }
+    }
+
+    /// Sets the names of all parameters the command declares, including
+    /// the ones which were not provided. [`Parameters::get`] uses them to
+    /// tell omitted parameters from unknown ones. Parameters parsed from the
+    /// input have their declared names set already.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::args::Parameters;
+    /// let params = Parameters::new(vec![]).with_declared(["port"]);
+    ///
+    /// assert_eq!(params.get::<Option<u16>>("port").unwrap(), None);
+    /// assert!(params.get::<Option<u16>>("prot").is_err());
+    /// ```
+    pub fn with_declared<I, N>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = N>,
+        N: Into<String>,
+    {
+        self.declared = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the unknown args which were ignored while parsing.
+    pub(crate) fn with_unknown(mut self, unknown: Vec<(&str, &str)>) -> Self {
+        self.unknown = unknown
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        self
+    }
+
+    /// Returns an iterator over the unknown args, including their prefix,
+    /// and their raw values, which were ignored because strict parsing is
+    /// disabled, see [`Command::with_strict`](crate::command::Command::with_strict).
+    /// The value of an unknown arg is the word following it, unless that is
+    /// prefixed as well, and empty otherwise.
+    pub fn unknown(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.unknown
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns if the parameter was provided, without converting its value.
+    pub fn contains(&self, name: &str) -> bool {
+        self.inner.iter().any(|(n, _)| n == name)
+    }
+
+    /// Returns an iterator over the names and raw values of all provided
+    /// parameters, in the order they were provided.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.inner
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the raw input after the declared parameters. Parameters are
+    /// parsed from the start of the input as long as the next word is the
+    /// name of a declared parameter. Everything starting with the first word
+    /// which is not a declared parameter is the remainder, without further
+    /// tokenization. The remainder is only accepted by commands created
+    /// with [`Command::with_remainder`](crate::command::Command::with_remainder).
+    ///
+    /// For `run --timeout 5 ls -la /tmp`, with `timeout` being the only
+    /// declared parameter of `run`, the remainder is `ls -la /tmp`.
+    pub fn remainder(&self) -> &str {
+        self.input[self.remainder..].trim_start()
+    }
+
+    /// Returns the value of the parameter converted into `T`. Conversions
+    /// failing with [`ParameterError::InvalidValue`] are reported as
+    /// [`ParameterError::ParseError`] and conversions failing with
+    /// [`ParameterError::Invalid`] as [`ParameterError::InvalidParameter`],
+    /// both including the name of the parameter and the value.
+    ///
+    /// If the parameter was not provided, [`ConvertFrom::missing`] is used
+    /// for declared parameters, which is `None` for `Option<T>`. Parameters
+    /// which are not declared at all always result in
+    /// [`ParameterError::NoSuchParameter`].
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::args::Parameters;
+    /// let params = Parameters::new(vec![("port", "53")]);
+    /// let port: u16 = params.get("port").unwrap();
+    ///
+    /// assert_eq!(port, 53);
+    /// ```
+    pub fn get<T>(&self, name: &str) -> ReplResult<T>
+    where
+        T: ConvertFrom<String>,
+    {
+        let value = match self.inner.iter().find(|(n, _)| n == name) {
+            Some((_, value)) => value.clone(),
+            None => {
+                let declared = self.declared.iter().any(|n| n == name);
+
+                return match declared.then(T::missing).flatten() {
+                    Some(value) => Ok(value),
+                    None => Err(ParameterError::NoSuchParameter(name.into()).into()),
+                };
+            }
+        };
+
+        T::convert(value.clone()).map_err(|err| match err {
+            ReplError::ParameterError(ParameterError::InvalidValue { value, ty }) => {
+                ParameterError::ParseError {
+                    name: name.into(),
+                    value,
+                    ty,
+                }
+                .into()
+            }
+            ReplError::ParameterError(ParameterError::Invalid(message)) => {
+                ParameterError::InvalidParameter {
+                    name: name.into(),
+                    value,
+                    message,
+                }
+                .into()
+            }
+            err => err,
+        })
+    }
+
+    /// Converts all parameters into `T` at once. See [`FromParameters`] for
+    /// more information.
+    pub fn parse<T>(&self) -> ReplResult<T>
+    where
+        T: FromParameters,
+    {
+        T::from_parameters(self)
+    }
+}
+
+/// Converts a raw parameter value into a typed value. Conversions of custom
+/// types can fail with [`ParameterError::Invalid`] to tell the user what's
+/// wrong with the value. [`Parameters::get`] adds the name of the parameter
+/// and the value to the message.
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::{args::{ConvertFrom, ParameterError, Parameters}, error::ReplResult};
+/// #[derive(Debug, PartialEq)]
+/// enum Protocol {
+///     Tcp,
+///     Udp,
+/// }
+///
+/// impl ConvertFrom<String> for Protocol {
+///     fn convert(value: String) -> ReplResult<Self> {
+///         match value.as_str() {
+///             "tcp" => Ok(Self::Tcp),
+///             "udp" => Ok(Self::Udp),
+///             _ => Err(ParameterError::Invalid("expected one of: tcp, udp".into()).into()),
+///         }
+///     }
+/// }
+///
+/// let params = Parameters::new(vec![("proto", "udp"), ("fallback", "icmp")]);
+/// assert_eq!(params.get::<Protocol>("proto").unwrap(), Protocol::Udp);
+/// assert_eq!(
+///     params.get::<Protocol>("fallback").unwrap_err().to_string(),
+///     "Parameter error: Invalid value 'icmp' of parameter fallback: expected one of: tcp, udp"
+/// );
+/// ```
+pub trait ConvertFrom<T>: Sized {
+    fn convert(value: T) -> ReplResult<Self>;
+
+    /// Returns the value used for declared parameters which were not
+    /// provided. The default is `None`, which reports the parameter as
+    /// missing.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+impl ConvertFrom<String> for String {
+    fn convert(value: String) -> ReplResult<Self> {
+        Ok(value)
+    }
+}
+
+macro_rules! impl_convert_from_str {
+    ($($ty:ty),*) => {
+        $(
+            impl ConvertFrom<String> for $ty {
+                fn convert(value: String) -> ReplResult<Self> {
+                    <$ty>::from_str(&value).map_err(|_| {
+                        ParameterError::InvalidValue {
+                            value,
+                            ty: stringify!($ty),
+                        }
+                        .into()
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_convert_from_str!(
+    bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, IpAddr,
+    Ipv4Addr, Ipv6Addr
+);
+
+/// Converts durations like `30s`, `5m` or `1h30m`. A duration consists of
+/// one or more whole numbers, each followed by a unit. Supported units are
+/// `d`, `h`, `m`, `s`, `ms`, `us` and `ns`.
+impl ConvertFrom<String> for Duration {
+    fn convert(value: String) -> ReplResult<Self> {
+        match parse_duration(&value) {
+            Some(duration) => Ok(duration),
+            None => Err(ParameterError::InvalidValue {
+                value,
+                ty: "Duration",
+            }
+            .into()),
+        }
+    }
+}
+
+fn parse_duration(input: &str) -> Option<Duration> {
+    if input.is_empty() {
+        return None;
+    }
+
+    let mut duration = Duration::ZERO;
+    let mut rest = input;
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount: u32 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..len] {
+            "d" => Duration::from_secs(24 * 60 * 60),
+            "h" => Duration::from_secs(60 * 60),
+            "m" => Duration::from_secs(60),
+            "s" => Duration::from_secs(1),
+            "ms" => Duration::from_millis(1),
+            "us" => Duration::from_micros(1),
+            "ns" => Duration::from_nanos(1),
+            _ => return None,
+        };
+        rest = &rest[len..];
+
+        duration = duration.checked_add(unit.checked_mul(amount)?)?;
+    }
+
+    Some(duration)
+}
+
+/// Converts comma-separated values, like `80,443,8080`, by converting each
+/// element into `T`. Whitespace around elements is ignored and an empty
+/// value results in an empty list. The first element failing to convert is
+/// reported.
+impl<T> ConvertFrom<String> for Vec<T>
+where
+    T: ConvertFrom<String>,
+{
+    fn convert(value: String) -> ReplResult<Self> {
+        if value.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        value
+            .split(',')
+            .map(|element| T::convert(element.trim().to_string()))
+            .collect()
+    }
+}
+
+/// Converts optional values. An empty value and an omitted parameter both
+/// result in `None`, every other value is converted into `T`.
+impl<T> ConvertFrom<String> for Option<T>
+where
+    T: ConvertFrom<String>,
+{
+    fn convert(value: String) -> ReplResult<Self> {
+        match value.is_empty() {
+            true => Ok(None),
+            false => T::convert(value).map(Some),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+/// Converts all parameters of a command into a single value, usually a
+/// struct with one field per parameter. Implementations are retrieved with
+/// [`Parameters::parse`].
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::{args::{FromParameters, Parameters}, error::ReplResult};
+/// struct HelloArgs {
+///     name: String,
+///     times: u8,
+/// }
+///
+/// impl FromParameters for HelloArgs {
+///     fn from_parameters(params: &Parameters) -> ReplResult<Self> {
+///         Ok(Self {
+///             name: params.get("name")?,
+///             times: params.get("times")?,
+///         })
+///     }
+/// }
+///
+/// let params = Parameters::new(vec![("name", "rupl"), ("times", "3")]);
+/// let args: HelloArgs = params.parse().unwrap();
+///
+/// assert_eq!(args.name, "rupl");
+/// assert_eq!(args.times, 3);
+/// ```
+pub trait FromParameters: Sized {
+    fn from_parameters(params: &Parameters) -> ReplResult<Self>;
+}
@@ -1,4 +1,7 @@
-use std::fmt::{Display, Write};
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Write},
+};
 
 use thiserror::Error;
 
@@ -9,6 +12,12 @@ pub enum BufferError {
 
     #[error("Deleting at {at} with count {count} overflows buf len")]
     DeleteCountOverflow { at: usize, count: usize },
+
+    #[error("Deleting {count} chars before {at} underflows buf start")]
+    DeleteCountUnderflow { at: usize, count: usize },
+
+    #[error("Invalid range {from}..{to}, must be within buf len")]
+    InvalidRange { from: usize, to: usize },
 }
 
 #[derive(Debug, Default)]
@@ -46,6 +55,10 @@ impl Buffer {
         self.to_string().into_bytes()
     }
 
+    pub fn get(&self, at: usize) -> Option<char> {
+        self.buf.get(at).copied()
+    }
+
     pub fn insert(&mut self, at: usize, chars: &[char]) -> Result<(), BufferError> {
         if at > self.len() {
             return Err(BufferError::InvalidStartIndex);
@@ -73,6 +86,21 @@ impl Buffer {
     pub fn remove_from_to(&mut self, at: usize, to: usize) -> Result<Vec<char>, BufferError> {
         Ok(self.buf.drain(at..to).collect())
     }
+
+    /// Replaces the chars in the range `from..to` with the provided chars.
+    /// The replacement can have a different length than the range.
+    pub fn replace_range(
+        &mut self,
+        from: usize,
+        to: usize,
+        chars: &[char],
+    ) -> Result<Vec<char>, BufferError> {
+        if from > to || to > self.len() {
+            return Err(BufferError::InvalidRange { from, to });
+        }
+
+        Ok(self.buf.splice(from..to, chars.iter().copied()).collect())
+    }
 }
 
 pub enum Direction {
@@ -80,15 +108,101 @@ pub enum Direction {
     Right,
 }
 
+/// The key bindings used to edit the input, see
+/// [`ReplBuilder::with_edit_mode`](crate::builder::ReplBuilder::with_edit_mode).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum EditMode {
+    /// Keys insert chars and the `Ctrl` and `Alt` bindings edit the input.
+    #[default]
+    Emacs,
+
+    /// Like [`EditMode::Emacs`] in insert mode. `Esc` switches to normal
+    /// mode, where keys move the cursor and edit the input, see [`ViMode`].
+    Vi,
+}
+
+/// The current mode of the vi key bindings, see [`EditMode::Vi`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViMode {
+    /// Keys insert chars. `Esc` switches to normal mode.
+    Insert,
+
+    /// The cursor stays on a char and keys are commands:
+    ///
+    /// - `h` and `l` move the cursor left and right.
+    /// - `w` and `b` move the cursor to the start of the next and previous
+    ///   word.
+    /// - `0` and `$` move the cursor to the start and end of the line.
+    /// - `i` and `a` switch to insert mode before and after the cursor.
+    /// - `x` deletes the char under the cursor.
+    /// - `k` and `j` recall the previous and next history entry, like Up and
+    ///   Down.
+    /// - `/` starts a history search, see [`ViMode::Search`].
+    ///
+    /// Enter submits the line and the next line starts in insert mode.
+    Normal,
+
+    /// Keys type the pattern of a history search. The line shows `/` and the
+    /// pattern instead of the prompt and the input. Enter recalls the most
+    /// recent history entry containing the pattern and switches back to
+    /// normal mode, where `k` and `j` continue from the recalled entry.
+    /// `Esc`, or Backspace on an empty pattern, cancels the search.
+    Search,
+}
+
+/// Defines which chars make up a word when moving the cursor by words or
+/// deleting words.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum WordStyle {
+    /// Words are separated by whitespace only, so `service.dns` is a single
+    /// word.
+    Whitespace,
+
+    /// Words consist of alphanumeric chars, everything else separates them.
+    #[default]
+    AlphaNumeric,
+
+    /// Words consist of all chars for which the predicate returns true.
+    Custom(fn(char) -> bool),
+}
+
+impl WordStyle {
+    /// Returns if the char is part of a word.
+    pub fn is_word_char(&self, c: char) -> bool {
+        match self {
+            Self::Whitespace => !c.is_whitespace(),
+            Self::AlphaNumeric => c.is_alphanumeric(),
+            Self::Custom(predicate) => predicate(c),
+        }
+    }
+}
+
+/// The maximum number of edits which can be undone.
+const UNDO_DEPTH: usize = 100;
+
+/// A single edit, which replaced the `removed` chars at `at` with the
+/// `inserted` chars. It contains everything needed to invert it.
+#[derive(Debug)]
+struct Edit {
+    at: usize,
+    removed: Vec<char>,
+    inserted: Vec<char>,
+    cursor_before: usize,
+    cursor_after: usize,
+}
+
 #[derive(Debug, Default)]
 pub struct CursorBuffer {
+    word_style: WordStyle,
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
     cur_pos: usize,
     buf: Buffer,
 }
 
 impl Display for CursorBuffer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.buf.to_string())
+        write!(f, "{}", self.buf)
     }
 }
 
@@ -97,6 +211,21 @@ impl CursorBuffer {
         Self::default()
     }
 
+    /// Sets the [`WordStyle`] used by all word based operations.
+    pub fn with_word_style(mut self, word_style: WordStyle) -> Self {
+        self.word_style = word_style;
+        self
+    }
+
+    /// Sets the initial content and moves the cursor to the end. Unlike
+    /// [`CursorBuffer::set_contents`], this can't be undone.
+    pub fn with_contents(mut self, text: &str) -> Self {
+        // Replacing the whole content never fails
+        let _ = self.set_contents(text);
+        self.undo.clear();
+        self
+    }
+
     pub fn len(&self) -> usize {
         self.buf.len()
     }
@@ -105,8 +234,11 @@ impl CursorBuffer {
         self.buf.is_empty()
     }
 
+    /// Clears the buffer and the undo history.
     pub fn clear(&mut self) {
         self.buf.clear();
+        self.undo.clear();
+        self.redo.clear();
         self.cur_pos = 0;
     }
 
@@ -132,36 +264,345 @@ impl CursorBuffer {
         false
     }
 
+    pub fn move_to_start(&mut self) -> bool {
+        if self.cur_pos > 0 {
+            self.cur_pos = 0;
+            return true;
+        }
+
+        false
+    }
+
+    pub fn move_to_end(&mut self) -> bool {
+        if self.cur_pos < self.buf.len() {
+            self.cur_pos = self.buf.len();
+            return true;
+        }
+
+        false
+    }
+
     pub fn get_pos(&self) -> usize {
         self.cur_pos
     }
 
+    /// Returns the char under the cursor.
+    pub fn current(&self) -> Option<char> {
+        self.buf.get(self.cur_pos)
+    }
+
+    /// Returns the char before the cursor.
+    pub fn previous(&self) -> Option<char> {
+        self.cur_pos
+            .checked_sub(1)
+            .and_then(|pos| self.buf.get(pos))
+    }
+
     pub fn insert(&mut self, chars: &[char]) -> Result<(), BufferError> {
-        self.buf.insert(self.cur_pos, chars)?;
-        self.cur_pos += chars.len();
+        let pos = self.cur_pos;
+        self.splice(pos, pos, chars, pos + chars.len())?;
         Ok(())
     }
 
+    /// Removes the char before or after the cursor. Removing to the left at
+    /// the start of the buffer fails with [`BufferError::DeleteCountUnderflow`].
     pub fn remove_one(&mut self, dir: Direction) -> Result<Vec<char>, BufferError> {
+        self.remove_many(1, dir)
+    }
+
+    /// Removes `count` chars before or after the cursor. Removing more chars
+    /// to the left than there are before the cursor fails with
+    /// [`BufferError::DeleteCountUnderflow`] and leaves the buffer as is.
+    pub fn remove_many(&mut self, count: usize, dir: Direction) -> Result<Vec<char>, BufferError> {
         match dir {
             Direction::Left => {
-                let chars = self.buf.remove(self.cur_pos - 1, 1)?;
-                self.cur_pos -= 1;
-                Ok(chars)
+                let at =
+                    self.cur_pos
+                        .checked_sub(count)
+                        .ok_or(BufferError::DeleteCountUnderflow {
+                            at: self.cur_pos,
+                            count,
+                        })?;
+                self.remove_at(at, count, at)
             }
-            Direction::Right => self.buf.remove(self.cur_pos, 1),
+            Direction::Right => self.remove_at(self.cur_pos, count, self.cur_pos),
         }
     }
 
-    pub fn remove_many(&mut self, count: usize, dir: Direction) -> Result<Vec<char>, BufferError> {
-        match dir {
-            Direction::Left => {
-                let chars = self.buf.remove(self.cur_pos - count, count)?;
-                self.cur_pos -= chars.len();
-                Ok(chars)
+    /// Reverts the last edit and restores the cursor position from before
+    /// the edit. Consecutively typed chars are reverted together, starting
+    /// a new group at every whitespace following a word. Returns if an edit
+    /// was reverted.
+    pub fn undo(&mut self) -> Result<bool, BufferError> {
+        let edit = match self.undo.pop_back() {
+            Some(edit) => edit,
+            None => return Ok(false),
+        };
+
+        let to = edit.at + edit.inserted.len();
+        self.buf.replace_range(edit.at, to, &edit.removed)?;
+        self.cur_pos = edit.cursor_before;
+        self.redo.push(edit);
+
+        Ok(true)
+    }
+
+    /// Reapplies the last reverted edit. Any new edit after an undo discards
+    /// the reverted edits. Returns if an edit was reapplied.
+    pub fn redo(&mut self) -> Result<bool, BufferError> {
+        let edit = match self.redo.pop() {
+            Some(edit) => edit,
+            None => return Ok(false),
+        };
+
+        let to = edit.at + edit.removed.len();
+        self.buf.replace_range(edit.at, to, &edit.inserted)?;
+        self.cur_pos = edit.cursor_after;
+        self.undo.push_back(edit);
+
+        Ok(true)
+    }
+
+    /// Replaces the whole content with the text and moves the cursor to the
+    /// end. The replacement can be undone like any other edit.
+    pub fn set_contents(&mut self, text: &str) -> Result<(), BufferError> {
+        let chars: Vec<char> = text.chars().collect();
+        self.splice(0, self.len(), &chars, chars.len())?;
+        Ok(())
+    }
+
+    /// Swaps the char before the cursor with the char under the cursor and
+    /// moves the cursor right. At the end of the input, the last two chars
+    /// are swapped instead. Nothing is swapped if there are fewer than two
+    /// chars or the cursor is at the start. Returns if chars were swapped.
+    pub fn transpose(&mut self) -> Result<bool, BufferError> {
+        if self.len() < 2 || self.cur_pos == 0 {
+            return Ok(false);
+        }
+
+        let pos = self.cur_pos.min(self.len() - 1);
+        let (a, b) = match (self.buf.get(pos - 1), self.buf.get(pos)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Ok(false),
+        };
+
+        self.splice(pos - 1, pos + 1, &[b, a], pos + 1)?;
+        Ok(true)
+    }
+
+    /// Returns the position after the end of the word under the cursor. If
+    /// the cursor is not on a word, the end of the next word is returned.
+    pub fn next_word_end(&self) -> usize {
+        let mut pos = self.cur_pos;
+
+        while self.buf.get(pos).is_some_and(|c| !self.is_word_char(c)) {
+            pos += 1;
+        }
+
+        while self.buf.get(pos).is_some_and(|c| self.is_word_char(c)) {
+            pos += 1;
+        }
+
+        pos
+    }
+
+    /// Returns the position of the start of the next word. If there is no
+    /// next word, the end of the buffer is returned.
+    pub fn next_word_start(&self) -> usize {
+        let mut pos = self.cur_pos;
+
+        while self.buf.get(pos).is_some_and(|c| self.is_word_char(c)) {
+            pos += 1;
+        }
+
+        while self.buf.get(pos).is_some_and(|c| !self.is_word_char(c)) {
+            pos += 1;
+        }
+
+        pos
+    }
+
+    /// Returns the position of the start of the word before the cursor. If
+    /// the cursor is inside a word, the start of this word is returned.
+    pub fn previous_word_start(&self) -> usize {
+        let mut pos = self.cur_pos;
+
+        while pos > 0 && self.buf.get(pos - 1).is_some_and(|c| !self.is_word_char(c)) {
+            pos -= 1;
+        }
+
+        while pos > 0 && self.buf.get(pos - 1).is_some_and(|c| self.is_word_char(c)) {
+            pos -= 1;
+        }
+
+        pos
+    }
+
+    /// Moves the cursor to the start of the previous word.
+    pub fn move_word_left(&mut self) -> bool {
+        let pos = self.previous_word_start();
+        let moved = pos != self.cur_pos;
+        self.cur_pos = pos;
+        moved
+    }
+
+    /// Moves the cursor to the start of the next word.
+    pub fn move_next_word_start(&mut self) -> bool {
+        let pos = self.next_word_start();
+        let moved = pos != self.cur_pos;
+        self.cur_pos = pos;
+        moved
+    }
+
+    /// Moves the cursor to the end of the next word.
+    pub fn move_word_right(&mut self) -> bool {
+        let pos = self.next_word_end();
+        let moved = pos != self.cur_pos;
+        self.cur_pos = pos;
+        moved
+    }
+
+    /// Removes the chars from the start of the previous word to the cursor.
+    pub fn remove_word_left(&mut self) -> Result<Vec<char>, BufferError> {
+        let count = self.cur_pos - self.previous_word_start();
+        self.remove_many(count, Direction::Left)
+    }
+
+    /// Removes the chars from the cursor to the end of the next word.
+    pub fn remove_word_right(&mut self) -> Result<Vec<char>, BufferError> {
+        let count = self.next_word_end() - self.cur_pos;
+        self.remove_many(count, Direction::Right)
+    }
+
+    /// Converts the word from the cursor onwards to uppercase and moves the
+    /// cursor past it.
+    pub fn upcase_word(&mut self) -> Result<(), BufferError> {
+        self.transform_word(|word| word.to_uppercase())
+    }
+
+    /// Converts the word from the cursor onwards to lowercase and moves the
+    /// cursor past it.
+    pub fn downcase_word(&mut self) -> Result<(), BufferError> {
+        self.transform_word(|word| word.to_lowercase())
+    }
+
+    /// Converts the first char of the word from the cursor onwards to
+    /// uppercase and the rest to lowercase and moves the cursor past it.
+    pub fn capitalize_word(&mut self) -> Result<(), BufferError> {
+        let word_style = self.word_style;
+
+        self.transform_word(|word| {
+            let mut chars = word.chars();
+            let mut capitalized = String::new();
+
+            // Skip any leading non-word chars before the word
+            for c in chars.by_ref() {
+                if word_style.is_word_char(c) {
+                    capitalized.extend(c.to_uppercase());
+                    break;
+                }
+
+                capitalized.push(c);
+            }
+
+            capitalized.extend(chars.flat_map(char::to_lowercase));
+            capitalized
+        })
+    }
+
+    /// Replaces the chars from the cursor to the end of the word with the
+    /// transformed chars. The cursor is placed after the transformed word,
+    /// which can be longer than the original one, e.g. `ß` becomes `SS`.
+    fn transform_word<F>(&mut self, transform: F) -> Result<(), BufferError>
+    where
+        F: FnOnce(String) -> String,
+    {
+        let end = self.next_word_end();
+        let word: String = (self.cur_pos..end)
+            .filter_map(|i| self.buf.get(i))
+            .collect();
+
+        let transformed: Vec<char> = transform(word).chars().collect();
+        let cursor = self.cur_pos + transformed.len();
+        self.splice(self.cur_pos, end, &transformed, cursor)?;
+
+        Ok(())
+    }
+
+    /// Removes `count` chars at `at` and places the cursor at `cursor`.
+    fn remove_at(
+        &mut self,
+        at: usize,
+        count: usize,
+        cursor: usize,
+    ) -> Result<Vec<char>, BufferError> {
+        if at > self.len() {
+            return Err(BufferError::InvalidStartIndex);
+        }
+
+        if at + count > self.len() {
+            return Err(BufferError::DeleteCountOverflow { at, count });
+        }
+
+        self.splice(at, at + count, &[], cursor)
+    }
+
+    /// Replaces the chars in the range `from..to`, places the cursor at
+    /// `cursor` and records the edit, so that it can be undone. All edits
+    /// of the buffer go through this function.
+    fn splice(
+        &mut self,
+        from: usize,
+        to: usize,
+        chars: &[char],
+        cursor: usize,
+    ) -> Result<Vec<char>, BufferError> {
+        let removed = self.buf.replace_range(from, to, chars)?;
+        let cursor_before = self.cur_pos;
+        self.cur_pos = cursor;
+
+        if removed != chars {
+            self.record(Edit {
+                removed: removed.clone(),
+                inserted: chars.to_vec(),
+                cursor_after: cursor,
+                cursor_before,
+                at: from,
+            });
+        }
+
+        Ok(removed)
+    }
+
+    /// Pushes the edit onto the undo stack. Typed chars directly following
+    /// the previous insertion are merged into it, unless a new word starts.
+    fn record(&mut self, edit: Edit) {
+        self.redo.clear();
+
+        if let Some(last) = self.undo.back_mut() {
+            let starts_word = edit.inserted.first().is_some_and(|c| c.is_whitespace())
+                && last.inserted.last().is_some_and(|c| !c.is_whitespace());
+
+            if last.removed.is_empty()
+                && edit.removed.is_empty()
+                && last.at + last.inserted.len() == edit.at
+                && !starts_word
+            {
+                last.inserted.extend(edit.inserted);
+                last.cursor_after = edit.cursor_after;
+                return;
             }
-            Direction::Right => self.buf.remove(self.cur_pos, count),
         }
+
+        if self.undo.len() == UNDO_DEPTH {
+            self.undo.pop_front();
+        }
+
+        self.undo.push_back(edit);
+    }
+
+    fn is_word_char(&self, c: char) -> bool {
+        self.word_style.is_word_char(c)
     }
 }
 
@@ -181,11 +622,24 @@ impl OutputBuffer {
         }
     }
 
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
     pub fn add_to_buffer<T: AsRef<str>>(&mut self, output: T) {
         self.buffer.push_str(output.as_ref())
     }
 
-    pub fn output(&self, clear_line: bool, cursor_position: usize) -> String {
+    /// Renders the prefix, the buffer and the suffix. If `clear_line` is
+    /// set, the current line of the terminal is cleared first, otherwise the
+    /// output is written over the existing text. The cursor is placed at
+    /// `cursor_column` afterwards, counted from the start of the line. The
+    /// column must be computed on the visible text, as the prefix and the
+    /// buffer may contain invisible escape codes.
+    ///
+    /// Prefer [`OutputBuffer::render_with_prompt`] and
+    /// [`OutputBuffer::render_plain`], which describe the intent.
+    pub fn render(&self, clear_line: bool, cursor_column: usize) -> String {
         let mut output = String::new();
 
         // Optionally clear current line
@@ -203,14 +657,39 @@ impl OutputBuffer {
         output.push_str(&self.suffix);
 
         // Position the cursor correctly again
-        let diff = self.buffer.len() - cursor_position;
-        if diff != 0 {
-            output.push_str(&termion::cursor::Left(diff as u16).to_string());
+        output.push('\r');
+        if cursor_column != 0 {
+            output.push_str(&termion::cursor::Right(cursor_column as u16).to_string());
         }
 
         output
     }
 
+    /// Replaces the current line of the terminal with the prefix, the buffer
+    /// and the suffix, and places the cursor at `cursor_column`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::buffer::OutputBuffer;
+    /// let mut buffer = OutputBuffer::new(">> ".into(), "".into());
+    /// buffer.add_to_buffer("hello");
+    ///
+    /// assert_eq!(
+    ///     buffer.render_with_prompt(0),
+    ///     format!("{}\r>> hello\r", termion::clear::CurrentLine)
+    /// );
+    /// ```
+    pub fn render_with_prompt(&self, cursor_column: usize) -> String {
+        self.render(true, cursor_column)
+    }
+
+    /// Renders the prefix, the buffer and the suffix without clearing the
+    /// current line. The cursor is moved to the start of the line.
+    pub fn render_plain(&self) -> String {
+        self.render(false, 0)
+    }
+
     pub fn newline(&self) -> String {
         format!("\r\n{}", self.prefix)
     }
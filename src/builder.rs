@@ -1,36 +1,138 @@
-use std::{collections::HashMap, io};
+use std::{
+    collections::{HashMap, VecDeque},
+    io::Write,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use termion::raw::IntoRawMode;
+use termion::event::Key;
 
 use crate::{
-    buffer::{CursorBuffer, OutputBuffer},
-    Command, Repl,
+    buffer::{CursorBuffer, EditMode, OutputBuffer, ViMode, WordStyle},
+    builtin::Builtin,
+    completion::CompletionStyle,
+    cursor::CursorShape,
+    error::{ReplError, ReplResult},
+    format::{DefaultFormatter, Messages, OutputFormatter},
+    history::{History, HistorySearchMode, LongHistoryEntry},
+    output::OutputQueue,
+    prompt::Prompts,
+    terminal::Terminal,
+    transcript::Transcript,
+    translate::{English, Translator},
+    vars::{UnknownVariable, Variables},
+    Command, ErrorFormatter, Evaluator, Highlighter, Repl, ReplStats, StateReset, Tick, Validator,
+    DEFAULT_RECENT_OUTPUTS,
 };
 
 pub struct ReplBuilder<'a, S> {
+    arg_prefix: String,
     commands: HashMap<String, Command<S>>,
+    comment_char: Option<char>,
+    completion_style: CompletionStyle,
+    cursor_shape: Option<CursorShape>,
+    finder_key: Option<Key>,
+    continuation_char: Option<char>,
+    disabled_builtins: Vec<Builtin>,
+    highlighter: Option<Highlighter>,
+    history_search: HistorySearchMode,
+    live_validator: Option<Validator>,
+    long_history_entry: LongHistoryEntry,
+    max_history_entry_length: Option<usize>,
+    submit_validator: Option<Validator>,
     ignore_empty_line: bool,
+    inline_output: bool,
+    use_prompt_markers: bool,
+    edit_mode: EditMode,
+    output_formatter: Box<dyn OutputFormatter>,
+    error_formatter: Option<ErrorFormatter>,
+    translator: Box<dyn Translator>,
     welcome_message: String,
-    output_prompt: String,
     exit_message: String,
+    idle_timeout: Option<Duration>,
+    on_tick: Option<(Duration, Tick<S>)>,
+    transcript_file: Option<PathBuf>,
+    use_auto_pairs: bool,
+    use_autosuggest: bool,
     use_builtins: bool,
+    use_chaining: bool,
+    use_echo_commands: bool,
+    use_exec_welcome: bool,
+    use_fuzzy_matching: bool,
+    use_help_flags: bool,
+    use_pipes: bool,
+    use_prefix_matching: bool,
+    use_strict_args: bool,
+    use_transcript_timestamps: bool,
+    use_variable_expansion: bool,
+    unknown_variable: UnknownVariable,
+    word_style: WordStyle,
+    initial_input: String,
     state: &'a mut S,
+    state_reset: Option<StateReset<S>>,
+    evaluator: Option<Evaluator<S>>,
+    command_prefix: Option<char>,
+    use_evaluator_fallback: bool,
+    tab_inserts_spaces: Option<usize>,
     version: String,
-    prompt: String,
+    prompts: Prompts,
+    recent_outputs: usize,
 }
 
 impl<'a, S> ReplBuilder<'a, S> {
     pub fn new(state: &'a mut S) -> Self {
         Self {
+            arg_prefix: String::from("--"),
             version: String::from(env!("CARGO_PKG_VERSION")),
             welcome_message: String::new(),
-            output_prompt: String::new(),
+            output_formatter: Box::new(DefaultFormatter),
+            error_formatter: None,
+            translator: Box::new(English),
             exit_message: String::new(),
-            prompt: String::from(">> "),
+            idle_timeout: None,
+            on_tick: None,
+            transcript_file: None,
+            prompts: Prompts::default(),
+            recent_outputs: DEFAULT_RECENT_OUTPUTS,
             commands: HashMap::new(),
+            comment_char: Some('#'),
+            completion_style: CompletionStyle::default(),
+            cursor_shape: None,
+            finder_key: Some(Key::Ctrl('p')),
+            continuation_char: Some('\\'),
+            disabled_builtins: Vec::new(),
+            highlighter: None,
+            history_search: HistorySearchMode::default(),
+            live_validator: None,
+            long_history_entry: LongHistoryEntry::default(),
+            max_history_entry_length: None,
+            submit_validator: None,
             ignore_empty_line: true,
+            inline_output: false,
+            use_prompt_markers: false,
+            edit_mode: EditMode::default(),
+            use_auto_pairs: false,
+            use_autosuggest: false,
             use_builtins: true,
+            use_chaining: false,
+            use_echo_commands: false,
+            use_exec_welcome: false,
+            use_fuzzy_matching: false,
+            use_help_flags: true,
+            use_pipes: false,
+            use_prefix_matching: false,
+            use_strict_args: true,
+            use_transcript_timestamps: false,
+            use_variable_expansion: false,
+            unknown_variable: UnknownVariable::default(),
+            word_style: WordStyle::default(),
+            initial_input: String::new(),
             state,
+            state_reset: None,
+            evaluator: None,
+            command_prefix: None,
+            use_evaluator_fallback: false,
+            tab_inserts_spaces: None,
         }
     }
 
@@ -42,23 +144,27 @@ impl<'a, S> ReplBuilder<'a, S> {
     /// ### Example
     ///
     /// ```no_run
-    /// let repl = Repl::builder().with_prompt("#");
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_prompt("#");
     /// ```
     pub fn with_prompt<P>(mut self, prompt: P) -> Self
     where
         P: Into<String>,
     {
-        self.prompt = prompt.into().trim_end().to_string() + " ";
+        self.prompts.primary = prompt.into().trim_end().to_string() + " ";
         self
     }
 
     /// Adds a welcome message which gets printed once at the start of the
-    /// REPL.
+    /// REPL, before the first prompt.
     ///
     /// ### Example
     ///
     /// ```no_run
-    /// let repl = Repl::builder(()).with_welcome_message("Welcome from your REPL!");
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_welcome_message("Welcome from your REPL!");
     /// ```
     pub fn with_welcome_message<M>(mut self, message: M) -> Self
     where
@@ -68,12 +174,55 @@ impl<'a, S> ReplBuilder<'a, S> {
         self
     }
 
+    /// Enables or disables printing the welcome message before the output of
+    /// [`Repl::exec_and_exit`]. One-shot runs are usually part of scripts,
+    /// so the welcome message is not printed by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_welcome_message("Welcome from your REPL!")
+    ///     .with_exec_welcome(true);
+    /// ```
+    pub fn with_exec_welcome(mut self, use_exec_welcome: bool) -> Self {
+        self.use_exec_welcome = use_exec_welcome;
+        self
+    }
+
+    /// Sets the text the input line starts with, with the cursor at its end,
+    /// e.g. to suggest a command in a tutorial. It is drawn with the first
+    /// prompt and can be edited like typed text. Pressing Enter right away
+    /// submits it. See [`Repl::set_input`] to replace the input later on.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_initial_input("service dns status");
+    /// ```
+    pub fn with_initial_input<I>(mut self, input: I) -> Self
+    where
+        I: Into<String>,
+    {
+        self.initial_input = input.into();
+        self
+    }
+
     /// Adds an exit message which gets printed when the user exists the REPL.
+    /// It is printed when the session times out as well, see
+    /// [`ReplBuilder::with_idle_timeout`], and with the `signals` feature
+    /// when the REPL exits on `SIGTERM`.
     ///
     /// ### Example
     ///
     /// ```no_run
-    /// let repl = Repl::builder(()).with_exit_message("Exiting... Bye!");
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_exit_message("Exiting... Bye!");
     /// ```
     pub fn with_exit_message<M>(mut self, message: M) -> Self
     where
@@ -83,13 +232,101 @@ impl<'a, S> ReplBuilder<'a, S> {
         self
     }
 
+    /// Exits the REPL after the duration without any input, e.g. to end
+    /// unattended sessions of a privileged REPL. On timeout, a notice is
+    /// printed followed by the exit message, the terminal is restored and
+    /// [`Repl::run`] returns. There is no timeout by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_idle_timeout(Duration::from_secs(300));
+    /// ```
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a function which is called with the state every time the
+    /// interval elapsed while the REPL waits for input, e.g. to refresh
+    /// timers or collect results of asynchronous work. Ticks run between
+    /// key presses, never while a command executes, so the interval is a
+    /// lower bound. There is no tick function by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use std::time::{Duration, Instant};
+    /// # use rupl::Repl;
+    /// let mut last_seen = Instant::now();
+    /// let repl = Repl::builder(&mut last_seen)
+    ///     .with_on_tick(Duration::from_secs(1), |last_seen| *last_seen = Instant::now());
+    /// ```
+    pub fn with_on_tick<T>(mut self, interval: Duration, tick: T) -> Self
+    where
+        T: Fn(&mut S) + 'static,
+    {
+        self.on_tick = Some((interval, Box::new(tick)));
+        self
+    }
+
+    /// Appends a plain-text transcript of the session to the file, e.g. for
+    /// auditing. Every submitted input line is written with the prompt,
+    /// followed by its outputs and the messages printed with
+    /// [`Repl::notify`]. Escape sequences are removed. The file is created
+    /// if it doesn't exist.
+    ///
+    /// If writing the transcript fails, the error is printed once after the
+    /// outputs of the line, or after the message of [`Repl::notify`], and the
+    /// transcript is disabled for the rest of the session. A failed write
+    /// never ends the session.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_transcript_file("session.log");
+    /// ```
+    pub fn with_transcript_file<P>(mut self, path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.transcript_file = Some(path.into());
+        self
+    }
+
+    /// Prefixes every line of the transcript with an UTC timestamp, like
+    /// `[2024-03-01T12:00:00Z] `, see [`ReplBuilder::with_transcript_file`].
+    /// Timestamps are disabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_transcript_file("session.log")
+    ///     .with_transcript_timestamps(true);
+    /// ```
+    pub fn with_transcript_timestamps(mut self, use_transcript_timestamps: bool) -> Self {
+        self.use_transcript_timestamps = use_transcript_timestamps;
+        self
+    }
+
     /// Adds a version string to the REPL. When builtin commands are enabled,
-    /// the version can be printed with the `version` command.
+    /// the version can be printed with the `version` command, see
+    /// [`Builtin::Version`]. Defaults to the version of this crate.
     ///
     /// ### Example
     ///
     /// ```no_run
-    /// let repl = Repl::builder(()).with_version("1.3.4");
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_version("1.3.4");
     /// ```
     pub fn with_version<V>(mut self, version: V) -> Self
     where
@@ -104,78 +341,911 @@ impl<'a, S> ReplBuilder<'a, S> {
     /// ### Example
     ///
     /// ```no_run
-    /// let repl = Repl::builder(()).ignore_empty_line(true);
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).ignore_empty_line(true);
     /// ```
     pub fn ignore_empty_line(mut self, ignore: bool) -> Self {
         self.ignore_empty_line = ignore;
         self
     }
 
-    /// Set the output prompt. When [`Some`] is provided, this value will be
-    /// used as the output prompt. Providing [`None`] will instead fallback to
-    /// the input prompt. Disabling the output prompt can be achieved by
-    /// providing `Some("")`.
+    /// Sets if the output of a command should replace the submitted input
+    /// line. By default, a newline is started after the input and the output
+    /// is written below it:
+    ///
+    /// ```text
+    /// >> service dns
+    /// # Hello from service_dns
+    /// >>
+    /// ```
+    ///
+    /// With inline output, the input line is cleared and reused for the
+    /// output:
+    ///
+    /// ```text
+    /// # Hello from service_dns
+    /// >>
+    /// ```
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_inline_output(true);
+    /// ```
+    pub fn with_inline_output(mut self, inline_output: bool) -> Self {
+        self.inline_output = inline_output;
+        self
+    }
+
+    /// Sets if prompts and command output are wrapped in OSC 133 shell
+    /// integration marks. Terminals and editors which support the marks can
+    /// detect prompt boundaries, e.g. to jump between commands or to select
+    /// the output of a command. Every prompt is marked as the start of a
+    /// prompt (`133;A`) followed by the start of the input (`133;B`). The
+    /// output of a submitted line starts with `133;C` and ends with `133;D`
+    /// and the exit code, which is `1` if any command of the line failed and
+    /// `0` otherwise. The marks are disabled by default.
     ///
     /// ### Example
     ///
     /// ```no_run
-    /// let repl = Repl::builder(()).with_output_prompt(Some("#"));
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_prompt_markers(true);
+    /// ```
+    pub fn with_prompt_markers(mut self, use_prompt_markers: bool) -> Self {
+        self.use_prompt_markers = use_prompt_markers;
+        self
+    }
+
+    /// Set the output prompt which appears in front of every output line. The
+    /// default is no output prompt. Like [`ReplBuilder::with_prompt`], a
+    /// space is added to the end of the prompt.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_output_prompt("#");
     /// ```
     pub fn with_output_prompt<P>(mut self, prompt: P) -> Self
     where
         P: Into<String>,
     {
-        self.output_prompt = prompt.into().trim_end().to_string() + " ";
+        self.prompts.output = prompt.into().trim_end().to_string() + " ";
+        self
+    }
+
+    /// Sets the [`OutputFormatter`] which turns the result of a command into
+    /// the text written to the terminal. The default is [`DefaultFormatter`],
+    /// which writes the output prompt followed by the output or the error
+    /// message.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{error::ReplResult, format::{FormatContext, OutputFormatter}, Repl};
+    /// struct Uppercase;
+    ///
+    /// impl OutputFormatter for Uppercase {
+    ///     fn format(
+    ///         &self,
+    ///         _command: Option<&str>,
+    ///         result: &ReplResult<Option<String>>,
+    ///         _ctx: &FormatContext<'_>,
+    ///     ) -> String {
+    ///         match result {
+    ///             Ok(output) => output.clone().unwrap_or_default().to_uppercase(),
+    ///             Err(err) => format!("error: {err}"),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_output_formatter(Uppercase);
+    /// ```
+    pub fn with_output_formatter<F>(mut self, formatter: F) -> Self
+    where
+        F: OutputFormatter + 'static,
+    {
+        self.output_formatter = Box::new(formatter);
+        self
+    }
+
+    /// Sets a function which renders the message of a failed command, e.g.
+    /// to prefix it with a colored `error:` or to map specific errors to
+    /// custom texts. The [`OutputFormatter`] receives the rendered message
+    /// with [`FormatContext::error_message`](crate::format::FormatContext::error_message),
+    /// so the output prompt and suggestions still apply. By default, the
+    /// translated message of the error is used, see
+    /// [`ReplBuilder::with_translator`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// use termion::color;
+    ///
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_error_formatter(|err| {
+    ///     format!("{}error:{} {}", color::Fg(color::Red), color::Fg(color::Reset), err)
+    /// });
+    /// ```
+    pub fn with_error_formatter<F>(mut self, formatter: F) -> Self
+    where
+        F: Fn(&ReplError) -> String + 'static,
+    {
+        self.error_formatter = Some(Box::new(formatter));
+        self
+    }
+
+    /// Sets the messages written for common errors, like unknown commands
+    /// or invalid arguments. This is a shorthand for
+    /// [`ReplBuilder::with_translator`], which translates all other texts
+    /// into English.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{format::Messages, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_messages(Messages {
+    ///     unknown_command: "No idea what you mean".into(),
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn with_messages(self, messages: Messages) -> Self {
+        self.with_translator(Box::new(messages))
+    }
+
+    /// Sets the translator for all built-in strings, like error messages or
+    /// the notices of background jobs, see [`Text`](crate::translate::Text).
+    /// It is passed to the output formatter in the
+    /// [`FormatContext`](crate::format::FormatContext). Prompts and other
+    /// configured messages are not translated. The default is [`English`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{translate::English, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_translator(Box::new(English));
+    /// ```
+    pub fn with_translator(mut self, translator: Box<dyn Translator>) -> Self {
+        self.translator = translator;
+        self
+    }
+
+    /// Sets all prompts at once. See [`Prompts`] for the available prompts
+    /// and their defaults. Unlike [`ReplBuilder::with_prompt`], the prompts
+    /// are used as they are.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{prompt::Prompts, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_prompts(Prompts {
+    ///     primary: "rupl> ".into(),
+    ///     continuation: "rupl| ".into(),
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn with_prompts(mut self, prompts: Prompts) -> Self {
+        self.prompts = prompts;
         self
     }
 
     /// Adds a command to the REPL. See [`Command`] for more information on how
     /// to construct commands.
     ///
+    /// ### Panics
+    ///
+    /// Panics if the command is declared incorrectly, see [`Command::validate`].
+    ///
     /// ### Example
     ///
     /// ```no_run
-    /// fn hello(params: Parameters, _ctx: &mut ()) -> ReplResult<Option<String>> {
-    ///     let name: String = params.get("name")?;
-    ///     let end: String = params.get("end")?;
+    /// # use rupl::{command::{Command, FnContext}, error::ReplResult, Repl};
+    /// fn hello(ctx: FnContext<()>) -> ReplResult<Option<String>> {
+    ///     let name: String = ctx.args().get("name")?;
+    ///     let end: String = ctx.args().get("end")?;
     ///
     ///     Ok(Some(format!("Hello, {}{}", name, end)))
     /// }
     ///
-    /// let mut repl = Repl::builder(())
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state)
     ///     .with_command(
     ///         Command::new("hello", hello)
-    ///             .with_param(Parameter::new("name"))
-    ///             .with_param(Parameter::new("end")),
+    ///             .with_arg("name", false)
+    ///             .with_arg("end", false),
     ///     )
     ///     .build();
     ///
     /// repl.run();
     /// ```
     pub fn with_command(mut self, command: Command<S>) -> Self {
+        if let Err(err) = command.validate() {
+            panic!("Invalid command '{}': {}", command.name(), err);
+        }
+
         self.commands.insert(command.name().clone(), command);
         self
     }
 
-    /// Enables or disables builtin commands, like `help` or `version`.
+    /// Enables or disables help flags. A command invoked with a trailing
+    /// `--help` or `-h`, like `service dns --help`, prints its usage and
+    /// description, see [`Command::with_description`], instead of running.
+    /// The flags use the argument prefix, see
+    /// [`ReplBuilder::with_arg_prefix`], and are disabled without one. Args
+    /// named `help` or with the short alias `h` take precedence. Help flags
+    /// are enabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_help_flags(false);
+    /// ```
+    pub fn with_help_flags(mut self, use_help_flags: bool) -> Self {
+        self.use_help_flags = use_help_flags;
+        self
+    }
+
+    /// Sets the prefix which precedes the name of every argument. The default
+    /// is `--`, like in `hello --name rupl`. Standalone args use the same
+    /// prefix, like `--verbose`. An empty prefix allows bare argument names,
+    /// like `hello name rupl`. The prefix is not part of the argument name
+    /// passed to [`Command::with_arg`] or [`Parameters::get`](crate::args::Parameters::get).
     ///
     /// ### Example
     ///
     /// ```no_run
-    /// let repl = Repl::builder(()).with_builtins(true);
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_arg_prefix("/");
+    /// ```
+    pub fn with_arg_prefix<P>(mut self, prefix: P) -> Self
+    where
+        P: Into<String>,
+    {
+        self.arg_prefix = prefix.into();
+        self
+    }
+
+    /// Enables or disables builtin commands, like `reset`, see [`Builtin`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_builtins(true);
     /// ```
     pub fn with_builtins(mut self, use_builtins: bool) -> Self {
         self.use_builtins = use_builtins;
         self
     }
 
+    /// Enables or disables a single builtin command, see [`Builtin`]. This
+    /// has no effect if builtins are disabled with
+    /// [`ReplBuilder::with_builtins`]. To replace a builtin, add a command
+    /// with the same name instead, as commands take precedence.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{builtin::Builtin, Repl};
+    /// let mut state = 0;
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_state_reset(|| 0)
+    ///     .with_builtin(Builtin::Reset, false);
+    /// ```
+    pub fn with_builtin(mut self, builtin: Builtin, enabled: bool) -> Self {
+        self.disabled_builtins
+            .retain(|disabled| *disabled != builtin);
+
+        if !enabled {
+            self.disabled_builtins.push(builtin);
+        }
+
+        self
+    }
+
+    /// Sets a highlighter which transforms the raw input into a styled string
+    /// every time the input is displayed. The highlighter can for example
+    /// color valid commands green and invalid ones red. The styling must not
+    /// change the visible text, only add escape codes.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// use termion::color;
+    ///
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_highlighter(|input| {
+    ///     format!("{}{}{}", color::Fg(color::Green), input, color::Fg(color::Reset))
+    /// });
+    /// ```
+    pub fn with_highlighter<H>(mut self, highlighter: H) -> Self
+    where
+        H: Fn(&str) -> String + 'static,
+    {
+        self.highlighter = Some(Box::new(highlighter));
+        self
+    }
+
+    /// Sets a validator which checks the input after every edit. If the
+    /// input is invalid, the returned message is displayed in red below the
+    /// input line until the input becomes valid. The validator is purely
+    /// visual, it neither blocks typing nor submitting the input.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_live_validator(|input| {
+    ///     match input.len() > 20 {
+    ///         true => Err("Input is too long".into()),
+    ///         false => Ok(()),
+    ///     }
+    /// });
+    /// ```
+    pub fn with_live_validator<V>(mut self, validator: V) -> Self
+    where
+        V: Fn(&str) -> Result<(), String> + 'static,
+    {
+        self.live_validator = Some(Box::new(validator));
+        self
+    }
+
+    /// Sets a validator which checks the input when Enter is pressed. If the
+    /// input is invalid, the returned message is displayed and the input
+    /// stays in the buffer for editing instead of being executed. Rejected
+    /// input is neither added to the history nor counted in the stats.
+    ///
+    /// The validator sees the complete input before variables are expanded
+    /// or the line is split into chained commands. Lines ending with the
+    /// continuation char are continued first, see
+    /// [`ReplBuilder::with_continuation_char`], so multi-line input is only
+    /// validated once it is complete. Empty input is never validated. See
+    /// [`ReplBuilder::with_live_validator`] for hints while typing.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_submit_validator(|input| {
+    ///     match input.matches('"').count() % 2 {
+    ///         0 => Ok(()),
+    ///         _ => Err("Unterminated quote".into()),
+    ///     }
+    /// });
+    /// ```
+    pub fn with_submit_validator<V>(mut self, validator: V) -> Self
+    where
+        V: Fn(&str) -> Result<(), String> + 'static,
+    {
+        self.submit_validator = Some(Box::new(validator));
+        self
+    }
+
+    /// Enables or disables echoing executed lines in line-based mode, see
+    /// [`Repl::run_script`]. Every line is printed with the prompt before
+    /// its output, which reproduces the look of an interactive session. This
+    /// is disabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_echo_commands(true);
+    /// ```
+    pub fn with_echo_commands(mut self, use_echo_commands: bool) -> Self {
+        self.use_echo_commands = use_echo_commands;
+        self
+    }
+
+    /// Sets how many recent command outputs are kept for the `last` builtin
+    /// and [`Repl::last_output`]. The oldest output is dropped when the
+    /// capacity is reached. `0` disables recording outputs. The default is
+    /// [`DEFAULT_RECENT_OUTPUTS`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_recent_outputs(50);
+    /// ```
+    pub fn with_recent_outputs(mut self, size: usize) -> Self {
+        self.recent_outputs = size;
+        self
+    }
+
+    /// Sets a resetter which creates a fresh state, e.g. to reinitialize the
+    /// state during development without restarting the process. When
+    /// builtins are enabled, the `reset` command replaces the state with the
+    /// result of the resetter, see [`Repl::reset_state`].
+    ///
+    /// The REPL only borrows the state, so the new state is written into the
+    /// borrowed value and the previous one is dropped. The caller sees the
+    /// reset state once the REPL is dropped. As the resetter is stored in the
+    /// REPL, it must not borrow the state itself.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut counter = 0;
+    /// let repl = Repl::builder(&mut counter).with_state_reset(|| 0);
+    /// ```
+    pub fn with_state_reset<R>(mut self, reset: R) -> Self
+    where
+        R: Fn() -> S + 'static,
+    {
+        self.state_reset = Some(Box::new(reset));
+        self
+    }
+
+    /// Sets an evaluator which receives every submitted line instead of
+    /// matching it against the commands, e.g. for calculators or language
+    /// interpreters. Builtins, variable expansion, chaining and redirection
+    /// don't apply to evaluated lines. Comments are still stripped, see
+    /// [`ReplBuilder::with_comment_char`]. Use
+    /// [`ReplBuilder::with_fallback_evaluator`] to keep the commands.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{args::ParameterError, Repl};
+    /// let mut sum = 0;
+    /// let repl = Repl::builder(&mut sum).with_evaluator(|line, sum| {
+    ///     let value: i64 = line.parse().map_err(|_| ParameterError::InvalidValue {
+    ///         value: line.into(),
+    ///         ty: "i64",
+    ///     })?;
+    ///     *sum += value;
+    ///     Ok(Some(sum.to_string()))
+    /// });
+    /// ```
+    pub fn with_evaluator<E>(mut self, evaluator: E) -> Self
+    where
+        E: Fn(&str, &mut S) -> ReplResult<Option<String>> + 'static,
+    {
+        self.evaluator = Some(Box::new(evaluator));
+        self.use_evaluator_fallback = false;
+        self
+    }
+
+    /// Sets an evaluator which receives every line no builtin or command
+    /// matches, instead of failing with an unknown command. This allows
+    /// meta-commands, like `:help`, alongside an evaluated language. Lines
+    /// are expanded, chained and redirected like commands before they are
+    /// evaluated.
+    ///
+    /// Only the command name decides if a command matches. If the arguments
+    /// of a matched command are invalid, the parser error is reported and the
+    /// line is not evaluated. Ambiguous abbreviations, see
+    /// [`ReplBuilder::with_prefix_matching`], are reported as well.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{command::Command, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_command(Command::new(":quit", |_| std::process::exit(0)))
+    ///     .with_fallback_evaluator(|line, _| Ok(Some(line.chars().rev().collect())));
+    /// ```
+    pub fn with_fallback_evaluator<E>(mut self, evaluator: E) -> Self
+    where
+        E: Fn(&str, &mut S) -> ReplResult<Option<String>> + 'static,
+    {
+        self.evaluator = Some(Box::new(evaluator));
+        self.use_evaluator_fallback = true;
+        self
+    }
+
+    /// Sets a prefix which marks a line as a command, like `:` in `:help`.
+    /// The prefix is stripped before the command is resolved, so the command
+    /// is registered as `help`. Lines without the prefix are passed to the
+    /// fallback evaluator, see [`ReplBuilder::with_fallback_evaluator`], or
+    /// are unknown commands without one. Prefixed lines are never evaluated.
+    /// Builtins require the prefix as well. There is no prefix by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{command::Command, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_command(Command::new("quit", |_| std::process::exit(0)))
+    ///     .with_fallback_evaluator(|line, _| Ok(Some(line.into())))
+    ///     .with_command_prefix(Some(':'));
+    /// ```
+    pub fn with_command_prefix(mut self, prefix: Option<char>) -> Self {
+        self.command_prefix = prefix;
+        self
+    }
+
+    /// Enables or disables auto-pairing of brackets and quotes. Typing an
+    /// opening bracket or quote inserts the closing one after the cursor.
+    /// Typing a closing bracket or quote moves over an existing one instead
+    /// of inserting it. This is disabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_auto_pairs(true);
+    /// ```
+    pub fn with_auto_pairs(mut self, use_auto_pairs: bool) -> Self {
+        self.use_auto_pairs = use_auto_pairs;
+        self
+    }
+
+    /// Enables or disables fish-style autosuggestions. While typing, the most
+    /// recent history entry starting with the current input is displayed as
+    /// dimmed text after the cursor. Pressing the right arrow or the end key
+    /// accepts the suggestion. This is disabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_autosuggest(true);
+    /// ```
+    pub fn with_autosuggest(mut self, use_autosuggest: bool) -> Self {
+        self.use_autosuggest = use_autosuggest;
+        self
+    }
+
+    /// Sets the key bindings used to edit the input. By default, the emacs
+    /// bindings are used. With the vi bindings, `Esc` switches to normal
+    /// mode, see [`ViMode`]. The prompt starts with an indicator of the
+    /// current mode, see [`Prompts::vi_insert`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{buffer::EditMode, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_edit_mode(EditMode::Vi);
+    /// ```
+    pub fn with_edit_mode(mut self, edit_mode: EditMode) -> Self {
+        self.edit_mode = edit_mode;
+        self
+    }
+
+    /// Sets which chars make up a word when moving the cursor by words with
+    /// Alt-B and Alt-F, or when deleting words with Ctrl-W and Alt-D. The
+    /// default is [`WordStyle::AlphaNumeric`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{buffer::WordStyle, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_word_style(WordStyle::Whitespace);
+    /// ```
+    pub fn with_word_style(mut self, word_style: WordStyle) -> Self {
+        self.word_style = word_style;
+        self
+    }
+
+    /// Sets which history entries are recalled with Up and Down, see
+    /// [`HistorySearchMode`]. With [`HistorySearchMode::Prefix`], typing
+    /// `git ` and pressing Up only recalls entries starting with `git `.
+    /// Pressing Down past the most recent match restores the typed input.
+    /// The default is [`HistorySearchMode::All`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{history::HistorySearchMode, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_history_search(HistorySearchMode::Prefix);
+    /// ```
+    pub fn with_history_search(mut self, history_search: HistorySearchMode) -> Self {
+        self.history_search = history_search;
+        self
+    }
+
+    /// Caps the length of history entries to `max` chars, e.g. to keep huge
+    /// pasted lines out of the history. Longer entries are truncated with an
+    /// ellipsis by default, see [`ReplBuilder::with_long_history_entries`].
+    /// The input line itself is not limited. See
+    /// [`History::with_max_entry_length`] for more information.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_max_history_entry_length(200);
+    /// ```
+    pub fn with_max_history_entry_length(mut self, max: usize) -> Self {
+        self.max_history_entry_length = Some(max);
+        self
+    }
+
+    /// Sets what happens with history entries longer than the maximum entry
+    /// length, see [`LongHistoryEntry`]. The default is
+    /// [`LongHistoryEntry::Truncate`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{history::LongHistoryEntry, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_max_history_entry_length(200)
+    ///     .with_long_history_entries(LongHistoryEntry::Skip);
+    /// ```
+    pub fn with_long_history_entries(mut self, long_history_entry: LongHistoryEntry) -> Self {
+        self.long_history_entry = long_history_entry;
+        self
+    }
+
+    /// Sets what happens when Tab completion finds multiple candidates, see
+    /// [`CompletionStyle`]. The default is [`CompletionStyle::ListImmediately`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{completion::CompletionStyle, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_completion_style(CompletionStyle::BellThenList);
+    /// ```
+    pub fn with_completion_style(mut self, completion_style: CompletionStyle) -> Self {
+        self.completion_style = completion_style;
+        self
+    }
+
+    /// Sets the key which opens the command finder, which searches the full
+    /// paths of all commands, like `service dns`, with fuzzy matching, see
+    /// [`fuzzy::score`](crate::fuzzy::score). Typed chars filter the
+    /// commands, the arrow keys select one and enter inserts it at the
+    /// cursor. Escape closes the finder without inserting a command. The
+    /// finder is drawn on the alternate screen. The default key is `Ctrl-P`,
+    /// `None` disables the finder.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// # use termion::event::Key;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_finder_key(Some(Key::Ctrl('o')));
+    /// ```
+    pub fn with_finder_key(mut self, key: Option<Key>) -> Self {
+        self.finder_key = key;
+        self
+    }
+
+    /// Sets the shape of the terminal cursor while the REPL runs, see
+    /// [`CursorShape`]. The shape is set when [`Repl::run`] starts and the
+    /// default shape of the terminal is restored when it returns, including
+    /// on errors and panics, but not if a command calls
+    /// [`std::process::exit`]. By default, the cursor shape is left
+    /// unchanged.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{cursor::CursorShape, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_cursor_shape(CursorShape::Bar);
+    /// ```
+    pub fn with_cursor_shape(mut self, cursor_shape: CursorShape) -> Self {
+        self.cursor_shape = Some(cursor_shape);
+        self
+    }
+
+    /// Sets what Tab inserts when there is nothing to complete. `Some(n)`
+    /// inserts `n` spaces and `Some(0)` a literal tab char. With `None`,
+    /// which is the default, Tab does nothing in this case. This keeps the
+    /// REPL usable for free-form input while command names are still
+    /// completed.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_tab_inserts_spaces(Some(4));
+    /// ```
+    pub fn with_tab_inserts_spaces(mut self, tab_inserts_spaces: Option<usize>) -> Self {
+        self.tab_inserts_spaces = tab_inserts_spaces;
+        self
+    }
+
+    /// Sets the char which starts a comment, or disables comments with
+    /// `None`. A comment starts with the char at the start of a word outside
+    /// of quotes and ends at the end of the line. Comments are kept in the
+    /// history, but never executed. The default is `#`.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_comment_char(Some(';'));
+    /// ```
+    pub fn with_comment_char(mut self, comment_char: Option<char>) -> Self {
+        self.comment_char = comment_char;
+        self
+    }
+
+    /// Sets the char which continues the input on the next line when the
+    /// line ends with it, or disables multi-line input with `None`. The
+    /// char is removed and the lines are joined without a separator. Continued
+    /// lines use the continuation prompt, see [`Prompts::continuation`].
+    /// Ctrl-C discards the input including the continued lines. The default
+    /// is `\`.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_continuation_char(None);
+    /// ```
+    pub fn with_continuation_char(mut self, continuation_char: Option<char>) -> Self {
+        self.continuation_char = continuation_char;
+        self
+    }
+
+    /// Enables or disables chaining commands in a single line. Commands
+    /// separated by `;` run one after another, a command after `&&` only
+    /// runs if the previous one succeeded and a command after `||` only if
+    /// it failed. A command succeeded if it returned `Ok`. This is disabled
+    /// by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_chaining(true);
+    /// ```
+    pub fn with_chaining(mut self, use_chaining: bool) -> Self {
+        self.use_chaining = use_chaining;
+        self
+    }
+
+    /// Enables or disables piping command output into external programs,
+    /// like `service dns status | less`. The program is spawned using the
+    /// shell. This is disabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_pipes(true);
+    /// ```
+    pub fn with_pipes(mut self, use_pipes: bool) -> Self {
+        self.use_pipes = use_pipes;
+        self
+    }
+
+    /// Enables or disables abbreviated command names. If no command matches
+    /// a word exactly, the only command starting with it is used instead,
+    /// so `ser dns` runs `service dns`. If multiple commands start with the
+    /// word, the candidates are reported. This applies to subcommands as
+    /// well and is disabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_prefix_matching(true);
+    /// ```
+    pub fn with_prefix_matching(mut self, use_prefix_matching: bool) -> Self {
+        self.use_prefix_matching = use_prefix_matching;
+        self
+    }
+
+    /// Enables or disables fuzzy command resolution. If no command matches
+    /// the first word, it is matched against the full paths of all commands
+    /// instead, see [`fuzzy::score`](crate::fuzzy::score), so `sdn --port 53`
+    /// runs `service dns --port 53`. The best match runs if it is a clear
+    /// winner, otherwise the candidates are reported. Weak matches, where the
+    /// chars are scattered across the path, never run. Of equally good matches
+    /// the parent command wins over its subcommands. Input for a fallback
+    /// evaluator, see [`ReplBuilder::with_fallback_evaluator`], is never
+    /// matched. Fuzzy matching is disabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_fuzzy_matching(true);
+    /// ```
+    pub fn with_fuzzy_matching(mut self, use_fuzzy_matching: bool) -> Self {
+        self.use_fuzzy_matching = use_fuzzy_matching;
+        self
+    }
+
+    /// Enables or disables strict argument parsing for all commands. In
+    /// strict mode, an argument which the command doesn't declare, like
+    /// `--nmae` or `-x`, is an error naming the argument. Otherwise unknown
+    /// arguments are ignored and available with
+    /// [`Parameters::unknown`](crate::args::Parameters::unknown). Commands can
+    /// override this with [`Command::with_strict`]. Strict mode is enabled by
+    /// default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_strict_args(false);
+    /// ```
+    pub fn with_strict_args(mut self, use_strict_args: bool) -> Self {
+        self.use_strict_args = use_strict_args;
+        self
+    }
+
+    /// Enables or disables the expansion of session variables, like `$NAME`
+    /// or `${NAME}`, in the input line before it is executed. The whole line
+    /// is expanded, including command names. See [`Variables::expand`] for
+    /// the syntax. This is disabled by default.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).with_variable_expansion(true);
+    /// ```
+    pub fn with_variable_expansion(mut self, use_variable_expansion: bool) -> Self {
+        self.use_variable_expansion = use_variable_expansion;
+        self
+    }
+
+    /// Sets how references to variables which are not set are expanded. The
+    /// default is [`UnknownVariable::Keep`].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::{vars::UnknownVariable, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_variable_expansion(true)
+    ///     .with_unknown_variables(UnknownVariable::Empty);
+    /// ```
+    pub fn with_unknown_variables(mut self, unknown_variable: UnknownVariable) -> Self {
+        self.unknown_variable = unknown_variable;
+        self
+    }
+
     /// Build the [`Repl`] based on the configured [`ReplBuilder`]. This is
     /// function is a finalizer and should be called last.
     ///
     /// ### Example
     ///
-    /// ```
-    /// let mut repl = Repl::builder(())
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state)
     ///     .with_version("0.1.4")
     ///     .with_prompt(">")
     ///     .build();
@@ -183,15 +1253,147 @@ impl<'a, S> ReplBuilder<'a, S> {
     /// repl.run();
     /// ```
     pub fn build(self) -> Repl<'a, S> {
-        let stdout = io::stdout().into_raw_mode().unwrap();
+        self.build_with(Terminal::new())
+    }
+
+    /// Build a [`Repl`] which leaves the terminal alone. It never enters raw
+    /// mode and writes nothing to stdout. This is useful to embed the REPL
+    /// in an application which already manages the terminal, like a TUI.
+    /// The application passes keys to [`Repl::handle_key`], draws the line
+    /// returned by [`Repl::render_line`] and the output returned by
+    /// [`Repl::take_output`] itself. [`Repl::run`] must not be called.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{command::Command, Repl};
+    /// use termion::event::Key;
+    ///
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state)
+    ///     .with_command(Command::new("hello", |_| Ok(Some("Hello!".into()))))
+    ///     .build_cooked();
+    ///
+    /// for c in "hello".chars() {
+    ///     repl.handle_key(Key::Char(c)).unwrap();
+    /// }
+    /// assert_eq!(repl.current_input(), "hello");
+    ///
+    /// repl.handle_key(Key::Char('\n')).unwrap();
+    /// assert_eq!(repl.take_output(), vec!["Hello!"]);
+    /// ```
+    pub fn build_cooked(self) -> Repl<'a, S> {
+        self.build_with(Terminal::Detached)
+    }
+
+    /// Build a [`Repl`] which writes everything it would write to the
+    /// terminal into the writer instead, including escape codes. Like
+    /// [`ReplBuilder::build_cooked`], the terminal is left alone and the
+    /// REPL is driven with [`Repl::handle_key`]. This is useful to test
+    /// what the REPL renders. The terminal size is assumed to be 80x24.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::Repl;
+    /// use termion::event::Key;
+    ///
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state)
+    ///     .with_prompt("> ")
+    ///     .build_with_writer(std::io::sink());
+    ///
+    /// repl.handle_key(Key::Char('a')).unwrap();
+    /// assert_eq!(repl.current_input(), "a");
+    /// ```
+    pub fn build_with_writer<W>(self, writer: W) -> Repl<'a, S>
+    where
+        W: Write + 'static,
+    {
+        self.build_with(Terminal::Writer(Box::new(writer)))
+    }
+
+    fn build_with(self, stdout: Terminal) -> Repl<'a, S> {
+        let mut history = History::default().with_long_entry(self.long_history_entry);
+        if let Some(max) = self.max_history_entry_length {
+            history = history.with_max_entry_length(max);
+        }
 
         Repl {
-            stdout_output: OutputBuffer::new(self.output_prompt, "".into()),
-            stdin_output: OutputBuffer::new(self.prompt, "".into()),
-            buffer: CursorBuffer::new(),
+            arg_prefix: self.arg_prefix,
+            stdout_output: OutputBuffer::new("".into(), "".into()),
+            buffer: CursorBuffer::new()
+                .with_word_style(self.word_style)
+                .with_contents(&self.initial_input),
+            history,
+            history_position: None,
+            history_search: self.history_search,
+            inline_output: self.inline_output,
+            use_prompt_markers: self.use_prompt_markers,
+            edit_mode: self.edit_mode,
+            vi_mode: ViMode::Insert,
+            vi_search: String::new(),
+            #[cfg(feature = "background")]
+            jobs: Vec::new(),
+            #[cfg(feature = "background")]
+            next_job_id: 0,
             commands: self.commands,
+            comment_char: self.comment_char,
+            completion_style: self.completion_style,
+            cursor_shape: self.cursor_shape,
+            finder: None,
+            finder_key: self.finder_key,
+            welcome_message: self.welcome_message,
+            exit_message: self.exit_message,
+            idle_timeout: self.idle_timeout,
+            on_tick: self.on_tick,
+            last_tick: Instant::now(),
+            events: None,
+            output_queue: OutputQueue::new(),
+            transcript: self
+                .transcript_file
+                .map(|path| Transcript::new(path, self.use_transcript_timestamps)),
+            disabled_builtins: self.disabled_builtins,
+            exit_requested: false,
+            continuation_char: self.continuation_char,
+            continued: String::new(),
+            stats: ReplStats::default(),
+            tab_count: 0,
+            tab_inserts_spaces: self.tab_inserts_spaces,
+            vars: Variables::new(),
+            highlighter: self.highlighter,
+            hint_visible: false,
+            live_validator: self.live_validator,
+            submit_validator: self.submit_validator,
+            output_formatter: self.output_formatter,
+            error_formatter: self.error_formatter,
+            translator: self.translator,
+            prompts: self.prompts,
+            recent_outputs: VecDeque::new(),
+            recent_outputs_size: self.recent_outputs,
             state: self.state,
+            version: self.version,
+            state_reset: self.state_reset,
+            evaluator: self.evaluator,
+            command_prefix: self.command_prefix,
+            use_evaluator_fallback: self.use_evaluator_fallback,
+            use_auto_pairs: self.use_auto_pairs,
+            use_autosuggest: self.use_autosuggest,
+            use_builtins: self.use_builtins,
+            use_chaining: self.use_chaining,
+            use_echo_commands: self.use_echo_commands,
+            use_exec_welcome: self.use_exec_welcome,
+            use_fuzzy_matching: self.use_fuzzy_matching,
+            last_runs: HashMap::new(),
+            use_help_flags: self.use_help_flags,
+            use_pipes: self.use_pipes,
+            use_prefix_matching: self.use_prefix_matching,
+            use_strict_args: self.use_strict_args,
+            use_variable_expansion: self.use_variable_expansion,
+            unknown_variable: self.unknown_variable,
+            output: Vec::new(),
             stdout,
+            script: None,
         }
     }
 }
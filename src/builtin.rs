@@ -0,0 +1,79 @@
+/// The builtin commands of the REPL. Builtins are enabled with
+/// [`ReplBuilder::with_builtins`](crate::builder::ReplBuilder::with_builtins)
+/// and can be disabled individually with
+/// [`ReplBuilder::with_builtin`](crate::builder::ReplBuilder::with_builtin).
+/// A command with the same name as a builtin always takes precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    /// `docgen` prints the Markdown documentation of all commands, see
+    /// [`Repl::generate_docs`](crate::Repl::generate_docs).
+    Docgen,
+
+    /// `exit` leaves the REPL loop, see
+    /// [`Repl::exit_requested`](crate::Repl::exit_requested).
+    Exit,
+
+    /// `help [COMMAND]` lists the top-level commands, or prints the help of
+    /// the command.
+    Help,
+
+    /// `jobs` lists background jobs. Requires the `background` feature.
+    Jobs,
+
+    /// `last [N]` prints the last `N` command outputs, see
+    /// [`Repl::last_output`](crate::Repl::last_output).
+    Last,
+
+    /// `reset` resets the state. Requires a resetter, see
+    /// [`ReplBuilder::with_state_reset`](crate::builder::ReplBuilder::with_state_reset).
+    Reset,
+
+    /// `version` prints the version, see
+    /// [`ReplBuilder::with_version`](crate::builder::ReplBuilder::with_version).
+    Version,
+
+    /// `wait N` waits for a background job. Requires the `background`
+    /// feature.
+    Wait,
+}
+
+impl Builtin {
+    /// All known builtins.
+    pub const ALL: [Builtin; 8] = [
+        Builtin::Docgen,
+        Builtin::Exit,
+        Builtin::Help,
+        Builtin::Jobs,
+        Builtin::Last,
+        Builtin::Reset,
+        Builtin::Version,
+        Builtin::Wait,
+    ];
+
+    /// Returns the name which invokes the builtin.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Docgen => "docgen",
+            Self::Exit => "exit",
+            Self::Help => "help",
+            Self::Jobs => "jobs",
+            Self::Last => "last",
+            Self::Reset => "reset",
+            Self::Version => "version",
+            Self::Wait => "wait",
+        }
+    }
+
+    /// Returns the builtin invoked by the name.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::builtin::Builtin;
+    /// assert_eq!(Builtin::from_name("reset"), Some(Builtin::Reset));
+    /// assert_eq!(Builtin::from_name("ls"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|builtin| builtin.name() == name)
+    }
+}
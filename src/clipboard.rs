@@ -0,0 +1,99 @@
+//! Integration with the system clipboard, enabled with the `clipboard`
+//! feature. Ctrl-Shift-C copies the current input line and Ctrl-Shift-V
+//! pastes the clipboard content at the cursor.
+//!
+//! Terminals report Ctrl-Shift-C and Ctrl-C with the same byte, so the keys
+//! are only recognized if the terminal sends extended key sequences, either
+//! with the kitty keyboard protocol (`CSI 99;6u`) or with xterm's
+//! `modifyOtherKeys` (`CSI 27;6;99~`). Many terminals bind these keys to
+//! their own copy and paste actions and never pass them to the REPL. In this
+//! case the terminal's own actions keep working as usual.
+//!
+//! Accessing the clipboard can fail, for example without a running display
+//! server. Failures are ignored, as they should not end the REPL.
+//!
+//! Pasted text is inserted as a single line. Control chars, like newlines
+//! and tabs, are dropped, so pasting never submits the input.
+
+use arboard::Clipboard;
+
+/// The modifier parameter of Ctrl-Shift, which is `1 + shift (1) + ctrl (4)`.
+const CTRL_SHIFT: u32 = 6;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ClipboardKey {
+    Copy,
+    Paste,
+}
+
+/// Parses the clipboard key from an escape sequence not handled by termion.
+pub(crate) fn parse_key(seq: &[u8]) -> Option<ClipboardKey> {
+    let seq = std::str::from_utf8(seq).ok()?.strip_prefix("\x1b[")?;
+    let (params, end) = seq.split_at(seq.len().checked_sub(1)?);
+    let params: Vec<u32> = params
+        .split(';')
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    let code = match (end, params.as_slice()) {
+        ("u", [code, CTRL_SHIFT]) => *code,
+        ("~", [27, CTRL_SHIFT, code]) => *code,
+        _ => return None,
+    };
+
+    match char::from_u32(code)?.to_ascii_lowercase() {
+        'c' => Some(ClipboardKey::Copy),
+        'v' => Some(ClipboardKey::Paste),
+        _ => None,
+    }
+}
+
+/// Copies the text into the clipboard.
+pub(crate) fn copy(text: String) {
+    if let Ok(mut clipboard) = Clipboard::new() {
+        let _ = clipboard.set_text(text);
+    }
+}
+
+/// Returns the chars of the text content of the clipboard, without control
+/// chars.
+pub(crate) fn paste() -> Option<Vec<char>> {
+    let text = Clipboard::new().ok()?.get_text().ok()?;
+    Some(strip_control_chars(&text))
+}
+
+fn strip_control_chars(text: &str) -> Vec<char> {
+    text.chars().filter(|c| !c.is_control()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_key, strip_control_chars, ClipboardKey};
+
+    #[test]
+    fn parse_clipboard_key() {
+        // Kitty keyboard protocol
+        assert_eq!(parse_key(b"\x1b[99;6u"), Some(ClipboardKey::Copy));
+        assert_eq!(parse_key(b"\x1b[118;6u"), Some(ClipboardKey::Paste));
+        assert_eq!(parse_key(b"\x1b[67;6u"), Some(ClipboardKey::Copy));
+
+        // xterm's modifyOtherKeys
+        assert_eq!(parse_key(b"\x1b[27;6;99~"), Some(ClipboardKey::Copy));
+        assert_eq!(parse_key(b"\x1b[27;6;118~"), Some(ClipboardKey::Paste));
+
+        // Other keys and modifiers
+        assert_eq!(parse_key(b"\x1b[99;5u"), None);
+        assert_eq!(parse_key(b"\x1b[120;6u"), None);
+        assert_eq!(parse_key(b"\x1b[27;5;99~"), None);
+        assert_eq!(parse_key(b"\x1b[u"), None);
+        assert_eq!(parse_key(b"\x1b["), None);
+        assert_eq!(parse_key(b"99;6u"), None);
+    }
+
+    #[test]
+    fn paste_strips_control_chars() {
+        let chars = strip_control_chars("status\n\tdns\r\n");
+        assert_eq!(chars.into_iter().collect::<String>(), "statusdns");
+    }
+}
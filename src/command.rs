@@ -1,23 +1,249 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
 
-use crate::args::Arg;
+use nom::{
+    branch::alt,
+    bytes::complete::{take_till, take_till1},
+    character::complete::{char, multispace0},
+    sequence::{delimited, preceded},
+    IResult,
+};
+use thiserror::Error;
+
+use crate::{
+    args::{Arg, ParameterError, Parameters},
+    error::ReplResult,
+    translate::{Text, Translator},
+    vars::Variables,
+    ParserError,
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CommandError {
+    #[error("Command name must not be empty")]
+    EmptyName,
+
+    #[error("Command name '{0}' must not contain whitespace")]
+    InvalidName(String),
+
+    #[error("Arg name of command '{0}' must not be empty")]
+    EmptyArgName(String),
+
+    #[error("Duplicate arg '{name}' in command '{command}'")]
+    DuplicateArg { command: String, name: String },
+
+    #[error("Duplicate short alias '{short}' in command '{command}'")]
+    DuplicateShort { command: String, short: char },
+}
+
+/// The function which is called when a command is executed. The function
+/// can mutate the variables it captured, see [`Command::new`]. Clones of a
+/// command share the function, see [`Command::clone`], so it is kept in a
+/// [`RefCell`] and commands run through a shared borrow. Running a command
+/// from within its own function panics, as the function is already borrowed.
+pub type CommandFn<S> = Rc<RefCell<dyn FnMut(FnContext<S>) -> ReplResult<Option<String>>>>;
+
+/// A function which reads the next raw input line, see
+/// [`FnContext::read_raw_line`].
+pub(crate) type LineReader<'a> = &'a mut (dyn FnMut() -> ReplResult<Option<String>> + 'a);
+
+/// The context passed to a command function. It provides access to the
+/// parameters of the command, the state of the REPL and the session
+/// variables.
+pub struct FnContext<'a, S> {
+    args: Parameters,
+    state: &'a mut S,
+    vars: &'a mut Variables,
+    reader: Option<LineReader<'a>>,
+}
+
+impl<'a, S> FnContext<'a, S> {
+    pub fn new(args: Parameters, state: &'a mut S, vars: &'a mut Variables) -> Self {
+        Self {
+            args,
+            state,
+            vars,
+            reader: None,
+        }
+    }
+
+    /// Sets the function which reads raw input lines.
+    pub(crate) fn with_reader(mut self, reader: LineReader<'a>) -> Self {
+        self.reader = Some(reader);
+        self
+    }
+
+    pub fn args(&self) -> &Parameters {
+        &self.args
+    }
+
+    pub fn state(&mut self) -> &mut S {
+        self.state
+    }
+
+    /// Returns the session variables, see [`Variables`].
+    pub fn vars(&self) -> &Variables {
+        self.vars
+    }
+
+    /// Returns the session variables for modification.
+    pub fn vars_mut(&mut self) -> &mut Variables {
+        self.vars
+    }
+
+    /// Reads the next input line as it is, without parsing it as a command
+    /// and without adding it to the history, e.g. to read a here-doc. The
+    /// trailing newline is removed.
+    ///
+    /// In a terminal, the typed line is echoed and can be edited with
+    /// backspace, `Ctrl-D` on an empty line ends the input. A script, see
+    /// [`Repl::run_script`](crate::Repl::run_script), returns its next line,
+    /// which is then skipped by the script. Returns `None` at the end of the
+    /// input and if the REPL has no input, like one built with
+    /// [`ReplBuilder::build_cooked`](crate::builder::ReplBuilder::build_cooked)
+    /// or a context created with [`FnContext::new`].
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{command::{Command, FnContext}, Repl};
+    /// let cmd: Command<()> = Command::new("cat", |mut ctx: FnContext<()>| {
+    ///     let mut lines = Vec::new();
+    ///
+    ///     while let Some(line) = ctx.read_raw_line()? {
+    ///         if line == "EOF" {
+    ///             break;
+    ///         }
+    ///         lines.push(line);
+    ///     }
+    ///
+    ///     Ok(Some(lines.join("\n")))
+    /// });
+    /// ```
+    pub fn read_raw_line(&mut self) -> ReplResult<Option<String>> {
+        match &mut self.reader {
+            Some(reader) => reader(),
+            None => Ok(None),
+        }
+    }
+}
 
 pub struct Command<S> {
     pub(crate) sub: HashMap<String, Command<S>>,
-    pub(crate) func: Box<dyn Fn(&mut S) -> String>,
+    pub(crate) func: Option<CommandFn<S>>,
+    #[cfg(feature = "background")]
+    pub(crate) background: Option<crate::jobs::BackgroundFn>,
+    pub(crate) takes_remainder: bool,
+    pub(crate) strict: Option<bool>,
+    pub(crate) output_prompt: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) dangerous: bool,
+    pub(crate) cooldown: Option<Duration>,
     pub(crate) args: Vec<Arg>,
     pub(crate) name: String,
 }
 
+/// Clones the command together with its subcommands, e.g. to register the
+/// same command in multiple places of the command tree. The clones share
+/// the function and the variables it captured, so a counter counts the runs
+/// of all clones. Everything else, like the args, is copied, so changing a
+/// clone doesn't affect the others.
+///
+/// The function is shared with an [`Rc`] instead of a reference, so
+/// commands don't borrow from each other and can be built and moved freely.
+/// Like the state, the function stays on the thread of the REPL.
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::{command::Command, Repl};
+/// let status: Command<()> = Command::new("status", |_| Ok(Some("running".into())));
+///
+/// let mut state = ();
+/// let repl = Repl::builder(&mut state)
+///     .with_command(Command::namespace("dns").with_subcommand(status.clone()))
+///     .with_command(Command::namespace("dhcp").with_subcommand(status))
+///     .build();
+/// ```
+impl<S> Clone for Command<S> {
+    fn clone(&self) -> Self {
+        Self {
+            sub: self.sub.clone(),
+            func: self.func.clone(),
+            #[cfg(feature = "background")]
+            background: self.background.clone(),
+            takes_remainder: self.takes_remainder,
+            strict: self.strict,
+            output_prompt: self.output_prompt.clone(),
+            description: self.description.clone(),
+            dangerous: self.dangerous,
+            cooldown: self.cooldown,
+            args: self.args.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
 impl<S> Command<S> {
+    /// Creates a command which calls the function when it is executed. The
+    /// function receives the shared state with the context. State which
+    /// only the command uses can be captured by the function instead, which
+    /// keeps it between executions.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{args::Parameters, command::Command, vars::Variables};
+    /// let mut count = 0;
+    /// let cmd: Command<()> = Command::new("count", move |_| {
+    ///     count += 1;
+    ///     Ok(Some(count.to_string()))
+    /// });
+    ///
+    /// let mut vars = Variables::new();
+    /// cmd.run(Parameters::new(vec![]), &mut (), &mut vars).unwrap();
+    /// let output = cmd.run(Parameters::new(vec![]), &mut (), &mut vars).unwrap();
+    /// assert_eq!(output.as_deref(), Some("2"));
+    /// ```
     pub fn new<N, F>(name: N, func: F) -> Self
     where
         N: Into<String>,
-        F: Fn(&mut S) -> String + 'static,
+        F: FnMut(FnContext<S>) -> ReplResult<Option<String>> + 'static,
     {
         Self {
-            func: Box::new(func),
+            func: Some(Rc::new(RefCell::new(func))),
+            ..Self::namespace(name)
+        }
+    }
+
+    /// Creates a command without a function, which only groups its
+    /// subcommands. Running it on its own fails, so chained commands like
+    /// `service && status` stop, and the REPL outputs a usage line listing
+    /// the subcommands, like `Usage: service <dns|http>`.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::command::Command;
+    /// let cmd: Command<()> = Command::namespace("service")
+    ///     .with_subcommand(Command::new("dns", |_| Ok(Some("dns".into()))));
+    ///
+    /// assert!(cmd.is_namespace());
+    /// ```
+    pub fn namespace<N>(name: N) -> Self
+    where
+        N: Into<String>,
+    {
+        Self {
+            func: None,
             sub: HashMap::new(),
+            #[cfg(feature = "background")]
+            background: None,
+            takes_remainder: false,
+            strict: None,
+            output_prompt: None,
+            description: None,
+            dangerous: false,
+            cooldown: None,
             name: name.into(),
             args: Vec::new(),
         }
@@ -27,6 +253,11 @@ impl<S> Command<S> {
         &self.name
     }
 
+    /// Returns if the command was created with [`Command::namespace`].
+    pub fn is_namespace(&self) -> bool {
+        self.func.is_none()
+    }
+
     pub fn list_subcommands(&self) -> Vec<&String> {
         self.sub.keys().collect()
     }
@@ -36,27 +267,527 @@ impl<S> Command<S> {
         self
     }
 
-    pub fn with_arg<N>(mut self, name: N, standalone: bool) -> Self
+    pub fn with_arg<N>(self, name: N, standalone: bool) -> Self
     where
         N: Into<String>,
     {
-        self.args.push(Arg::new(name, standalone));
+        self.with_argument(Arg::new(name, standalone))
+    }
+
+    /// Adds a fully configured [`Arg`], for example one with a short alias.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{args::Arg, command::Command};
+    /// let cmd: Command<()> = Command::new("ls", |_| Ok(None))
+    ///     .with_argument(Arg::new("all", true).with_short('a'));
+    /// ```
+    pub fn with_argument(mut self, arg: Arg) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    /// Accepts arbitrary input after the declared arguments, which is
+    /// available to the command with [`Parameters::remainder`]. Without it,
+    /// such input is rejected as invalid arguments.
+    pub fn with_remainder(mut self, takes_remainder: bool) -> Self {
+        self.takes_remainder = takes_remainder;
+        self
+    }
+
+    /// Adds a description, which is printed below the usage line when the
+    /// command is invoked with a help flag, like `--help`, see
+    /// [`ReplBuilder::with_help_flags`](crate::builder::ReplBuilder::with_help_flags).
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::command::Command;
+    /// let cmd: Command<()> = Command::new("status", |_| Ok(None))
+    ///     .with_description("Prints the status of all services");
+    /// ```
+    pub fn with_description<D>(mut self, description: D) -> Self
+    where
+        D: Into<String>,
+    {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Marks the command as dangerous, e.g. because it deletes data. Before a
+    /// dangerous command runs, the user is asked to confirm it with `y`. If
+    /// declined, the command is skipped and fails. Without a terminal to
+    /// ask, like in a REPL built with
+    /// [`ReplBuilder::build_cooked`](crate::builder::ReplBuilder::build_cooked),
+    /// it is always declined. In a script, the next line is the answer.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::command::Command;
+    /// let cmd: Command<()> = Command::new("delete", |_| Ok(None)).dangerous(true);
+    /// assert!(cmd.is_dangerous());
+    /// ```
+    pub fn dangerous(mut self, dangerous: bool) -> Self {
+        self.dangerous = dangerous;
         self
     }
 
-    pub fn run(&self, state: &mut S) -> String {
-        (self.func)(state)
+    pub fn is_dangerous(&self) -> bool {
+        self.dangerous
+    }
+
+    /// Refuses to run the command again until the cooldown elapsed since it
+    /// last ran, e.g. to rate limit requests to an external service. The
+    /// first invocation always runs. Refused invocations fail with the
+    /// remaining time and don't restart the cooldown.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use rupl::command::Command;
+    /// let cmd: Command<()> = Command::new("sync", |_| Ok(None))
+    ///     .with_cooldown(Duration::from_secs(30));
+    /// ```
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = Some(cooldown);
+        self
     }
 
-    pub(crate) fn parse_args<'a>(&self, args: Vec<(&'a str, &'a str)>) -> bool {
-        args.iter().all(|arg| {
-            self.args.iter().any(|a| {
-                if !a.is_standalone() && arg.1.is_empty() {
-                    return false;
+    /// Enables or disables strict argument parsing for this command only,
+    /// overriding [`ReplBuilder::with_strict_args`](crate::builder::ReplBuilder::with_strict_args).
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::command::Command;
+    /// let cmd: Command<()> = Command::new("ping", |ctx| {
+    ///     let ignored: Vec<_> = ctx.args().unknown().map(|(name, _)| name).collect();
+    ///     Ok(Some(format!("pong (ignored: {})", ignored.join(", "))))
+    /// })
+    /// .with_strict(false);
+    /// ```
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = Some(strict);
+        self
+    }
+
+    /// Overrides the output prompt, see [`Prompts::output`](crate::prompt::Prompts::output),
+    /// for the results of this command only. `Some("")` writes the output
+    /// without a prompt, e.g. for raw data which is processed further.
+    /// `None` uses the global output prompt, which is the default.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::command::Command;
+    /// let cmd: Command<()> = Command::new("dump", |_| Ok(Some("00 ff 10".into())))
+    ///     .with_output_prompt(Some(String::new()));
+    /// ```
+    pub fn with_output_prompt(mut self, prompt: Option<String>) -> Self {
+        self.output_prompt = prompt;
+        self
+    }
+
+    /// Checks that the command and all of its subcommands are declared
+    /// correctly. The following invariants must hold:
+    ///
+    /// - The command name is not empty and contains no whitespace
+    /// - Arg names are not empty and unique within the command
+    /// - Short aliases are unique within the command
+    ///
+    /// The remainder, see [`Command::with_remainder`], is the only variadic
+    /// input of a command and always follows the declared args, so it needs
+    /// no further checks. [`ReplBuilder::with_command`](crate::builder::ReplBuilder::with_command)
+    /// calls this function and panics if the command is invalid.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::command::{Command, CommandError};
+    /// let cmd: Command<()> = Command::new("hello", |_| Ok(None))
+    ///     .with_arg("name", false)
+    ///     .with_arg("name", false);
+    ///
+    /// assert_eq!(
+    ///     cmd.validate(),
+    ///     Err(CommandError::DuplicateArg {
+    ///         command: "hello".into(),
+    ///         name: "name".into()
+    ///     })
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), CommandError> {
+        if self.name.is_empty() {
+            return Err(CommandError::EmptyName);
+        }
+
+        if self.name.contains(char::is_whitespace) {
+            return Err(CommandError::InvalidName(self.name.clone()));
+        }
+
+        for (i, arg) in self.args.iter().enumerate() {
+            if arg.name().is_empty() {
+                return Err(CommandError::EmptyArgName(self.name.clone()));
+            }
+
+            let previous = &self.args[..i];
+
+            if previous.iter().any(|other| other.name() == arg.name()) {
+                return Err(CommandError::DuplicateArg {
+                    command: self.name.clone(),
+                    name: arg.name().clone(),
+                });
+            }
+
+            if let Some(short) = arg.short() {
+                if previous.iter().any(|other| other.short() == Some(short)) {
+                    return Err(CommandError::DuplicateShort {
+                        command: self.name.clone(),
+                        short,
+                    });
                 }
+            }
+        }
 
-                a == arg.0
-            })
+        self.sub.values().try_for_each(Command::validate)
+    }
+
+    /// Allows running the command in the background by ending the line with
+    /// `&`. The background function is called instead of the regular one on
+    /// a separate thread. See [`jobs`](crate::jobs) for more information.
+    #[cfg(feature = "background")]
+    pub fn with_background<F>(mut self, func: F) -> Self
+    where
+        F: Fn(Parameters) -> ReplResult<Option<String>> + Send + Sync + 'static,
+    {
+        self.background = Some(std::sync::Arc::new(func));
+        self
+    }
+
+    /// Runs the command with the parameters. The command succeeded if it
+    /// returns `Ok`, which decides whether chained commands run, see
+    /// [`ReplBuilder::with_chaining`](crate::builder::ReplBuilder::with_chaining).
+    /// A namespace fails with [`ParserError::MissingSubcommand`], see
+    /// [`Command::namespace`].
+    ///
+    /// ### Panics
+    ///
+    /// Panics if the command is run from within its own function, see
+    /// [`CommandFn`].
+    pub fn run(
+        &self,
+        args: Parameters,
+        state: &mut S,
+        vars: &mut Variables,
+    ) -> ReplResult<Option<String>> {
+        match &self.func {
+            Some(func) => (func.borrow_mut())(FnContext::new(args, state, vars)),
+            None => Err(ParserError::MissingSubcommand.into()),
+        }
+    }
+
+    /// Returns the help of the command at the path, like `service dns`. The
+    /// help is the usage line, see [`Command::args_usage`], followed by the
+    /// description, if there is one.
+    pub(crate) fn help(&self, path: &str, prefix: &str, translator: &dyn Translator) -> String {
+        let usage = self.args_usage(path, prefix, translator);
+
+        match &self.description {
+            Some(description) => format!("{}\n{}", usage, description),
+            None => usage,
+        }
+    }
+
+    /// Returns the usage line of the command at the path, which lists the
+    /// args in the order they were declared and the sorted names of the
+    /// subcommands, like `Usage: service dns [--port <port>] <status>`.
+    pub(crate) fn args_usage(
+        &self,
+        path: &str,
+        prefix: &str,
+        translator: &dyn Translator,
+    ) -> String {
+        let short_prefix = prefix.chars().next().map(String::from).unwrap_or_default();
+        let mut command = path.to_string();
+
+        for arg in &self.args {
+            let name = match arg.short() {
+                Some(short) => format!("{}{}|{}{}", short_prefix, short, prefix, arg.name()),
+                None => format!("{}{}", prefix, arg.name()),
+            };
+
+            match arg.is_standalone() {
+                true => command.push_str(&format!(" [{}]", name)),
+                false => command.push_str(&format!(" [{} <{}>]", name, arg.value_name())),
+            }
+        }
+
+        if self.takes_remainder {
+            command.push_str(" [...]");
+        }
+
+        translator.translate(Text::Usage {
+            command: &command,
+            subcommands: &self.sorted_subcommands(),
         })
     }
+
+    /// Returns the Markdown documentation of the command at the path without
+    /// its heading, see [`Repl::generate_docs`](crate::Repl::generate_docs).
+    /// The documentation consists of the description, the usage line in a
+    /// code block and a list of the parameters.
+    pub(crate) fn docs(&self, path: &str, prefix: &str, translator: &dyn Translator) -> String {
+        let mut docs = String::new();
+
+        if let Some(description) = &self.description {
+            docs.push_str(&format!("{}\n\n", description));
+        }
+
+        docs.push_str(&format!(
+            "```text\n{}\n```\n",
+            self.args_usage(path, prefix, translator)
+        ));
+
+        if self.args.is_empty() && !self.takes_remainder {
+            return docs;
+        }
+
+        docs.push_str(&format!(
+            "\n{}:\n\n",
+            translator.translate(Text::DocsParameters)
+        ));
+        let short_prefix = prefix.chars().next().map(String::from).unwrap_or_default();
+
+        for arg in &self.args {
+            let mut names = match arg.short() {
+                Some(short) => format!("`{}{}`, `{}{}", short_prefix, short, prefix, arg.name()),
+                None => format!("`{}{}", prefix, arg.name()),
+            };
+
+            match arg.is_standalone() {
+                true => names.push('`'),
+                false => names.push_str(&format!(" <{}>`", arg.value_name())),
+            }
+
+            docs.push_str(&format!("- {}\n", names));
+        }
+
+        if self.takes_remainder {
+            docs.push_str(&format!(
+                "- `...`: {}\n",
+                translator.translate(Text::DocsRemainder)
+            ));
+        }
+
+        docs
+    }
+
+    /// Returns the words which can follow the command in a completion
+    /// script, see [`Repl::generate_completions`](crate::Repl::generate_completions).
+    /// These are the sorted subcommands followed by the args, each with its
+    /// short alias first. Without an arg prefix, args are not completed.
+    pub(crate) fn completion_words(&self, prefix: &str) -> Vec<String> {
+        let mut words: Vec<String> = self
+            .sorted_subcommands()
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let short_prefix = match prefix.chars().next() {
+            Some(c) => c,
+            None => return words,
+        };
+
+        for arg in &self.args {
+            if let Some(short) = arg.short() {
+                words.push(format!("{}{}", short_prefix, short));
+            }
+
+            words.push(format!("{}{}", prefix, arg.name()));
+        }
+
+        words
+    }
+
+    /// Returns the args with choices which can follow the command in a
+    /// completion script, each with its sorted choices, see [`Arg::one_of`].
+    /// An arg with a short alias is returned for both words, like `-m` and
+    /// `--mode`. Without an arg prefix, args are not completed.
+    pub(crate) fn completion_choices(&self, prefix: &str) -> Vec<(String, Vec<String>)> {
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        self.completion_words(prefix)
+            .into_iter()
+            .filter_map(|word| {
+                let choices = self.arg_choices(&word, prefix)?;
+                Some((word, choices.into_iter().map(String::from).collect()))
+            })
+            .collect()
+    }
+
+    /// Returns if the word is a help flag, which is `help` or the short alias
+    /// `h` with the prefix, like `--help` and `-h`. Args declared by the
+    /// command take precedence, so a command can use these names itself.
+    /// Without a prefix, there is no help flag.
+    pub(crate) fn is_help_flag(&self, word: &str, prefix: &str) -> bool {
+        let short_prefix = match prefix.chars().next() {
+            Some(c) => c,
+            None => return false,
+        };
+
+        let is_help = word.strip_prefix(prefix) == Some("help")
+            || word.strip_prefix(short_prefix) == Some("h");
+
+        is_help && self.find_arg(word, prefix).is_none()
+    }
+
+    fn sorted_subcommands(&self) -> Vec<&str> {
+        let mut subcommands: Vec<_> = self.sub.keys().map(String::as_str).collect();
+        subcommands.sort();
+        subcommands
+    }
+
+    /// Parses the arguments of the command from the input. Every arg name
+    /// must be preceded by `prefix` and every short alias by the first char
+    /// of `prefix`. Standalone args are flags without a value, all other args
+    /// are followed by their value. Parsing stops at the first word which is
+    /// not a prefixed declared arg.
+    ///
+    /// A prefixed word which is not a declared arg is an unknown arg. It is
+    /// an error if `strict` is set and the command doesn't override it, see
+    /// [`Command::with_strict`]. Otherwise it is skipped together with the
+    /// following word, if that is not prefixed, and stored as its value in
+    /// the parameters. Commands taking the remainder start it at the first
+    /// unknown arg instead.
+    pub(crate) fn parse_args(
+        &self,
+        input: &str,
+        prefix: &str,
+        strict: bool,
+    ) -> Result<Parameters, ParserError> {
+        let strict = self.strict.unwrap_or(strict);
+        let mut args = Vec::new();
+        let mut unknown = Vec::new();
+        let mut rest = input;
+
+        while let Ok((after_name, word)) = token(rest) {
+            let arg = match self.find_arg(word, prefix) {
+                Some(arg) => arg,
+                None if self.takes_remainder || !is_prefixed(word, prefix) => break,
+                None if strict => return Err(ParserError::UnknownArg(word.into())),
+                None => {
+                    rest = after_name;
+
+                    let value = match token(rest) {
+                        Ok((after_value, value)) if !is_prefixed(value, prefix) => {
+                            rest = after_value;
+                            value
+                        }
+                        _ => "",
+                    };
+
+                    unknown.push((word, value));
+                    continue;
+                }
+            };
+            let name = arg.name().as_str();
+
+            if arg.is_standalone() {
+                args.push((name, ""));
+                rest = after_name;
+                continue;
+            }
+
+            let (after_value, value) =
+                token(after_name).map_err(|_| ParserError::InvalidArgCount)?;
+
+            if !arg.choices().is_empty() && !arg.choices().iter().any(|choice| choice == value) {
+                return Err(ParserError::InvalidChoice(
+                    ParameterError::InvalidParameter {
+                        name: name.into(),
+                        value: value.into(),
+                        message: format!("expected one of: {}", arg.choices().join(", ")),
+                    },
+                ));
+            }
+
+            args.push((name, value));
+            rest = after_value;
+        }
+
+        if !self.takes_remainder && !rest.trim().is_empty() {
+            return Err(ParserError::InvalidArgs);
+        }
+
+        let remainder = input.len() - rest.len();
+        let declared = self.args.iter().map(Arg::name);
+        Ok(Parameters::with_input(args, input, remainder)
+            .with_declared(declared)
+            .with_unknown(unknown))
+    }
+
+    /// Returns the sorted choices of the declared arg the word refers to,
+    /// see [`Arg::one_of`]. Returns `None` if the word is no arg with
+    /// choices.
+    pub(crate) fn arg_choices(&self, word: &str, prefix: &str) -> Option<Vec<&str>> {
+        let arg = self
+            .find_arg(word, prefix)
+            .filter(|arg| !arg.is_standalone())?;
+        let mut choices: Vec<_> = arg.choices().iter().map(String::as_str).collect();
+
+        match choices.is_empty() {
+            true => None,
+            false => {
+                choices.sort();
+                Some(choices)
+            }
+        }
+    }
+
+    /// Returns the declared arg the word refers to, either by its prefixed
+    /// name or by its prefixed short alias.
+    fn find_arg(&self, word: &str, prefix: &str) -> Option<&Arg> {
+        if let Some(arg) = word
+            .strip_prefix(prefix)
+            .and_then(|name| self.args.iter().find(|arg| *arg == name))
+        {
+            return Some(arg);
+        }
+
+        let short_prefix = prefix.chars().next().map(String::from).unwrap_or_default();
+        let mut chars = word.strip_prefix(short_prefix.as_str())?.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(short), None) => self.args.iter().find(|arg| arg.short() == Some(short)),
+            _ => None,
+        }
+    }
+}
+
+/// Returns if the word starts with the arg prefix or the short prefix, which
+/// is the first char of the arg prefix. Every word is prefixed by an empty
+/// prefix.
+fn is_prefixed(word: &str, prefix: &str) -> bool {
+    match prefix.chars().next() {
+        Some(short_prefix) => word.starts_with(short_prefix),
+        None => true,
+    }
+}
+
+/// Parses a single word, which is either a quoted string or everything up to
+/// the next whitespace. Quotes are not part of the returned word.
+fn token(input: &str) -> IResult<&str, &str> {
+    preceded(
+        multispace0,
+        alt((
+            delimited(char('"'), take_till(|c| c == '"'), char('"')),
+            delimited(char('\''), take_till(|c| c == '\''), char('\'')),
+            take_till1(char::is_whitespace),
+        )),
+    )(input)
 }
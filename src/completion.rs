@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use crate::command::Command;
+
+/// The maximum edit distance between a misspelled word and a suggested
+/// command name.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Defines what happens when Tab completion finds multiple candidates and
+/// the input can't be extended any further.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CompletionStyle {
+    /// Lists all candidates on the first Tab press.
+    #[default]
+    ListImmediately,
+
+    /// Rings the bell on the first Tab press and lists all candidates on
+    /// the second consecutive one, like bash does.
+    BellThenList,
+}
+
+/// The shells for which completion scripts can be generated, see
+/// [`Repl::generate_completions`](crate::Repl::generate_completions).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Shell {
+    /// A script for bash, which registers the completion with `complete`.
+    Bash,
+}
+
+/// Returns the completion script for the binary. The commands are the paths
+/// of all commands, like `service dns`, with the words which can follow
+/// them, see [`Command::completion_words`]. Subcommands must follow their
+/// parents. The choices are the paths followed by an arg, like
+/// `service dns --mode`, with the choices of the arg, see
+/// [`Command::completion_choices`].
+pub(crate) fn script(
+    shell: Shell,
+    bin_name: &str,
+    commands: &[(String, Vec<String>)],
+    choices: &[(String, Vec<String>)],
+) -> String {
+    match shell {
+        Shell::Bash => bash_script(bin_name, commands, choices),
+    }
+}
+
+/// Returns a bash completion script. The previous words which continue a
+/// command path select the candidates, other words like args and their
+/// values are skipped. The candidates are the choices if the previous word
+/// is an arg with choices, otherwise the words of the command at the path,
+/// or the top-level commands.
+///
+/// All names are quoted, see [`bash_quote`], and the candidates are matched
+/// without `compgen -W`, which would expand them. This way names with
+/// quotes, `$` or backticks neither break the script nor run code.
+fn bash_script(
+    bin_name: &str,
+    commands: &[(String, Vec<String>)],
+    choices: &[(String, Vec<String>)],
+) -> String {
+    let function: String = bin_name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    let quote_all =
+        |words: &[String]| -> Vec<String> { words.iter().map(|word| bash_quote(word)).collect() };
+
+    // An empty pattern list would be a syntax error
+    let mut paths: Vec<_> = commands.iter().map(|(path, _)| bash_quote(path)).collect();
+    if paths.is_empty() {
+        paths.push("''".into());
+    }
+
+    let top_level: Vec<_> = commands
+        .iter()
+        .filter(|(path, _)| !path.contains(' '))
+        .map(|(path, _)| bash_quote(path))
+        .collect();
+
+    let mut cases = vec![format!("        '') words=({}) ;;", top_level.join(" "))];
+    cases.extend(
+        commands
+            .iter()
+            .chain(choices)
+            .filter(|(_, words)| !words.is_empty())
+            .map(|(path, words)| {
+                format!(
+                    "        {}) words=({}) ;;",
+                    bash_quote(path),
+                    quote_all(words).join(" ")
+                )
+            }),
+    );
+
+    // After an arg with choices, its choices are completed instead
+    let choice_key = match choices.is_empty() {
+        true => String::new(),
+        false => {
+            let keys: Vec<_> = choices.iter().map(|(key, _)| bash_quote(key)).collect();
+            format!(
+                "    case \"${{path:+$path }}$prev\" in\n        \
+                 {}) key=\"${{path:+$path }}$prev\" ;;\n    \
+                 esac\n",
+                keys.join("|")
+            )
+        }
+    };
+
+    format!(
+        "\
+_{function}() {{
+    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\" prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+    local path=\"\" key word
+    local -a words=()
+    for word in \"${{COMP_WORDS[@]:1:COMP_CWORD-1}}\"; do
+        case \"${{path:+$path }}$word\" in
+            {paths}) path=\"${{path:+$path }}$word\" ;;
+        esac
+    done
+    key=\"$path\"
+{choice_key}    case \"$key\" in
+{cases}
+    esac
+    COMPREPLY=()
+    for word in \"${{words[@]}}\"; do
+        if [[ \"$word\" == \"$cur\"* ]]; then
+            COMPREPLY+=(\"$word\")
+        fi
+    done
+}}
+complete -F _{function} {bin_name}
+",
+        function = function,
+        paths = paths.join("|"),
+        cases = cases.join("\n"),
+        choice_key = choice_key,
+        bin_name = bash_quote(bin_name),
+    )
+}
+
+/// Quotes the word for bash with single quotes, in which no char is special.
+/// A single quote ends the quoted string, is escaped and starts a new one.
+fn bash_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// Returns all command names which complete the last word of the input,
+/// in alphabetical order. All words before the last one must match a
+/// command or subcommand exactly. After an arg with choices, like
+/// `service dns --mode`, the choices are completed instead, see
+/// [`Arg::one_of`](crate::args::Arg::one_of).
+pub(crate) fn candidates<'a, S>(
+    input: &str,
+    commands: &'a HashMap<String, Command<S>>,
+    arg_prefix: &str,
+) -> Vec<&'a str> {
+    let mut cmds = commands;
+    let mut cmd = None;
+    let mut words: Vec<&str> = input.split(' ').collect();
+    let partial = words.pop().unwrap_or_default();
+    let mut rest = words.as_slice();
+
+    while let Some((word, tail)) = rest.split_first() {
+        match cmds.get(*word) {
+            Some(sub) => {
+                cmds = &sub.sub;
+                cmd = Some(sub);
+                rest = tail;
+            }
+            None => break,
+        }
+    }
+
+    let mut candidates: Vec<&str> = match (cmd, rest.last()) {
+        (_, None) => cmds.keys().map(String::as_str).collect(),
+        (Some(cmd), Some(word)) => cmd.arg_choices(word, arg_prefix).unwrap_or_default(),
+        (None, Some(_)) => Vec::new(),
+    };
+
+    candidates.retain(|candidate| candidate.starts_with(partial));
+    candidates.sort();
+    candidates
+}
+
+/// Returns the longest common prefix of all candidates.
+pub(crate) fn common_prefix<'a>(candidates: &[&'a str]) -> &'a str {
+    let first = match candidates.first() {
+        Some(first) => *first,
+        None => return "",
+    };
+
+    let len = candidates[1..].iter().fold(first.len(), |len, candidate| {
+        first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((i, a), b)| *i < len && a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+    });
+
+    &first[..len]
+}
+
+/// Returns the closest command path for the input, if a word of the input
+/// matches no command but is similar to one. The path includes all matched
+/// parent commands, like `service dns` for `service dsn`.
+pub(crate) fn suggest<S>(input: &str, commands: &HashMap<String, Command<S>>) -> Option<String> {
+    let mut cmds = commands;
+    let mut path = Vec::new();
+
+    for word in input.split_whitespace() {
+        if let Some(cmd) = cmds.get(word) {
+            path.push(cmd.name().as_str());
+            cmds = &cmd.sub;
+            continue;
+        }
+
+        let closest = cmds
+            .keys()
+            .map(|name| (distance(word, name), name))
+            .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE && *distance < word.len())
+            .min()?;
+
+        path.push(closest.1);
+        return Some(path.join(" "));
+    }
+
+    None
+}
+
+/// Returns the Levenshtein distance between both words, which is the number
+/// of inserted, removed or replaced chars to turn one word into the other.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current.push(
+                (previous[j] + cost)
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
@@ -0,0 +1,93 @@
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+/// The shape of the terminal cursor, see
+/// [`ReplBuilder::with_cursor_shape`](crate::builder::ReplBuilder::with_cursor_shape).
+/// The shape is set with a DECSCUSR escape sequence, which is ignored by
+/// terminals that don't support it.
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::cursor::CursorShape;
+/// assert_eq!(CursorShape::Bar.to_string(), "\x1b[6 q");
+/// assert_eq!(CursorShape::RESET, "\x1b[0 q");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorShape {
+    /// A steady block, like `█`.
+    Block,
+
+    /// A steady underline, like `_`.
+    Underline,
+
+    /// A steady vertical bar, like `|`.
+    Bar,
+}
+
+impl CursorShape {
+    /// Restores the default cursor shape of the terminal.
+    pub const RESET: &'static str = "\x1b[0 q";
+}
+
+impl fmt::Display for CursorShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let param = match self {
+            Self::Block => 2,
+            Self::Underline => 4,
+            Self::Bar => 6,
+        };
+
+        write!(f, "\x1b[{} q", param)
+    }
+}
+
+/// Sets the cursor shape and restores the default one when it is dropped,
+/// so the shape is restored however [`Repl::run`](crate::Repl::run) is left,
+/// including a panic of a command. Like every destructor, it doesn't run if
+/// the process exits with [`std::process::exit`].
+pub(crate) struct ShapeGuard<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> ShapeGuard<W> {
+    pub(crate) fn set(shape: CursorShape, mut writer: W) -> io::Result<Self> {
+        write!(writer, "{}", shape)?;
+        writer.flush()?;
+
+        Ok(Self { writer })
+    }
+}
+
+impl<W: Write> Drop for ShapeGuard<W> {
+    fn drop(&mut self) {
+        // Errors can't be reported while dropping
+        let _ = write!(self.writer, "{}", CursorShape::RESET);
+        let _ = self.writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic;
+
+    use super::{CursorShape, ShapeGuard};
+
+    #[test]
+    fn shape_guard() {
+        let mut out = Vec::new();
+        let guard = ShapeGuard::set(CursorShape::Bar, &mut out).unwrap();
+        drop(guard);
+        assert_eq!(out, b"\x1b[6 q\x1b[0 q");
+
+        let mut out = Vec::new();
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let _guard = ShapeGuard::set(CursorShape::Block, &mut out).unwrap();
+            panic!("command failed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(out, b"\x1b[2 q\x1b[0 q");
+    }
+}
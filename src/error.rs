@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-use crate::{buffer::BufferError, ParserError};
+use crate::{args::ParameterError, buffer::BufferError, command::CommandError, ParserError};
 
 pub type ReplResult<T> = std::result::Result<T, ReplError>;
 
@@ -12,14 +12,49 @@ pub enum ReplError {
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
-    // #[error("Parameter error: {0}")]
-    // ArgError(#[from] ArgError),
+    #[error("Parameter error: {0}")]
+    ParameterError(#[from] ParameterError),
+
     #[error("No such command: {0}")]
     NoSuchCommandError(String),
 
+    #[cfg(feature = "background")]
+    #[error("Job error: {0}")]
+    JobError(#[from] crate::jobs::JobError),
+
+    #[error("Command error: {0}")]
+    CommandError(#[from] CommandError),
+
     #[error("Buffer error: {0}")]
     BufferError(#[from] BufferError),
 
     #[error("Parser error: {0}")]
     ParserError(#[from] ParserError),
+
+    #[error("Command '{command}' can run again in {}s", .remaining.as_secs_f64().ceil())]
+    CooldownError {
+        command: String,
+        remaining: std::time::Duration,
+    },
+
+    #[error("Skipped '{0}'")]
+    DeclinedError(String),
+
+    #[error("Transcript error: {0}")]
+    TranscriptError(std::io::Error),
+
+    #[error("Redirect error: {0}")]
+    RedirectError(std::io::Error),
+
+    #[error("Pipe error: {0}")]
+    PipeError(std::io::Error),
+}
+
+impl ReplError {
+    /// Returns if the error leaves the REPL in an unusable state, because
+    /// the terminal or the input buffer failed. Fatal errors end the REPL
+    /// instead of being reported as the output of the command.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Self::IoError(_) | Self::BufferError(_))
+    }
 }
@@ -0,0 +1,67 @@
+use crate::fuzzy;
+
+/// The state of the command finder, see
+/// [`ReplBuilder::with_finder_key`](crate::builder::ReplBuilder::with_finder_key).
+/// The candidates are the full paths of all commands, like `service dns`.
+#[derive(Debug)]
+pub(crate) struct Finder {
+    candidates: Vec<String>,
+    query: String,
+    selected: usize,
+}
+
+impl Finder {
+    pub(crate) fn new(candidates: Vec<String>) -> Self {
+        Self {
+            candidates,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub(crate) fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub(crate) fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Returns the candidates matching the query, the best match first.
+    /// Candidates with the same score keep their order.
+    pub(crate) fn matches(&self) -> Vec<&str> {
+        let mut matches: Vec<_> = self
+            .candidates
+            .iter()
+            .filter_map(|candidate| Some((fuzzy::score(&self.query, candidate)?, candidate)))
+            .collect();
+
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches.into_iter().map(|(_, c)| c.as_str()).collect()
+    }
+
+    /// Returns the selected match, if any candidate matches.
+    pub(crate) fn selection(&self) -> Option<&str> {
+        self.matches().get(self.selected).copied()
+    }
+
+    pub(crate) fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub(crate) fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub(crate) fn select_next(&mut self) {
+        if self.selected + 1 < self.matches().len() {
+            self.selected += 1;
+        }
+    }
+}
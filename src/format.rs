@@ -0,0 +1,210 @@
+use std::fmt;
+
+use crate::{
+    error::{ReplError, ReplResult},
+    translate::{English, Text, Translator},
+    ParserError,
+};
+
+/// The messages the [`DefaultFormatter`] writes for common errors, which
+/// allows customizing or localizing them, see
+/// [`ReplBuilder::with_messages`](crate::builder::ReplBuilder::with_messages).
+/// All other texts are translated by [`English`].
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::format::Messages;
+/// let messages = Messages {
+///     unknown_command: "Unbekannter Befehl".into(),
+///     did_you_mean: "{message}. Meinten Sie '{suggestion}'?".into(),
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(
+///     messages.suggest("Unbekannter Befehl", "service"),
+///     "Unbekannter Befehl. Meinten Sie 'service'?"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Messages {
+    /// Written if no command matches the input.
+    pub unknown_command: String,
+
+    /// Written if the input contains arguments the command doesn't accept.
+    pub invalid_args: String,
+
+    /// Written if an argument is missing its value.
+    pub invalid_arg_count: String,
+
+    /// Appended to an error if a similar command exists. `{message}` is
+    /// replaced with the error message and `{suggestion}` with the similar
+    /// command path.
+    pub did_you_mean: String,
+}
+
+impl Default for Messages {
+    fn default() -> Self {
+        Self {
+            unknown_command: "Unknown command".into(),
+            invalid_args: "Invalid arguments".into(),
+            invalid_arg_count: "Invalid number of args".into(),
+            did_you_mean: "{message}. Did you mean '{suggestion}'?".into(),
+        }
+    }
+}
+
+impl Messages {
+    /// Fills the [`Messages::did_you_mean`] template with the error message
+    /// and the suggested command path.
+    pub fn suggest(&self, message: &str, suggestion: &str) -> String {
+        self.did_you_mean
+            .replace("{message}", message)
+            .replace("{suggestion}", suggestion)
+    }
+}
+
+impl Translator for Messages {
+    fn translate(&self, text: Text<'_>) -> String {
+        match text {
+            Text::Error(ReplError::NoSuchCommandError(_)) => self.unknown_command.clone(),
+            Text::Error(ReplError::ParserError(ParserError::InvalidArgs)) => {
+                self.invalid_args.clone()
+            }
+            Text::Error(ReplError::ParserError(ParserError::InvalidArgCount)) => {
+                self.invalid_arg_count.clone()
+            }
+            Text::DidYouMean {
+                message,
+                suggestion,
+            } => self.suggest(message, suggestion),
+            text => English.translate(text),
+        }
+    }
+}
+
+/// Additional information about the executed input line passed to an
+/// [`OutputFormatter`].
+pub struct FormatContext<'a> {
+    /// The executed input. If commands are chained, this is the single
+    /// command the result belongs to.
+    pub input: &'a str,
+
+    /// The output prompt, see [`Prompts::output`](crate::prompt::Prompts::output).
+    pub output_prompt: &'a str,
+
+    /// The closest command path, if the input contains a misspelled command
+    /// or subcommand, like `service dns` for `service dsn`.
+    pub suggestion: Option<&'a str>,
+
+    /// The usage line of the matched command, like
+    /// `Usage: service dns [--port <port>]`, if its args are invalid.
+    pub usage: Option<&'a str>,
+
+    /// The function which renders error messages, see
+    /// [`ReplBuilder::with_error_formatter`](crate::builder::ReplBuilder::with_error_formatter).
+    /// Use [`FormatContext::error_message`] to render an error.
+    pub error_formatter: Option<&'a dyn Fn(&ReplError) -> String>,
+
+    /// The translator for built-in strings, like error messages, see
+    /// [`ReplBuilder::with_translator`](crate::builder::ReplBuilder::with_translator).
+    pub translator: &'a dyn Translator,
+}
+
+impl FormatContext<'_> {
+    /// Returns the message of the error, rendered by the error formatter if
+    /// there is one and translated otherwise.
+    pub fn error_message(&self, err: &ReplError) -> String {
+        match self.error_formatter {
+            Some(formatter) => formatter(err),
+            None => self.translator.translate(Text::Error(err)),
+        }
+    }
+}
+
+impl fmt::Debug for FormatContext<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormatContext")
+            .field("input", &self.input)
+            .field("output_prompt", &self.output_prompt)
+            .field("suggestion", &self.suggestion)
+            .field("usage", &self.usage)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether an executed command succeeded, see [`Execution`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecStatus {
+    /// The command returned `Ok`.
+    Success,
+
+    /// The command returned an error or the input couldn't be parsed. The
+    /// error message is included.
+    Failure(String),
+}
+
+/// The structured result of a single executed command, returned by
+/// [`Repl::feed_line`](crate::Repl::feed_line). Unlike the output of an
+/// [`OutputFormatter`], it leaves rendering entirely to the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    /// The names of the matched command and its parent commands, like
+    /// `["service", "dns"]`. Empty if no command matched.
+    pub command_path: Vec<String>,
+
+    /// The output of the command, if it succeeded and produced any.
+    pub output: Option<String>,
+
+    /// Whether the command succeeded. A failure carries the error message.
+    pub status: ExecStatus,
+}
+
+/// Formats the result of an executed input line into the text which is
+/// written to the terminal. The formatter receives the name of the matched
+/// command, if any, and the result of the execution, which is either the
+/// output of the command or an error.
+pub trait OutputFormatter {
+    fn format(
+        &self,
+        command: Option<&str>,
+        result: &ReplResult<Option<String>>,
+        ctx: &FormatContext<'_>,
+    ) -> String;
+}
+
+/// The default formatter writes the output prompt followed by the output of
+/// the command or the error message. Errors caused by invalid args are
+/// followed by the usage line of the command.
+#[derive(Debug, Default)]
+pub struct DefaultFormatter;
+
+impl OutputFormatter for DefaultFormatter {
+    fn format(
+        &self,
+        _command: Option<&str>,
+        result: &ReplResult<Option<String>>,
+        ctx: &FormatContext<'_>,
+    ) -> String {
+        let text = match result {
+            Ok(Some(output)) => output.clone(),
+            Ok(None) => String::new(),
+            Err(err) => ctx.error_message(err),
+        };
+
+        let text = match (result, ctx.suggestion) {
+            (Err(_), Some(suggestion)) => ctx.translator.translate(Text::DidYouMean {
+                message: &text,
+                suggestion,
+            }),
+            _ => text,
+        };
+
+        let text = match (result, ctx.usage) {
+            (Err(_), Some(usage)) => format!("{}\n{}", text, usage),
+            _ => text,
+        };
+
+        format!("{}{}", ctx.output_prompt, text)
+    }
+}
@@ -0,0 +1,55 @@
+//! A small fuzzy matcher for command names, used by the command finder, see
+//! [`ReplBuilder::with_finder_key`](crate::builder::ReplBuilder::with_finder_key).
+
+/// Returns the score of the pattern, if its chars occur in the candidate in
+/// the same order, like `sd` in `service dns`. Chars are compared ignoring
+/// case. A higher score is a better match: matched chars score one point,
+/// consecutive matches and matches at the start of a word score extra
+/// points. An empty pattern matches every candidate with a score of zero.
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::fuzzy::score;
+/// assert!(score("sd", "service dns").is_some());
+/// assert!(score("nd", "service dns").is_none());
+/// assert!(score("sd", "service dns") > score("sd", "used"));
+/// ```
+pub fn score(pattern: &str, candidate: &str) -> Option<usize> {
+    let mut pattern = pattern.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+
+    for c in candidate.chars() {
+        let matched = match pattern.peek() {
+            Some(p) => c.to_lowercase().eq(std::iter::once(*p)),
+            None => break,
+        };
+
+        if matched {
+            pattern.next();
+            score += 1;
+
+            if previous_matched {
+                score += 2;
+            }
+
+            if previous.is_none_or(is_separator) {
+                score += 3;
+            }
+        }
+
+        previous = Some(c);
+        previous_matched = matched;
+    }
+
+    match pattern.peek() {
+        Some(_) => None,
+        None => Some(score),
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, ' ' | '-' | '_')
+}
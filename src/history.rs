@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+/// The default number of entries kept in the history.
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Defines what happens with entries longer than the maximum entry length,
+/// see [`History::with_max_entry_length`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LongHistoryEntry {
+    /// Truncates the entry to the maximum length, ending with an ellipsis.
+    #[default]
+    Truncate,
+
+    /// Doesn't add the entry to the history at all.
+    Skip,
+}
+
+/// Defines which history entries are recalled with Up and Down, based on the
+/// input typed before navigating the history.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HistorySearchMode {
+    /// Recalls all entries.
+    #[default]
+    All,
+
+    /// Recalls entries starting with the input.
+    Prefix,
+
+    /// Recalls entries containing the input.
+    Substring,
+}
+
+impl HistorySearchMode {
+    /// Returns if the entry is recalled for the input.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::history::HistorySearchMode;
+    /// assert!(HistorySearchMode::Prefix.matches("git status", "git "));
+    /// assert!(!HistorySearchMode::Prefix.matches("ls", "git "));
+    /// ```
+    pub fn matches(&self, entry: &str, input: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Prefix => entry.starts_with(input),
+            Self::Substring => entry.contains(input),
+        }
+    }
+}
+
+/// A ring of previously submitted input lines. When the capacity is
+/// reached, the oldest entry is dropped for every new entry.
+#[derive(Debug)]
+pub struct History {
+    entries: VecDeque<String>,
+    max_entry_length: Option<usize>,
+    long_entry: LongHistoryEntry,
+    capacity: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_SIZE)
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_entry_length: None,
+            long_entry: LongHistoryEntry::default(),
+            capacity,
+        }
+    }
+
+    /// Caps the length of new entries to `max` chars. What happens with
+    /// longer entries is set with [`History::with_long_entry`]. The cap is
+    /// applied when an entry is pushed, independent of the length of the
+    /// input line, so a history persisted from the entries only contains
+    /// capped entries.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::history::History;
+    /// let mut history = History::new(10).with_max_entry_length(8);
+    /// history.push("service dns status");
+    ///
+    /// assert_eq!(history.iter().next().unwrap(), "service…");
+    /// ```
+    pub fn with_max_entry_length(mut self, max: usize) -> Self {
+        self.max_entry_length = Some(max);
+        self
+    }
+
+    /// Sets what happens with entries longer than the maximum entry length,
+    /// see [`LongHistoryEntry`]. The default is [`LongHistoryEntry::Truncate`].
+    pub fn with_long_entry(mut self, long_entry: LongHistoryEntry) -> Self {
+        self.long_entry = long_entry;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds an entry to the history. Consecutive duplicates are only stored
+    /// once. Long entries are capped, see [`History::with_max_entry_length`].
+    pub fn push<E>(&mut self, entry: E)
+    where
+        E: Into<String>,
+    {
+        let entry = match self.cap(entry.into()) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        if self.capacity == 0 || self.entries.back() == Some(&entry) {
+            return;
+        }
+
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(entry);
+    }
+
+    /// Returns an iterator over all entries, starting with the most recent
+    /// one.
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().rev()
+    }
+
+    /// Returns the entry at the index, where `0` is the most recent entry.
+    pub fn get(&self, index: usize) -> Option<&String> {
+        self.iter().nth(index)
+    }
+
+    /// Returns the most recent entry which starts with the prefix and is
+    /// longer than it.
+    pub fn find_prefix(&self, prefix: &str) -> Option<&String> {
+        self.iter()
+            .find(|entry| entry.len() > prefix.len() && entry.starts_with(prefix))
+    }
+
+    /// Applies the maximum entry length to the entry. Returns `None` if the
+    /// entry must be skipped.
+    fn cap(&self, entry: String) -> Option<String> {
+        let max = match self.max_entry_length {
+            Some(max) if entry.chars().count() > max => max,
+            _ => return Some(entry),
+        };
+
+        match (self.long_entry, max) {
+            (LongHistoryEntry::Skip, _) | (LongHistoryEntry::Truncate, 0) => None,
+            (LongHistoryEntry::Truncate, _) => {
+                let mut entry: String = entry.chars().take(max - 1).collect();
+                entry.push('…');
+                Some(entry)
+            }
+        }
+    }
+}
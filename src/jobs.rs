@@ -0,0 +1,119 @@
+//! Background execution of commands, enabled with the `background` feature.
+//! A line ending with `&`, like `backup --path /tmp &`, runs the command on
+//! a separate thread while the REPL keeps accepting input. Only commands
+//! with a background function, see [`Command::with_background`], can run in
+//! the background.
+//!
+//! Only a single job can run at a time. Once it finished, the REPL prints a
+//! `[job N] done` notice followed by the output of the command.
+//!
+//! When builtins are enabled, `jobs` lists all jobs of the session with
+//! their number and status, and `wait N` blocks until job `N` finished and
+//! prints its output.
+//!
+//! [`Command::with_background`]: crate::command::Command::with_background
+
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use thiserror::Error;
+
+use crate::{args::Parameters, error::ReplResult};
+
+/// The function which is called when a command runs in the background. It
+/// can't access the REPL state, as it runs on a separate thread. State which
+/// is shared with the background function must be captured, e.g. as an
+/// `Arc<Mutex<T>>`.
+pub type BackgroundFn = Arc<dyn Fn(Parameters) -> ReplResult<Option<String>> + Send + Sync>;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum JobError {
+    #[error("A background job is already running")]
+    AlreadyRunning,
+
+    #[error("Command '{0}' can't run in the background")]
+    NotSupported(String),
+
+    #[error("No such job: {0}")]
+    NoSuchJob(String),
+
+    #[error("Background job {0} panicked")]
+    Panicked(usize),
+}
+
+enum JobStatus {
+    Running(JoinHandle<ReplResult<Option<String>>>),
+    Done(String),
+}
+
+/// A command started in the background. Finished jobs keep their formatted
+/// output, so it can be printed again with the `wait` builtin.
+pub(crate) struct Job {
+    status: JobStatus,
+    line: String,
+    id: usize,
+}
+
+impl Job {
+    /// Runs the function with the parameters on a new thread.
+    pub(crate) fn spawn(id: usize, line: &str, func: BackgroundFn, params: Parameters) -> Self {
+        Self {
+            status: JobStatus::Running(thread::spawn(move || func(params))),
+            line: line.into(),
+            id,
+        }
+    }
+
+    pub(crate) fn id(&self) -> usize {
+        self.id
+    }
+
+    pub(crate) fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Returns if the job was not joined yet.
+    pub(crate) fn is_running(&self) -> bool {
+        matches!(self.status, JobStatus::Running(_))
+    }
+
+    /// Returns if the job is running, but its thread already finished.
+    pub(crate) fn is_finished(&self) -> bool {
+        match &self.status {
+            JobStatus::Running(handle) => handle.is_finished(),
+            JobStatus::Done(_) => false,
+        }
+    }
+
+    /// Returns the formatted output of a joined job.
+    pub(crate) fn output(&self) -> Option<&str> {
+        match &self.status {
+            JobStatus::Running(_) => None,
+            JobStatus::Done(output) => Some(output),
+        }
+    }
+
+    /// Waits for the job to finish and returns the result of the command.
+    /// Returns `None` if the job was joined before. The job must be
+    /// completed with its formatted output afterwards, see [`Job::complete`].
+    pub(crate) fn join(&mut self) -> Option<ReplResult<Option<String>>> {
+        let handle = match std::mem::replace(&mut self.status, JobStatus::Done(String::new())) {
+            JobStatus::Running(handle) => handle,
+            JobStatus::Done(output) => {
+                self.status = JobStatus::Done(output);
+                return None;
+            }
+        };
+
+        match handle.join() {
+            Ok(result) => Some(result),
+            Err(_) => Some(Err(JobError::Panicked(self.id).into())),
+        }
+    }
+
+    pub(crate) fn complete(&mut self, output: String) {
+        self.status = JobStatus::Done(output);
+    }
+}
@@ -1,45 +1,249 @@
 use std::{
-    collections::HashMap,
-    io::{stdin, Stdout, Write},
+    collections::{HashMap, VecDeque},
+    fs::OpenOptions,
+    io::{self, stdin, BufRead, Write},
+    process::{self, Stdio},
+    time::{Duration, Instant},
 };
 
-use nom::{
-    character::complete::{alpha1, alphanumeric1, char},
-    combinator::cut,
-    multi::many0,
-    sequence::separated_pair,
-    IResult,
+use termion::{
+    event::{Event, Key},
+    input::TermRead,
 };
-use termion::{event::Key, input::TermRead, raw::RawTerminal};
 use thiserror::Error;
 
+#[macro_use]
+mod logging;
+
+pub mod ansi;
 pub mod args;
 pub mod buffer;
 pub mod builder;
+pub mod builtin;
 pub mod command;
+pub mod completion;
+pub mod cursor;
 pub mod error;
+pub mod format;
+pub mod fuzzy;
+pub mod history;
+#[cfg(feature = "background")]
+pub mod jobs;
+pub mod output;
+pub mod prompt;
+#[cfg(feature = "signals")]
+pub mod signals;
+pub mod translate;
+pub mod vars;
+
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod finder;
+mod terminal;
+mod transcript;
 
+use args::*;
 use buffer::*;
 use builder::*;
+use builtin::*;
 use command::*;
+use completion::*;
+use cursor::*;
 use error::*;
+use finder::*;
+use format::*;
+use history::*;
+use output::*;
+use prompt::*;
+use terminal::*;
+use transcript::*;
+use translate::*;
+use vars::*;
 
 #[derive(Debug, Error)]
 pub enum ParserError {
     #[error("Empty input")]
     EmptyInput,
 
+    #[error("Invalid number of args")]
+    InvalidArgCount,
+
     #[error("Invalid arguments")]
     InvalidArgs,
+
+    #[error("Unknown argument '{0}'")]
+    UnknownArg(String),
+
+    #[error("Missing redirection target")]
+    MissingRedirectTarget,
+
+    #[error("Missing pipe target")]
+    MissingPipeTarget,
+
+    #[error("Output can't be both redirected and piped")]
+    RedirectedPipe,
+
+    #[error("Missing command in chain")]
+    MissingChainCommand,
+
+    /// A namespace was run without one of its subcommands, see
+    /// [`Command::namespace`](command::Command::namespace).
+    #[error("Missing subcommand")]
+    MissingSubcommand,
+
+    /// The value of an arg is none of its choices, see [`Arg::one_of`]. This
+    /// is a [`ParameterError::InvalidParameter`], like the errors of custom
+    /// conversions.
+    #[error(transparent)]
+    InvalidChoice(ParameterError),
+
+    #[error("Ambiguous command '{input}', candidates: {}", .candidates.join(", "))]
+    AmbiguousCommand {
+        input: String,
+        candidates: Vec<String>,
+    },
+}
+
+impl ParserError {
+    /// Returns if the error is caused by the args of a matched command.
+    pub fn is_arg_error(&self) -> bool {
+        matches!(
+            self,
+            Self::InvalidArgCount
+                | Self::InvalidArgs
+                | Self::UnknownArg(_)
+                | Self::InvalidChoice(_)
+                | Self::MissingSubcommand
+        )
+    }
+}
+
+/// How often the REPL loop checks for finished jobs, signals, ticks and the
+/// idle timeout while waiting for input.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Opening chars and their closing counterpart which are inserted
+/// automatically when auto-pairing is enabled.
+const AUTO_PAIRS: [(char, char); 5] =
+    [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')];
+
+/// The minimum fuzzy score per char of the input a command path needs to be
+/// run, see [`ReplBuilder::with_fuzzy_matching`]. Scattered matches, like
+/// `ts` in `status`, score less and are rejected.
+const FUZZY_MIN_SCORE_PER_CHAR: usize = 2;
+
+/// The default number of recent command outputs, see [`Repl::last_output`].
+pub const DEFAULT_RECENT_OUTPUTS: usize = 10;
+
+/// A function which transforms the raw input into a styled string.
+pub type Highlighter = Box<dyn Fn(&str) -> String>;
+
+/// A function which checks the raw input and returns a message describing
+/// the problem if the input is invalid.
+pub type Validator = Box<dyn Fn(&str) -> Result<(), String>>;
+
+/// A function which renders the message of a failed command, see
+/// [`ReplBuilder::with_error_formatter`].
+pub type ErrorFormatter = Box<dyn Fn(&ReplError) -> String>;
+
+/// A function which creates a new state, see [`ReplBuilder::with_state_reset`].
+pub type StateReset<S> = Box<dyn Fn() -> S>;
+
+/// A function which evaluates every submitted line instead of commands, see
+/// [`ReplBuilder::with_evaluator`].
+pub type Evaluator<S> = Box<dyn Fn(&str, &mut S) -> ReplResult<Option<String>>>;
+
+/// A function which is called periodically while the REPL waits for input,
+/// see [`ReplBuilder::with_on_tick`].
+pub type Tick<S> = Box<dyn Fn(&mut S)>;
+
+/// The events read from stdin on the event reader thread.
+type Events = std::sync::mpsc::Receiver<io::Result<Event>>;
+
+/// Counters about a REPL session, see [`Repl::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplStats {
+    /// The number of executed input lines.
+    pub commands: usize,
+
+    /// The number of executed input lines which resulted in an error.
+    pub failed: usize,
+
+    /// The number of handled keys.
+    pub keys: usize,
 }
 
 pub struct Repl<'a, S> {
+    arg_prefix: String,
     commands: HashMap<String, Command<S>>,
-    stdout: RawTerminal<Stdout>,
+    comment_char: Option<char>,
+    completion_style: CompletionStyle,
+    cursor_shape: Option<CursorShape>,
+    finder: Option<Finder>,
+    finder_key: Option<Key>,
+    welcome_message: String,
+    exit_message: String,
+    idle_timeout: Option<Duration>,
+    on_tick: Option<(Duration, Tick<S>)>,
+    last_tick: Instant,
+    events: Option<Events>,
+    output_queue: OutputQueue,
+    transcript: Option<Transcript>,
+    disabled_builtins: Vec<Builtin>,
+    exit_requested: bool,
+    continuation_char: Option<char>,
+    continued: String,
+    highlighter: Option<Highlighter>,
+    output_formatter: Box<dyn OutputFormatter>,
+    error_formatter: Option<ErrorFormatter>,
+    translator: Box<dyn Translator>,
+    stdout: Terminal,
+    script: Option<Box<dyn BufRead + 'a>>,
     stdout_output: OutputBuffer,
-    stdin_output: OutputBuffer,
     buffer: CursorBuffer,
+    hint_visible: bool,
+    history: History,
+    history_position: Option<(usize, String)>,
+    history_search: HistorySearchMode,
+    inline_output: bool,
+    use_prompt_markers: bool,
+    edit_mode: EditMode,
+    vi_mode: ViMode,
+    vi_search: String,
+    #[cfg(feature = "background")]
+    jobs: Vec<jobs::Job>,
+    #[cfg(feature = "background")]
+    next_job_id: usize,
+    live_validator: Option<Validator>,
+    submit_validator: Option<Validator>,
+    output: Vec<String>,
+    prompts: Prompts,
+    recent_outputs: VecDeque<(String, String)>,
+    recent_outputs_size: usize,
     state: &'a mut S,
+    version: String,
+    state_reset: Option<StateReset<S>>,
+    evaluator: Option<Evaluator<S>>,
+    command_prefix: Option<char>,
+    use_evaluator_fallback: bool,
+    use_help_flags: bool,
+    stats: ReplStats,
+    tab_count: usize,
+    tab_inserts_spaces: Option<usize>,
+    unknown_variable: UnknownVariable,
+    vars: Variables,
+    use_auto_pairs: bool,
+    use_autosuggest: bool,
+    use_builtins: bool,
+    use_chaining: bool,
+    use_echo_commands: bool,
+    use_exec_welcome: bool,
+    use_fuzzy_matching: bool,
+    last_runs: HashMap<Vec<String>, Instant>,
+    use_pipes: bool,
+    use_prefix_matching: bool,
+    use_strict_args: bool,
+    use_variable_expansion: bool,
 }
 
 impl<'a, S> Repl<'a, S> {
@@ -48,7 +252,9 @@ impl<'a, S> Repl<'a, S> {
     /// ### Example
     ///
     /// ```no_run
-    /// let mut repl = Repl::new(());
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::new(&mut state);
     /// repl.run();
     /// ```
     pub fn new(context: &'a mut S) -> Self {
@@ -60,7 +266,9 @@ impl<'a, S> Repl<'a, S> {
     /// ### Example
     ///
     /// ```no_run
-    /// let mut repl = Repl::builder(())
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state)
     ///     .with_version("0.1.4")
     ///     .with_prompt(">")
     ///     .build();
@@ -71,233 +279,2715 @@ impl<'a, S> Repl<'a, S> {
         ReplBuilder::new(context)
     }
 
+    /// Returns the number of columns the current prompt occupies in the
+    /// terminal, including the indicator of the vi mode, see
+    /// [`Prompts::vi_insert`]. On continued lines, this is the continuation
+    /// prompt. Escape codes, e.g. for colors, are not counted and wide chars
+    /// count as two columns.
+    pub fn prompt_len(&self) -> usize {
+        ansi::visible_width(&self.current_prompt())
+    }
+
+    /// Returns the width and height of the terminal in columns and rows.
+    /// Code depending on the terminal size must use this function, as it
+    /// never returns zero. If the size can't be queried, e.g. because stdout
+    /// is not a TTY or the REPL is detached, 80x24 is returned.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).build_cooked();
+    ///
+    /// assert_eq!(repl.terminal_dimensions(), (80, 24));
+    /// ```
+    pub fn terminal_dimensions(&self) -> (u16, u16) {
+        self.stdout.dimensions()
+    }
+
     /// List all commands in alphabetical order.
     pub fn list_commands(&self) -> Vec<&String> {
         let mut cmds: Vec<_> = self.commands.keys().collect();
-        cmds.sort_by(|a, b| a.cmp(b));
+        cmds.sort();
         cmds
     }
 
-    /// Runs the REPL. This will block until the user exists the REPL with
-    /// CTRL-C or CTROL-D for example. This behaviour can be customized.
+    /// Visits every command and subcommand depth-first, together with its
+    /// full path from the root, like `["service", "dns", "status"]`. Commands
+    /// on the same level are visited in alphabetical order.
     ///
     /// ### Example
     ///
-    /// ```no_run
-    /// let mut repl = Repl::new(());
-    /// repl.run();
     /// ```
-    pub fn run(&mut self) -> ReplResult<()> {
-        let mut stdin = stdin().keys();
-
-        loop {
-            match stdin.next() {
-                Some(result) => match result {
-                    Ok(key) => self.handle_key(key)?,
-                    Err(err) => panic!("{err}"),
-                },
-                None => continue,
-            };
-        }
+    /// # use rupl::{command::Command, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_command(
+    ///         Command::new("service", |_| Ok(None))
+    ///             .with_subcommand(Command::new("dns", |_| Ok(None))),
+    ///     )
+    ///     .build();
+    ///
+    /// let mut paths = Vec::new();
+    /// repl.walk_commands(|path, _| paths.push(path.join(" ")));
+    ///
+    /// assert_eq!(paths, vec!["service", "service dns"]);
+    /// ```
+    pub fn walk_commands<F>(&self, mut f: F)
+    where
+        F: FnMut(&[&str], &Command<S>),
+    {
+        walk(&self.commands, &mut Vec::new(), &mut f);
     }
 
-    fn handle_key(&mut self, key: Key) -> ReplResult<()> {
-        match key {
-            Key::Backspace => self.handle_backspace_key(),
-            Key::Left => self.handle_left_key(),
-            Key::Right => self.handle_right_key(),
-            Key::Up => self.handle_up_key(),
-            Key::Down => self.handle_down_key(),
-            Key::Home => self.handle_home_key(),
-            Key::End => self.handle_end_key(),
-            Key::PageUp => todo!(),
-            Key::PageDown => todo!(),
-            Key::BackTab => todo!(),
-            Key::Delete => todo!(),
-            Key::Insert => todo!(),
-            Key::F(_) => todo!(),
-            Key::Char(c) => self.handle_char_key(c),
-            Key::Alt(_) => todo!(),
-            Key::Ctrl(_) => todo!(),
-            Key::Null => todo!(),
-            Key::Esc => todo!(),
-            _ => todo!(),
-        }
-    }
+    /// Generates a Markdown document of all commands. Every command is a
+    /// section with its path as the heading, like `## service dns`, followed
+    /// by its description, its usage line and its parameters, see
+    /// [`Command::with_description`]. Top-level commands use second-level
+    /// headings below the `# Commands` title, see [`Text::DocsTitle`], and
+    /// every level of subcommands adds a level, up to the sixth. Commands
+    /// are sorted by name and subcommands follow their parent, like in
+    /// [`Repl::walk_commands`].
+    ///
+    /// The document is also printed by the `docgen` builtin, see
+    /// [`Builtin::Docgen`].
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{command::Command, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_command(
+    ///         Command::namespace("service").with_subcommand(
+    ///             Command::new("dns", |_| Ok(None)).with_description("Restarts the DNS server"),
+    ///         ),
+    ///     )
+    ///     .build();
+    ///
+    /// let docs = repl.generate_docs();
+    /// assert!(docs.contains("### service dns\n\nRestarts the DNS server"));
+    /// ```
+    pub fn generate_docs(&self) -> String {
+        let title = self.translator.translate(Text::DocsTitle);
+        let mut sections = vec![format!("# {}\n", title)];
 
-    fn handle_backspace_key(&mut self) -> ReplResult<()> {
-        // We are all the way left, pressing backspace does nothing
-        if self.buffer.get_pos() == 0 {
-            return Ok(());
-        }
+        self.walk_commands(|path, cmd| {
+            let path = path.join(" ");
+            let level = (path.split(' ').count() + 1).min(6);
+            let docs = cmd.docs(&path, &self.arg_prefix, self.translator.as_ref());
 
-        let _ = self.buffer.remove_one(Direction::Left)?;
-        self.display_stdin()
+            sections.push(format!("{} {}\n\n{}", "#".repeat(level), path, docs));
+        });
+
+        sections.join("\n")
     }
 
-    fn handle_left_key(&mut self) -> ReplResult<()> {
-        self.left()
+    /// Generates a script which completes the commands of the REPL binary in
+    /// the shell, like `myrepl service dns`. The binary name is the name the
+    /// binary is invoked with. Commands, subcommands, their args and the
+    /// choices of args are completed, args need an arg prefix, see
+    /// [`ReplBuilder::with_arg_prefix`]. The script only completes words, the
+    /// binary has to execute the commands it receives as arguments itself.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{command::Command, completion::Shell, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_command(
+    ///         Command::namespace("service").with_subcommand(Command::new("dns", |_| Ok(None))),
+    ///     )
+    ///     .build();
+    ///
+    /// // Installed with `source <(myrepl --completions)` for example
+    /// let script = repl.generate_completions(Shell::Bash, "myrepl");
+    /// assert!(script.ends_with("complete -F _myrepl 'myrepl'\n"));
+    /// ```
+    pub fn generate_completions(&self, shell: Shell, bin_name: &str) -> String {
+        let mut commands = Vec::new();
+        let mut choices = Vec::new();
+
+        self.walk_commands(|path, cmd| {
+            let path = path.join(" ");
+
+            for (word, arg_choices) in cmd.completion_choices(&self.arg_prefix) {
+                choices.push((format!("{} {}", path, word), arg_choices));
+            }
+            commands.push((path, cmd.completion_words(&self.arg_prefix)));
+        });
+
+        completion::script(shell, bin_name, &commands, &choices)
     }
 
-    fn handle_right_key(&mut self) -> ReplResult<()> {
-        self.right()
+    /// Returns if a command exists at the path of command names separated by
+    /// spaces, like `service dns`. The names must match exactly, prefix
+    /// matching doesn't apply.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{command::Command, Repl};
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state)
+    ///     .with_command(
+    ///         Command::new("service", |_| Ok(None))
+    ///             .with_subcommand(Command::new("dns", |_| Ok(None))),
+    ///     )
+    ///     .build();
+    ///
+    /// assert!(repl.has_command("service dns"));
+    /// assert!(!repl.has_command("service http"));
+    /// ```
+    pub fn has_command(&self, path: &str) -> bool {
+        let path = path.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        match resolve(&path, &self.commands, false) {
+            Ok((Some(_), rest)) => rest.is_empty(),
+            _ => false,
+        }
     }
 
-    fn handle_up_key(&mut self) -> ReplResult<()> {
-        Ok(())
+    /// Returns if leaving the REPL loop was requested with the `exit`
+    /// builtin, see [`Builtin::Exit`]. [`Repl::run`] returns afterwards.
+    /// Hosts which drive the REPL with [`Repl::handle_key`] can check this
+    /// after every key.
+    pub fn exit_requested(&self) -> bool {
+        self.exit_requested
     }
 
-    fn handle_down_key(&mut self) -> ReplResult<()> {
-        Ok(())
+    /// Runs the REPL. This will block until the user exists the REPL with
+    /// CTRL-C or CTROL-D for example. This behaviour can be customized.
+    ///
+    /// The mode depends on stdin and stdout:
+    ///
+    /// - Both are TTYs: the REPL runs interactively.
+    /// - stdin is not a TTY, e.g. `cat commands.txt | myrepl`: all lines are
+    ///   read and executed in order, see [`Repl::run_script`], then the
+    ///   function returns.
+    /// - stdin is a TTY, but stdout is not, e.g. `myrepl > out.txt`: the
+    ///   lines typed in the terminal are executed like a script, so only the
+    ///   outputs are written to stdout, without prompts or escape codes. The
+    ///   terminal echoes the typed lines. The function returns at the end of
+    ///   the input, e.g. on CTRL-D.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::new(&mut state);
+    /// repl.run();
+    /// ```
+    pub fn run(&mut self) -> ReplResult<()> {
+        // Without raw mode, the terminal does the line editing
+        if !termion::is_tty(&stdin()) || self.stdout.is_plain() {
+            return self.run_script(stdin().lock());
+        }
+
+        // The guard restores the cursor shape however the loop is left
+        let _shape = match self.cursor_shape {
+            Some(shape) => Some(cursor::ShapeGuard::set(shape, io::stdout())?),
+            None => None,
+        };
+
+        self.greet()?;
+        self.run_events()
     }
 
-    fn handle_home_key(&mut self) -> ReplResult<()> {
-        Ok(())
+    /// Writes the welcome message, if there is one, followed by the prompt,
+    /// so the prompt is visible before the first key is typed.
+    fn greet(&mut self) -> ReplResult<()> {
+        if !self.welcome_message.is_empty() {
+            let message = self.welcome_message.replace('\n', "\r\n");
+            write!(self.stdout, "{}\r\n", message)?;
+        }
+
+        self.draw_prompt()
     }
 
-    fn handle_end_key(&mut self) -> ReplResult<()> {
-        Ok(())
+    /// Draws the prompt followed by the current input. [`Repl::run`] draws
+    /// the prompt when it starts, after the welcome message. Hosts which
+    /// drive the REPL with [`Repl::handle_key`] can call this function to
+    /// draw the prompt before the first key.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state).build_with_writer(std::io::stdout());
+    /// repl.draw_prompt().unwrap();
+    /// ```
+    pub fn draw_prompt(&mut self) -> ReplResult<()> {
+        self.display_stdin()
     }
 
-    fn handle_char_key(&mut self, c: char) -> ReplResult<()> {
-        match c {
-            '\n' => self.handle_enter_key(),
-            '\t' => self.handle_tab_key(),
-            _ => {
-                self.buffer.insert(&[c])?;
-                self.display_stdin()?;
-                Ok(())
+    /// Prints the text above the prompt without clobbering the typed input,
+    /// e.g. to report the result of asynchronous work. The input line is
+    /// erased, the text is printed and the prompt is redrawn with the input
+    /// and the cursor where they were. Hosts which drive the REPL with
+    /// [`Repl::handle_key`] can call this function between keys.
+    ///
+    /// The text is also written to the transcript, if there is one. A
+    /// failed transcript write doesn't fail this function, the error is
+    /// printed once below the text instead.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state).build();
+    /// repl.notify("Download finished").unwrap();
+    /// ```
+    pub fn notify(&mut self, text: &str) -> ReplResult<()> {
+        let recorded = self.record(text);
+        self.print_above(text)?;
+
+        // The transcript fails only once, so the error is reported once
+        match recorded {
+            Ok(()) => Ok(()),
+            Err(err) => {
+                let message = self.translator.translate(Text::Error(&err));
+                self.print_above(&message)
             }
         }
     }
 
-    fn handle_enter_key(&mut self) -> ReplResult<()> {
-        // No input, do nothing
-        if self.buffer.is_empty() {
-            return self.newline();
+    /// Prints the text above the prompt, see [`Repl::notify`].
+    fn print_above(&mut self, text: &str) -> ReplResult<()> {
+        if self.stdout.is_detached() {
+            self.output.push(text.to_string());
+            return Ok(());
         }
 
-        // Else handle the input
-        self.newline()?;
-        self.parse_input()
+        self.clear_hint()?;
+        write!(
+            self.stdout,
+            "{}\r{}\r\n",
+            termion::clear::CurrentLine,
+            text.replace('\n', "\r\n")
+        )?;
+
+        self.display_stdin()
     }
 
-    fn handle_tab_key(&mut self) -> ReplResult<()> {
-        Ok(())
+    /// Returns a handle to print messages above the prompt from other
+    /// threads, e.g. from background work. The REPL loop prints the queued
+    /// messages with [`Repl::notify`], see [`output`] for the ordering
+    /// guarantees.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let repl = Repl::builder(&mut state).build();
+    /// let output = repl.output_handle();
+    /// output.print("Connected");
+    /// ```
+    pub fn output_handle(&self) -> OutputHandle {
+        self.output_queue.handle()
     }
 
-    /// Parses the input. The function tries to match commands, subcommands
-    /// and arguments.
-    fn parse_input(&mut self) -> ReplResult<()> {
-        let input = self.buffer.to_string();
-        let input = input.as_str();
-
-        // TODO (Techassi): Introduce standalone args and kv args
-        let res = match parse(input, &self.commands) {
-            Ok(res) => res,
-            Err(_) => {
-                self.stdout_output.add_to_buffer("Invalid number of args");
-                self.buffer.clear();
-                self.display_stdout()?;
-                self.newline()?;
-                return Ok(());
-            }
-        };
+    /// Runs the REPL loop, which handles the events read from stdin. The
+    /// events are read on a separate thread, so that finished background
+    /// jobs are reported, ticks run, `SIGTERM` is handled and idle sessions
+    /// time out while waiting for input.
+    fn run_events(&mut self) -> ReplResult<()> {
+        use std::sync::mpsc::{self, RecvTimeoutError};
+
+        #[cfg(feature = "signals")]
+        let termination = signals::Termination::register()?;
 
-        match res {
-            (Some(cmd), args) => {
-                if !cmd.parse_args(args) {
-                    self.stdout_output.add_to_buffer("Invalid arguments");
-                } else {
-                    self.stdout_output.add_to_buffer(cmd.run(self.state));
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for event in stdin().events() {
+                if tx.send(event).is_err() {
+                    break;
                 }
             }
-            _ => self.stdout_output.add_to_buffer("Unknown command"),
+        });
+
+        self.events = Some(rx);
+
+        let poll_interval = match &self.on_tick {
+            Some((interval, _)) => POLL_INTERVAL.min(*interval),
+            None => POLL_INTERVAL,
         };
+        let mut last_event = Instant::now();
 
-        // Clear the current input buffer after parsing the
-        // inpput and executing any matched commands.
-        self.buffer.clear();
+        loop {
+            #[cfg(feature = "signals")]
+            if termination.is_requested() {
+                log_debug!("Received SIGTERM, exiting");
+                return self.exit();
+            }
 
-        self.display_stdout()?;
-        self.newline()?;
+            if self.check_idle(last_event.elapsed())? {
+                return Ok(());
+            }
 
-        Ok(())
-    }
+            let events = self.events.as_ref().expect("events are read");
 
-    /// Displays the user input on stdout. This is achieved by first erasing
-    /// the contents of the current line, writing the refreshed input to
-    /// stdout, flushing it and then clearing the output buffer.
-    fn display_stdin(&mut self) -> ReplResult<()> {
-        // Append current input buffer, write to stdout
-        self.stdin_output.add_to_buffer(self.buffer.to_string());
-        write!(
-            self.stdout,
-            "{}",
-            self.stdin_output.output(true, self.buffer.get_pos())
-        )?;
+            match events.recv_timeout(poll_interval) {
+                Ok(result) => {
+                    last_event = Instant::now();
+                    self.handle_event(result)?
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            };
 
-        // Flush and clear current output
-        self.stdout.flush()?;
-        self.stdin_output.clear();
+            if self.exit_requested {
+                log_debug!("Exit was requested, exiting");
+                return self.exit();
+            }
 
-        Ok(())
+            self.tick()?;
+        }
     }
 
-    fn display_stdout(&mut self) -> ReplResult<()> {
-        write!(self.stdout, "{}", self.stdout_output.output(true, 0))?;
+    /// Runs the periodic work of the REPL: reports finished background jobs,
+    /// prints the messages sent with [`OutputHandle`]s and calls the tick
+    /// function, see [`ReplBuilder::with_on_tick`], if its interval elapsed
+    /// since the last call. [`Repl::run`] calls this function while waiting
+    /// for input. Hosts which drive the REPL with [`Repl::handle_key`] can
+    /// call it from their own loop.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use rupl::Repl;
+    /// let mut ticks = 0;
+    /// let mut repl = Repl::builder(&mut ticks)
+    ///     .with_on_tick(Duration::from_secs(1), |ticks| *ticks += 1)
+    ///     .build();
+    /// repl.tick().unwrap();
+    /// ```
+    pub fn tick(&mut self) -> ReplResult<()> {
+        #[cfg(feature = "background")]
+        self.poll_job()?;
+
+        while let Some(text) = self.output_queue.next() {
+            self.notify(&text)?;
+        }
 
-        self.stdout.flush()?;
-        self.stdout_output.clear();
+        if let Some((interval, tick)) = &self.on_tick {
+            if self.last_tick.elapsed() >= *interval {
+                self.last_tick = Instant::now();
+                tick(self.state);
+            }
+        }
 
         Ok(())
     }
 
-    /// Inserts a newline into stdout
-    fn newline(&mut self) -> ReplResult<()> {
-        write!(self.stdout, "{}", self.stdin_output.newline())?;
-        Ok(self.stdout.flush()?)
+    /// Checks if the REPL was idle for longer than the idle timeout, see
+    /// [`ReplBuilder::with_idle_timeout`]. On timeout, the notice and the exit
+    /// message are printed, the terminal is restored and `true` is returned.
+    /// [`Repl::run`] calls this function while waiting for input. Hosts which
+    /// drive the REPL with [`Repl::handle_key`] can call it with the time
+    /// since the last key.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use std::time::{Duration, Instant};
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state)
+    ///     .with_idle_timeout(Duration::from_secs(300))
+    ///     .build();
+    /// let last_key = Instant::now();
+    /// repl.check_idle(last_key.elapsed()).unwrap();
+    /// ```
+    pub fn check_idle(&mut self, idle: Duration) -> ReplResult<bool> {
+        match self.idle_timeout {
+            Some(timeout) if idle >= timeout => {
+                log_debug!("Idle for {:?}, exiting", timeout);
+                let notice = self.translator.translate(Text::IdleTimeout);
+                write!(self.stdout, "\r\n{}", notice)?;
+                self.exit()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
     }
 
-    /// Moves the cursor left. This moves the cursor in the
-    /// terminal and the input buffer.
-    fn left(&mut self) -> ReplResult<()> {
-        if self.buffer.move_left() {
-            write!(self.stdout, "{}", termion::cursor::Left(1))?;
-            self.stdout.flush()?
+    /// Leaves the REPL loop by printing the exit message, if there is one,
+    /// and restoring the terminal.
+    fn exit(&mut self) -> ReplResult<()> {
+        write!(self.stdout, "\r\n")?;
+
+        if !self.exit_message.is_empty() {
+            let message = self.exit_message.replace('\n', "\r\n");
+            write!(self.stdout, "{}\r\n", message)?;
         }
 
-        Ok(())
+        self.stdout.flush()?;
+        Ok(self.stdout.suspend_raw_mode()?)
     }
 
-    /// Moves the cursor right. This moves the cursor in the
-    /// terminal and the input buffer.
-    fn right(&mut self) -> ReplResult<()> {
-        if self.buffer.move_right() {
-            write!(self.stdout, "{}", termion::cursor::Right(1))?;
-            self.stdout.flush()?
+    fn handle_event(&mut self, result: std::io::Result<Event>) -> ReplResult<()> {
+        match result {
+            Ok(Event::Key(key)) => self.handle_key(key),
+            Ok(Event::Unsupported(seq)) => self.handle_unsupported(&seq),
+            Ok(Event::Mouse(_)) => Ok(()),
+            Err(err) => Err(err.into()),
         }
-
-        Ok(())
     }
-}
 
-fn parse<'a, C>(
-    input: &'a str,
-    commands: &'a HashMap<String, Command<C>>,
-) -> Result<(Option<&'a Command<C>>, Vec<(&'a str, &'a str)>), ParserError> {
-    let mut input = input;
+    /// Executes the input line by line, like a script. Empty lines are
+    /// skipped. The outputs are written to stdout, each followed by a
+    /// newline. [`Repl::run`] calls this function if stdin is not a TTY,
+    /// e.g. when commands are piped into the REPL. If enabled with
+    /// [`ReplBuilder::with_echo_commands`], every line is printed with the
+    /// prompt before its output. Commands read the following lines of the
+    /// script with [`FnContext::read_raw_line`], which are not executed.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::new(&mut state);
+    /// repl.run_script("service dns status\nversion".as_bytes());
+    /// ```
+    pub fn run_script<R: BufRead + 'a>(&mut self, reader: R) -> ReplResult<()> {
+        self.stdout.suspend_raw_mode()?;
+        self.script = Some(Box::new(reader));
 
-    let mut cmds = commands;
-    let mut cmd = None;
+        let result = self.run_script_lines();
 
-    loop {
-        let (part, rest) = match input.split_once(' ') {
-            Some(split) => split,
-            None => (input, ""),
-        };
+        self.script = None;
+        self.stdout.flush()?;
+        self.stdout.activate_raw_mode()?;
 
-        if let Some(c) = cmds.get(part) {
+        result
+    }
+
+    /// Executes the lines of the script, see [`Repl::run_script`].
+    fn run_script_lines(&mut self) -> ReplResult<()> {
+        while let Some(line) = read_raw_line(&mut self.script, None, &mut self.stdout)? {
+            let line = line.trim_end();
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if self.use_echo_commands {
+                let echo = format!("{}{}", self.prompts.primary, line);
+                self.write_line(echo)?;
+            }
+
+            for output in self.submit(line)? {
+                self.write_line(output)?;
+            }
+
+            if self.exit_requested {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Executes the input line as if it was typed and submitted with Enter,
+    /// but returns the structured results instead of writing the formatted
+    /// output. Every executed command results in one [`Execution`], so there
+    /// are multiple ones for chained commands. The line is added to the
+    /// history and counted in the stats, the output formatter is not used.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{command::Command, format::ExecStatus, Repl};
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state)
+    ///     .with_command(
+    ///         Command::new("service", |_| Ok(None))
+    ///             .with_subcommand(Command::new("dns", |_| Ok(Some("running".into())))),
+    ///     )
+    ///     .build_cooked();
+    ///
+    /// let executions = repl.feed_line("service dns");
+    ///
+    /// assert_eq!(executions[0].command_path, vec!["service", "dns"]);
+    /// assert_eq!(executions[0].output.as_deref(), Some("running"));
+    /// assert_eq!(executions[0].status, ExecStatus::Success);
+    /// ```
+    pub fn feed_line(&mut self, line: &str) -> Vec<Execution> {
+        self.execute_input(line)
+            .into_iter()
+            .map(|(line, result)| {
+                self.count(&result);
+
+                let command_path = match self.command_input(&line) {
+                    Some(input) => command_path(input, &self.commands, self.use_prefix_matching),
+                    None => Vec::new(),
+                };
+                let (output, status) = match result {
+                    Ok(output) => (output, ExecStatus::Success),
+                    Err(err) => (None, ExecStatus::Failure(err.to_string())),
+                };
+
+                Execution {
+                    command_path,
+                    output,
+                    status,
+                }
+            })
+            .collect()
+    }
+
+    /// Executes a single line of input without entering the REPL loop. The
+    /// line is executed like a submitted line, so chained commands,
+    /// redirections and pipes apply like in the REPL. The outputs of the
+    /// commands are written to stdout, as are the errors of all but the last
+    /// command. The result of the last command is returned. Afterwards the
+    /// terminal is restored, so the caller can exit right away. The welcome
+    /// message is only printed if enabled with
+    /// [`ReplBuilder::with_exec_welcome`]. This is useful to support one-shot
+    /// execution, like `myrepl -c "service dns status"`.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::new(&mut state);
+    /// repl.exec_and_exit("service dns status");
+    /// ```
+    pub fn exec_and_exit(&mut self, line: &str) -> ReplResult<Option<String>> {
+        if self.use_exec_welcome && !self.welcome_message.is_empty() {
+            let message = self.welcome_message.replace('\n', "\r\n");
+            write!(self.stdout, "{}\r\n", message)?;
+        }
+
+        let results = self.execute_input(line);
+
+        // Leave raw mode before writing, so that the output ends with a
+        // plain newline when it is piped into another program.
+        self.stdout.suspend_raw_mode()?;
+
+        let mut last = Ok(None);
+
+        for (_, result) in results {
+            self.count(&result);
+
+            if let Err(err) = &last {
+                let message = self.translator.translate(Text::Error(err));
+                writeln!(self.stdout, "{}", message)?;
+            }
+
+            match &result {
+                Err(err) if err.is_fatal() => return result,
+                Ok(Some(output)) => writeln!(self.stdout, "{}", output)?,
+                _ => {}
+            }
+
+            last = result;
+        }
+
+        self.stdout.flush()?;
+        last
+    }
+
+    /// Returns the counters of the session so far, like the number of
+    /// executed commands.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::new(&mut state);
+    /// repl.run().unwrap();
+    ///
+    /// let stats = repl.stats();
+    /// println!("{} commands, {} failed", stats.commands, stats.failed);
+    /// ```
+    pub fn stats(&self) -> ReplStats {
+        self.stats
+    }
+
+    /// Returns the most recent command output together with the input line
+    /// of the command, like `("service dns", "running")`. Commands without
+    /// output and failed commands are not recorded. See
+    /// [`ReplBuilder::with_recent_outputs`].
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::{command::Command, Repl};
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state)
+    ///     .with_command(Command::new("hello", |_| Ok(Some("Hello!".into()))))
+    ///     .build_cooked();
+    ///
+    /// repl.feed_line("hello");
+    /// assert_eq!(repl.last_output(), Some(("hello", "Hello!")));
+    /// ```
+    pub fn last_output(&self) -> Option<(&str, &str)> {
+        self.recent_outputs
+            .back()
+            .map(|(command, output)| (command.as_str(), output.as_str()))
+    }
+
+    /// Replaces the state with a new one created by the resetter, see
+    /// [`ReplBuilder::with_state_reset`]. The previous state is dropped.
+    /// Returns if a resetter is set.
+    pub fn reset_state(&mut self) -> bool {
+        match &self.state_reset {
+            Some(reset) => {
+                log_debug!("Resetting state");
+                *self.state = reset();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the session variables, see [`Variables`].
+    pub fn vars(&self) -> &Variables {
+        &self.vars
+    }
+
+    /// Returns the session variables for modification, e.g. to set initial
+    /// values before running the REPL.
+    pub fn vars_mut(&mut self) -> &mut Variables {
+        &mut self.vars
+    }
+
+    /// Returns the current input line without the prompt.
+    pub fn current_input(&self) -> String {
+        self.buffer.to_string()
+    }
+
+    /// Replaces the input line with the text, places the cursor at its end
+    /// and redraws the line. This can be used to load a previous command for
+    /// editing, e.g. one which failed.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::Repl;
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state).build_cooked();
+    /// repl.set_input("service dns").unwrap();
+    ///
+    /// assert_eq!(repl.current_input(), "service dns");
+    /// assert_eq!(repl.cursor_position(), 11);
+    /// ```
+    pub fn set_input(&mut self, text: &str) -> ReplResult<()> {
+        self.buffer.set_contents(text)?;
+        self.display_stdin()
+    }
+
+    /// Returns the current vi mode, or `None` if the vi key bindings are not
+    /// used, see [`ReplBuilder::with_edit_mode`].
+    pub fn vi_mode(&self) -> Option<ViMode> {
+        match self.edit_mode {
+            EditMode::Vi => Some(self.vi_mode),
+            EditMode::Emacs => None,
+        }
+    }
+
+    /// Returns the position of the cursor in the input line, counted in
+    /// chars from the start of the input. The prompt is not included, see
+    /// [`Repl::cursor_column`] for the column in the terminal.
+    pub fn cursor_position(&self) -> usize {
+        self.buffer.get_pos()
+    }
+
+    /// Returns the input buffer, e.g. to assert on the input and the cursor
+    /// position in tests.
+    pub fn buffer(&self) -> &CursorBuffer {
+        &self.buffer
+    }
+
+    /// Returns the current input line as it is displayed in the terminal:
+    /// the prompt, followed by the highlighted input and the dimmed
+    /// autosuggestion, if any. The string contains no cursor movements, see
+    /// [`Repl::cursor_column`] to place the cursor.
+    pub fn render_line(&self) -> String {
+        let input = self.buffer.to_string();
+        let mut line = self.current_prompt();
+
+        match &self.highlighter {
+            Some(highlighter) => line.push_str(&highlighter(&input)),
+            None => line.push_str(&input),
+        }
+
+        if let Some(suggestion) = self.suggestion() {
+            line.push_str(&format!(
+                "{}{}{}",
+                termion::style::Faint,
+                suggestion,
+                termion::style::Reset
+            ));
+        }
+
+        line
+    }
+
+    /// Returns the message of the live validator for the current input, if
+    /// the input is invalid. See [`ReplBuilder::with_live_validator`].
+    pub fn validation_hint(&self) -> Option<String> {
+        let validator = self.live_validator.as_ref()?;
+        validator(&self.buffer.to_string()).err()
+    }
+
+    /// Returns the column of the cursor in the rendered line, which is the
+    /// width of the prompt and the input before the cursor. Escape codes are
+    /// not counted, see [`Repl::prompt_len`]. On continued lines, the width
+    /// of the continuation prompt is used.
+    pub fn cursor_column(&self) -> usize {
+        let input: String = self
+            .buffer
+            .to_string()
+            .chars()
+            .take(self.buffer.get_pos())
+            .collect();
+
+        ansi::visible_width(&self.current_prompt()) + ansi::visible_width(&input)
+    }
+
+    /// Returns the continuation prompt while multi-line input is entered and
+    /// the primary prompt otherwise. With the vi key bindings, the indicator
+    /// of the vi mode comes first, see [`Prompts::vi_insert`].
+    fn current_prompt(&self) -> String {
+        let prompt = match self.continued.is_empty() {
+            true => &self.prompts.primary,
+            false => &self.prompts.continuation,
+        };
+
+        format!("{}{}", self.vi_indicator(), prompt)
+    }
+
+    /// Returns the indicator of the vi mode, which is empty with the emacs
+    /// key bindings.
+    fn vi_indicator(&self) -> &str {
+        match (self.edit_mode, self.vi_mode) {
+            (EditMode::Emacs, _) => "",
+            (EditMode::Vi, ViMode::Normal) => &self.prompts.vi_normal,
+            (EditMode::Vi, _) => &self.prompts.vi_insert,
+        }
+    }
+
+    /// Returns the formatted output of all commands executed since the last
+    /// call. Output is only collected by a REPL built with
+    /// [`ReplBuilder::build_cooked`], otherwise it is written to stdout.
+    pub fn take_output(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Handles the keys one after another, see [`Repl::handle_key`]. This is
+    /// mostly useful to drive a REPL built with [`ReplBuilder::build_cooked`]
+    /// in tests. Stops at the first key which fails.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::Repl;
+    /// # use termion::event::Key;
+    /// let mut state = ();
+    /// let mut repl = Repl::builder(&mut state).build_cooked();
+    ///
+    /// repl.feed_keys(&[Key::Char('a'), Key::Char('b'), Key::Left, Key::Char('X')])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(repl.buffer().to_string(), "aXb");
+    /// assert_eq!(repl.buffer().get_pos(), 2);
+    /// ```
+    pub fn feed_keys(&mut self, keys: &[Key]) -> ReplResult<()> {
+        keys.iter().try_for_each(|key| self.handle_key(*key))
+    }
+
+    /// Handles a single key press, like editing the input or executing the
+    /// input line on enter. [`Repl::run`] calls this for every key read from
+    /// stdin. A REPL built with [`ReplBuilder::build_cooked`] is driven by
+    /// calling this function directly.
+    pub fn handle_key(&mut self, key: Key) -> ReplResult<()> {
+        log_trace!("Handling key {:?}", key);
+        self.stats.keys += 1;
+
+        #[cfg(feature = "background")]
+        self.poll_job()?;
+
+        if self.finder.is_some() {
+            return self.handle_finder_key(key);
+        }
+
+        if Some(key) == self.finder_key {
+            return self.open_finder();
+        }
+
+        if self.edit_mode == EditMode::Vi {
+            match (self.vi_mode, key) {
+                (ViMode::Insert, Key::Esc) => return self.enter_vi_normal_mode(),
+                (ViMode::Normal, Key::Esc) => return Ok(()),
+                (ViMode::Normal, Key::Char('\n' | '\r')) => self.vi_mode = ViMode::Insert,
+                (ViMode::Normal, Key::Char(c)) => return self.handle_vi_key(c),
+                (ViMode::Search, key) => return self.handle_vi_search_key(key),
+                _ => {}
+            }
+        }
+
+        if key != Key::Char('\t') {
+            self.tab_count = 0;
+        }
+
+        if !matches!(key, Key::Up | Key::Down) {
+            self.history_position = None;
+        }
+
+        match key {
+            Key::Backspace => self.handle_backspace_key(),
+            Key::Left => self.handle_left_key(),
+            Key::Right => self.handle_right_key(),
+            Key::Up => self.handle_up_key(),
+            Key::Down => self.handle_down_key(),
+            Key::Home => self.handle_home_key(),
+            Key::End => self.handle_end_key(),
+            Key::Delete => self.handle_delete_key(),
+            Key::Char(c) => self.handle_char_key(c),
+            Key::Alt(c) => self.handle_alt_key(c),
+            Key::Ctrl(c) => self.handle_ctrl_key(c),
+            // Keys without a binding are ignored
+            _ => Ok(()),
+        }
+    }
+
+    /// Handles escape sequences termion does not know about. Currently,
+    /// these are only the clipboard keys, see [`clipboard`].
+    #[cfg(feature = "clipboard")]
+    fn handle_unsupported(&mut self, seq: &[u8]) -> ReplResult<()> {
+        match clipboard::parse_key(seq) {
+            Some(clipboard::ClipboardKey::Copy) => {
+                clipboard::copy(self.buffer.to_string());
+                Ok(())
+            }
+            Some(clipboard::ClipboardKey::Paste) => match clipboard::paste() {
+                Some(chars) => {
+                    self.buffer.insert(&chars)?;
+                    self.display_stdin()
+                }
+                None => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    fn handle_unsupported(&mut self, _seq: &[u8]) -> ReplResult<()> {
+        Ok(())
+    }
+
+    fn handle_alt_key(&mut self, c: char) -> ReplResult<()> {
+        match c {
+            'u' => self.buffer.upcase_word()?,
+            'l' => self.buffer.downcase_word()?,
+            'c' => self.buffer.capitalize_word()?,
+            'b' => return self.word_left(),
+            'f' => return self.word_right(),
+            'd' => {
+                self.buffer.remove_word_right()?;
+            }
+            _ => return Ok(()),
+        }
+
+        self.display_stdin()
+    }
+
+    fn handle_ctrl_key(&mut self, c: char) -> ReplResult<()> {
+        match c {
+            't' => {
+                if self.buffer.transpose()? {
+                    self.display_stdin()?;
+                }
+
+                Ok(())
+            }
+            'w' => {
+                self.buffer.remove_word_left()?;
+                self.display_stdin()
+            }
+            'z' => {
+                if self.buffer.undo()? {
+                    self.display_stdin()?;
+                }
+
+                Ok(())
+            }
+            'y' => {
+                if self.buffer.redo()? {
+                    self.display_stdin()?;
+                }
+
+                Ok(())
+            }
+            'c' => self.cancel_input(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Discards the input, including continued lines, and starts over on a
+    /// new line, like Ctrl-C in a shell.
+    fn cancel_input(&mut self) -> ReplResult<()> {
+        self.clear_hint()?;
+        self.clear_suggestion()?;
+
+        self.continued.clear();
+        self.buffer.clear();
+        self.newline()
+    }
+
+    /// Switches to vi normal mode. Like in vi, the cursor moves onto the
+    /// last inserted char.
+    fn enter_vi_normal_mode(&mut self) -> ReplResult<()> {
+        self.vi_mode = ViMode::Normal;
+
+        match self.buffer.move_left() {
+            true => self.display_stdin(),
+            false => Ok(()),
+        }
+    }
+
+    /// Handles a key in vi normal mode, see [`ViMode::Normal`]. Unknown keys
+    /// are ignored.
+    fn handle_vi_key(&mut self, c: char) -> ReplResult<()> {
+        if !matches!(c, 'j' | 'k') {
+            self.history_position = None;
+        }
+
+        match c {
+            'h' => {
+                self.buffer.move_left();
+            }
+            'l' => {
+                self.buffer.move_right();
+            }
+            'w' => {
+                self.buffer.move_next_word_start();
+            }
+            'b' => {
+                self.buffer.move_word_left();
+            }
+            '0' => {
+                self.buffer.move_to_start();
+            }
+            '$' => {
+                self.buffer.move_to_end();
+            }
+            'i' => self.vi_mode = ViMode::Insert,
+            'a' => {
+                self.vi_mode = ViMode::Insert;
+                self.buffer.move_right();
+            }
+            'x' => {
+                if self.buffer.current().is_some() {
+                    self.buffer.remove_one(Direction::Right)?;
+                }
+            }
+            'k' => self.handle_up_key()?,
+            'j' => self.handle_down_key()?,
+            '/' => {
+                self.vi_mode = ViMode::Search;
+                self.vi_search.clear();
+            }
+            _ => return Ok(()),
+        }
+
+        // In normal mode, the cursor stays on a char
+        if self.vi_mode == ViMode::Normal && self.buffer.current().is_none() {
+            self.buffer.move_left();
+        }
+
+        self.display_stdin()
+    }
+
+    /// Handles a key while typing the pattern of a history search, see
+    /// [`ViMode::Search`].
+    fn handle_vi_search_key(&mut self, key: Key) -> ReplResult<()> {
+        match key {
+            Key::Char('\n' | '\r') => return self.search_vi_history(),
+            Key::Char(c) => self.vi_search.push(c),
+            Key::Backspace if !self.vi_search.is_empty() => {
+                self.vi_search.pop();
+            }
+            Key::Backspace | Key::Esc | Key::Ctrl('c') => self.vi_mode = ViMode::Normal,
+            _ => return Ok(()),
+        }
+
+        self.display_stdin()
+    }
+
+    /// Recalls the most recent history entry containing the search pattern
+    /// and switches back to normal mode. The bell rings if no entry matches.
+    /// Like Up, the recalled entry becomes the position in the history.
+    fn search_vi_history(&mut self) -> ReplResult<()> {
+        self.vi_mode = ViMode::Normal;
+
+        let pattern = std::mem::take(&mut self.vi_search);
+        let found = self
+            .history
+            .iter()
+            .enumerate()
+            .find(|(_, entry)| entry.contains(&pattern))
+            .map(|(index, entry)| (index, entry.clone()));
+
+        match found {
+            Some((index, entry)) => {
+                self.history_position = Some((index, self.buffer.to_string()));
+                self.buffer.set_contents(&entry)?;
+                self.buffer.move_to_start();
+            }
+            None => write!(self.stdout, "\x07")?,
+        }
+
+        self.display_stdin()
+    }
+
+    /// Opens the command finder, see [`ReplBuilder::with_finder_key`].
+    fn open_finder(&mut self) -> ReplResult<()> {
+        let mut paths = Vec::new();
+        self.walk_commands(|path, _| paths.push(path.join(" ")));
+        self.finder = Some(Finder::new(paths));
+
+        if !self.stdout.is_detached() {
+            write!(self.stdout, "{}", termion::screen::ToAlternateScreen)?;
+        }
+
+        self.draw_finder()
+    }
+
+    fn handle_finder_key(&mut self, key: Key) -> ReplResult<()> {
+        let finder = match &mut self.finder {
+            Some(finder) => finder,
+            None => return Ok(()),
+        };
+
+        match key {
+            Key::Char('\n') => {
+                let selection = finder.selection().map(String::from);
+                return self.close_finder(selection);
+            }
+            Key::Esc | Key::Ctrl('c') | Key::Ctrl('g') => return self.close_finder(None),
+            Key::Char(c) => finder.push(c),
+            Key::Backspace => finder.pop(),
+            Key::Up | Key::Ctrl('p') => finder.select_previous(),
+            Key::Down | Key::Ctrl('n') => finder.select_next(),
+            _ => return Ok(()),
+        }
+
+        self.draw_finder()
+    }
+
+    /// Closes the command finder and inserts the selected command followed by
+    /// a space at the cursor, if there is one.
+    fn close_finder(&mut self, selection: Option<String>) -> ReplResult<()> {
+        self.finder = None;
+
+        if !self.stdout.is_detached() {
+            write!(self.stdout, "{}", termion::screen::ToMainScreen)?;
+        }
+
+        if let Some(command) = selection {
+            let chars: Vec<char> = command.chars().chain([' ']).collect();
+            self.buffer.insert(&chars)?;
+        }
+
+        self.display_stdin()
+    }
+
+    /// Draws the query of the command finder in the first row, followed by
+    /// as many matches as fit on the screen. The selected match is inverted.
+    fn draw_finder(&mut self) -> ReplResult<()> {
+        let finder = match &self.finder {
+            Some(finder) if !self.stdout.is_detached() => finder,
+            _ => return Ok(()),
+        };
+
+        let (width, height) = self.terminal_dimensions();
+        let query = format!("{}{}", self.prompts.search, finder.query());
+
+        write!(
+            self.stdout,
+            "{}{}{}",
+            termion::clear::All,
+            termion::cursor::Goto(1, 1),
+            ansi::truncate(&query, width as usize)
+        )?;
+
+        for (i, name) in finder
+            .matches()
+            .into_iter()
+            .take(height as usize - 1)
+            .enumerate()
+        {
+            let name = ansi::truncate(name, width as usize);
+
+            match i == finder.selected() {
+                true => write!(
+                    self.stdout,
+                    "\r\n{}{}{}",
+                    termion::style::Invert,
+                    name,
+                    termion::style::NoInvert
+                )?,
+                false => write!(self.stdout, "\r\n{}", name)?,
+            }
+        }
+
+        let column = ansi::visible_width(&query).min(width as usize - 1) + 1;
+        write!(self.stdout, "{}", termion::cursor::Goto(column as u16, 1))?;
+
+        Ok(self.stdout.flush()?)
+    }
+
+    fn handle_backspace_key(&mut self) -> ReplResult<()> {
+        // We are all the way left, pressing backspace does nothing
+        if self.buffer.get_pos() == 0 {
+            return Ok(());
+        }
+
+        let _ = self.buffer.remove_one(Direction::Left)?;
+        self.display_stdin()
+    }
+
+    fn handle_delete_key(&mut self) -> ReplResult<()> {
+        // We are all the way right, pressing delete does nothing
+        if self.buffer.current().is_none() {
+            return Ok(());
+        }
+
+        let _ = self.buffer.remove_one(Direction::Right)?;
+        self.display_stdin()
+    }
+
+    fn handle_left_key(&mut self) -> ReplResult<()> {
+        self.left()
+    }
+
+    fn handle_right_key(&mut self) -> ReplResult<()> {
+        if self.suggestion().is_some() {
+            return self.accept_suggestion();
+        }
+
+        self.right()
+    }
+
+    /// Recalls the previous history entry matching the input typed before
+    /// navigating the history, see [`HistorySearchMode`].
+    fn handle_up_key(&mut self) -> ReplResult<()> {
+        let (start, input) = match self.history_position.take() {
+            Some((index, input)) => (index + 1, input),
+            None => (0, self.buffer.to_string()),
+        };
+
+        let found = self
+            .history
+            .iter()
+            .enumerate()
+            .skip(start)
+            .find(|(_, entry)| self.history_search.matches(entry, &input))
+            .map(|(index, entry)| (index, entry.clone()));
+
+        match found {
+            Some((index, entry)) => {
+                self.history_position = Some((index, input));
+                self.set_input(&entry)
+            }
+            None => {
+                // Stay at the oldest matching entry
+                self.history_position = start.checked_sub(1).map(|index| (index, input));
+                Ok(())
+            }
+        }
+    }
+
+    /// Recalls the next history entry matching the input typed before
+    /// navigating the history. Past the most recent one, the typed input is
+    /// restored.
+    fn handle_down_key(&mut self) -> ReplResult<()> {
+        let (end, input) = match self.history_position.take() {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        let found = self
+            .history
+            .iter()
+            .enumerate()
+            .take(end)
+            .filter(|(_, entry)| self.history_search.matches(entry, &input))
+            .last()
+            .map(|(index, entry)| (index, entry.clone()));
+
+        match found {
+            Some((index, entry)) => {
+                self.history_position = Some((index, input));
+                self.set_input(&entry)
+            }
+            None => self.set_input(&input),
+        }
+    }
+
+    fn handle_home_key(&mut self) -> ReplResult<()> {
+        Ok(())
+    }
+
+    fn handle_end_key(&mut self) -> ReplResult<()> {
+        if self.suggestion().is_some() {
+            return self.accept_suggestion();
+        }
+
+        if self.buffer.move_to_end() {
+            self.display_stdin()?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_char_key(&mut self, c: char) -> ReplResult<()> {
+        match c {
+            '\n' | '\r' => self.handle_enter_key(),
+            '\t' => self.handle_tab_key(),
+            _ if self.use_auto_pairs => self.insert_paired(c),
+            _ => {
+                self.buffer.insert(&[c])?;
+                self.display_stdin()?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Inserts the char with auto-pairing. Typing an opening char also
+    /// inserts the closing char after the cursor. Typing a closing char
+    /// which is already under the cursor moves over it instead. Quotes
+    /// directly after a word, like in `don't`, are not paired.
+    fn insert_paired(&mut self, c: char) -> ReplResult<()> {
+        let is_closing = AUTO_PAIRS.iter().any(|(_, closing)| *closing == c);
+        let pair = AUTO_PAIRS.iter().find(|(opening, _)| *opening == c);
+        let after_word = self.buffer.previous().is_some_and(char::is_alphanumeric);
+
+        match pair {
+            _ if is_closing && self.buffer.current() == Some(c) => {
+                self.buffer.move_right();
+            }
+            Some((opening, closing)) if !(opening == closing && after_word) => {
+                self.buffer.insert(&[*opening, *closing])?;
+                self.buffer.move_left();
+            }
+            _ => self.buffer.insert(&[c])?,
+        }
+
+        self.display_stdin()
+    }
+
+    fn handle_enter_key(&mut self) -> ReplResult<()> {
+        self.clear_hint()?;
+
+        // No input, do nothing
+        if self.buffer.is_empty() && self.continued.is_empty() {
+            return self.newline();
+        }
+
+        // Else handle the input. The suggestion should not stay visible
+        // on the submitted line.
+        self.clear_suggestion()?;
+
+        let input = format!("{}{}", self.continued, self.buffer);
+
+        // A line ending with the continuation char is continued on the next
+        // line before the complete input is validated
+        if let Some(line) = self
+            .continuation_char
+            .and_then(|c| strip_continuation(&input, c))
+        {
+            self.continued = line.to_string();
+            self.buffer.clear();
+            write!(self.stdout, "\r\n")?;
+            return self.display_stdin();
+        }
+
+        // A rejected line stays in the buffer, so that it can be fixed
+        if let Some(Err(message)) = self
+            .submit_validator
+            .as_ref()
+            .map(|validator| validator(&input))
+        {
+            return self.reject_input(message);
+        }
+
+        // With inline output, the output is written over the input line
+        // instead of the line below it. The input is complete, so the line
+        // starts with the primary prompt even after continued lines.
+        if !self.inline_output {
+            let prompt = format!("{}{}", self.vi_indicator(), self.prompts.primary);
+            write!(self.stdout, "\r\n{}", prompt)?;
+        }
+
+        self.parse_input()
+    }
+
+    /// Displays the message of the submit validator below the input line and
+    /// displays the unchanged input again below it.
+    fn reject_input(&mut self, message: String) -> ReplResult<()> {
+        log_debug!("Input rejected: {}", message);
+
+        if self.stdout.is_detached() {
+            self.output.push(message);
+            return Ok(());
+        }
+
+        write!(
+            self.stdout,
+            "\r\n{}{}{}\r\n",
+            termion::color::Fg(termion::color::Red),
+            message,
+            termion::color::Fg(termion::color::Reset)
+        )?;
+
+        self.display_stdin()
+    }
+
+    /// Completes the command name before the cursor. A single candidate is
+    /// inserted followed by a space. Multiple candidates are completed up to
+    /// their common prefix. If this doesn't extend the input, the candidates
+    /// are listed according to the [`CompletionStyle`].
+    fn handle_tab_key(&mut self) -> ReplResult<()> {
+        let input: String = self
+            .buffer
+            .to_string()
+            .chars()
+            .take(self.buffer.get_pos())
+            .collect();
+
+        let input = match strip_command_prefix(&input, self.command_prefix) {
+            Some(input) => input,
+            None => return self.insert_tab(),
+        };
+
+        let candidates = completion::candidates(input, &self.commands, &self.arg_prefix);
+        let partial = input.rsplit(' ').next().unwrap_or_default();
+
+        let completion = match candidates.as_slice() {
+            [] => return self.insert_tab(),
+            [candidate] => format!("{} ", &candidate[partial.len()..]),
+            _ => completion::common_prefix(&candidates)[partial.len()..].to_string(),
+        };
+
+        if !completion.is_empty() {
+            let chars: Vec<char> = completion.chars().collect();
+            self.buffer.insert(&chars)?;
+            return self.display_stdin();
+        }
+
+        self.tab_count += 1;
+
+        match (self.completion_style, self.tab_count) {
+            (CompletionStyle::BellThenList, 1) => write!(self.stdout, "\x07")?,
+            _ => {
+                let list = candidates.join("  ");
+
+                self.clear_suggestion()?;
+                write!(self.stdout, "\r\n{}\r\n", list)?;
+                self.display_stdin()?;
+            }
+        }
+
+        Ok(self.stdout.flush()?)
+    }
+
+    /// Inserts the configured fallback for Tab presses without completion
+    /// candidates, see [`ReplBuilder::with_tab_inserts_spaces`].
+    fn insert_tab(&mut self) -> ReplResult<()> {
+        let chars = match self.tab_inserts_spaces {
+            Some(0) => vec!['\t'],
+            Some(count) => vec![' '; count],
+            None => return Ok(()),
+        };
+
+        self.buffer.insert(&chars)?;
+        self.display_stdin()
+    }
+
+    /// Parses the input. The function tries to match commands, subcommands
+    /// and arguments.
+    fn parse_input(&mut self) -> ReplResult<()> {
+        let input = format!("{}{}", std::mem::take(&mut self.continued), self.buffer);
+        let failed = self.stats.failed;
+        let outputs = self.submit(&input)?;
+        let exit_code = u8::from(self.stats.failed > failed);
+
+        for (i, output) in outputs.into_iter().enumerate() {
+            if self.stdout.is_detached() {
+                self.output.push(output);
+                continue;
+            }
+
+            if i > 0 {
+                self.stdout_output.add_to_buffer("\r\n");
+            }
+
+            self.stdout_output.add_to_buffer(output);
+        }
+
+        // Clear the current input buffer after parsing the
+        // inpput and executing any matched commands.
+        self.buffer.clear();
+
+        self.display_stdout(exit_code)?;
+        self.newline()?;
+
+        Ok(())
+    }
+
+    /// Adds the input line to the history, executes it and returns the
+    /// formatted outputs. Fatal errors are returned instead, see
+    /// [`ReplError::is_fatal`].
+    fn submit(&mut self, input: &str) -> ReplResult<Vec<String>> {
+        let mut recorded = self.record(&format!("{}{}", self.prompts.primary, input));
+        let mut outputs = Vec::new();
+
+        for (line, result) in self.execute_input(input) {
+            match result {
+                Err(err) if err.is_fatal() => return Err(err),
+                result => outputs.push(self.report(&line, result)),
+            }
+        }
+
+        for output in &outputs {
+            recorded = recorded.and_then(|_| self.record(output));
+        }
+
+        // The transcript fails only once, so the error is reported once
+        if let Err(err) = recorded {
+            outputs.push(self.translator.translate(Text::Error(&err)));
+        }
+
+        Ok(outputs)
+    }
+
+    /// Appends the text to the transcript, if there is one, see
+    /// [`ReplBuilder::with_transcript_file`].
+    fn record(&mut self, text: &str) -> ReplResult<()> {
+        match &mut self.transcript {
+            Some(transcript) => transcript.write(text),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the input line to the history and executes it. Returns every
+    /// executed command line together with its result.
+    fn execute_input(&mut self, input: &str) -> Vec<(String, ReplResult<Option<String>>)> {
+        self.history.push(input);
+
+        // Comments are kept in the history, but never executed
+        let input = match self.comment_char {
+            Some(c) => strip_comment(input, c),
+            None => input,
+        };
+
+        if input.trim().is_empty() {
+            return Vec::new();
+        }
+
+        if self.evaluates_all() {
+            let line = input.trim().to_string();
+            let result = self.evaluate(&line);
+            return vec![(line, result)];
+        }
+
+        // The history keeps the line as it was typed, while the expanded
+        // line is executed
+        let line = self.expand(input);
+
+        match self.use_chaining {
+            true => match split_chain(&line) {
+                Ok(chain) => self.execute_chain(&chain),
+                Err(err) => vec![(line, Err(err.into()))],
+            },
+            false => {
+                let result = self.execute_line(&line);
+                vec![(line, result)]
+            }
+        }
+    }
+
+    /// Executes the chained commands one after another and returns their
+    /// results. A command chained with `&&` only runs if the last executed
+    /// command succeeded, one chained with `||` only if it failed. A command
+    /// succeeded if it returned `Ok`.
+    fn execute_chain(
+        &mut self,
+        chain: &[(Chain, &str)],
+    ) -> Vec<(String, ReplResult<Option<String>>)> {
+        let mut results = Vec::new();
+        let mut success = None;
+
+        for (chain, line) in chain {
+            match (chain, success) {
+                (Chain::OnSuccess, Some(false)) | (Chain::OnFailure, Some(true)) => continue,
+                _ => {}
+            }
+
+            let result = self.execute_line(line);
+            success = Some(result.is_ok());
+            results.push((line.to_string(), result));
+
+            if self.exit_requested {
+                break;
+            }
+        }
+
+        results
+    }
+
+    /// Counts the result of the executed line in the session stats and
+    /// formats it with the output formatter.
+    fn report(&mut self, line: &str, result: ReplResult<Option<String>>) -> String {
+        self.count(&result);
+
+        let input = self.command_input(line);
+
+        let command = input
+            .and_then(|input| resolve(input, &self.commands, self.use_prefix_matching).ok())
+            .and_then(|(cmd, _)| cmd);
+
+        // The output prompt of the command takes precedence over the global one
+        let output_prompt = command
+            .and_then(|cmd| cmd.output_prompt.clone())
+            .unwrap_or_else(|| self.prompts.output.clone());
+
+        let suggestion = match (&result, input) {
+            (Err(ReplError::NoSuchCommandError(_) | ReplError::ParserError(_)), Some(input)) => {
+                completion::suggest(input, &self.commands).map(|suggestion| {
+                    match self.command_prefix {
+                        Some(prefix) => format!("{}{}", prefix, suggestion),
+                        None => suggestion,
+                    }
+                })
+            }
+            _ => None,
+        };
+
+        // Invalid args of a matched command are explained by its usage, unless
+        // a misspelled subcommand is the more likely cause
+        let usage = match (&result, command, input, &suggestion) {
+            (Err(ReplError::ParserError(err)), Some(cmd), Some(input), None)
+                if err.is_arg_error() =>
+            {
+                let path = command_path(input, &self.commands, self.use_prefix_matching);
+                Some(cmd.args_usage(&path.join(" "), &self.arg_prefix, self.translator.as_ref()))
+            }
+            _ => None,
+        };
+
+        let ctx = FormatContext {
+            input: line,
+            output_prompt: &output_prompt,
+            suggestion: suggestion.as_deref(),
+            usage: usage.as_deref(),
+            error_formatter: self.error_formatter.as_deref(),
+            translator: self.translator.as_ref(),
+        };
+
+        self.output_formatter
+            .format(command.map(|cmd| cmd.name().as_str()), &result, &ctx)
+    }
+
+    /// Returns the command part of the executed line, without the command
+    /// prefix, a trailing `&`, an output redirection or a pipe. Returns
+    /// `None` if the line is passed to the evaluator.
+    fn command_input<'l>(&self, line: &'l str) -> Option<&'l str> {
+        let input = strip_command_prefix(line, self.command_prefix)?;
+        if self.evaluates_all() {
+            return None;
+        }
+
+        #[cfg(feature = "background")]
+        let input = strip_background(input).unwrap_or(input);
+
+        let input = match self.use_pipes {
+            true => split_pipe(input).map_or(input, |(input, _)| input),
+            false => input,
+        };
+
+        Some(split_redirect(input).map_or(input, |(input, _)| input))
+    }
+
+    /// Counts the executed input line in the session stats.
+    fn count(&mut self, result: &ReplResult<Option<String>>) {
+        self.stats.commands += 1;
+
+        if result.is_err() {
+            self.stats.failed += 1;
+        }
+    }
+
+    /// Expands session variables in the input, if enabled.
+    fn expand(&self, input: &str) -> String {
+        match self.use_variable_expansion {
+            true => self.vars.expand(input, self.unknown_variable),
+            false => input.into(),
+        }
+    }
+
+    /// Executes the input line and routes the output of the command. Output
+    /// which is redirected into a file or piped into a program is not
+    /// returned.
+    fn execute_line(&mut self, input: &str) -> ReplResult<Option<String>> {
+        #[cfg(feature = "background")]
+        if let Some(input) = strip_background(input) {
+            return self.spawn_job(input);
+        }
+
+        let (input, program) = match self.use_pipes {
+            true => split_pipe(input)?,
+            false => (input, None),
+        };
+
+        let (input, redirect) = split_redirect(input)?;
+        if redirect.is_some() && program.is_some() {
+            return Err(ParserError::RedirectedPipe.into());
+        }
+
+        let output = self.execute(input)?;
+
+        // Like in a shell, the target file is created (or truncated) even if
+        // the command has no output
+        if let Some(redirect) = redirect {
+            log_debug!("Redirecting output into '{}'", redirect.path);
+            redirect
+                .write(output.as_deref())
+                .map_err(ReplError::RedirectError)?;
+            return Ok(None);
+        }
+
+        let output = match output {
+            Some(output) => output,
+            None => return Ok(None),
+        };
+
+        if let Some(program) = program {
+            log_debug!("Piping output into '{}'", program);
+            self.pipe(program, &output)?;
+            return Ok(None);
+        }
+
+        Ok(Some(output))
+    }
+
+    /// Runs the command in the background. Only a single job can run at a
+    /// time.
+    #[cfg(feature = "background")]
+    fn spawn_job(&mut self, line: &str) -> ReplResult<Option<String>> {
+        let input = strip_command_prefix(line, self.command_prefix)
+            .ok_or_else(|| ReplError::NoSuchCommandError(line.into()))?;
+
+        let parsed = parse(
+            input,
+            &self.commands,
+            &self.arg_prefix,
+            self.use_prefix_matching,
+            self.use_strict_args,
+        )?;
+
+        let (cmd, params) = match parsed {
+            Some((cmd, params)) => (cmd, params?),
+            None => return Err(ReplError::NoSuchCommandError(input.into())),
+        };
+
+        let func = match &cmd.background {
+            Some(func) => func.clone(),
+            None => return Err(jobs::JobError::NotSupported(cmd.name().clone()).into()),
+        };
+
+        if self.jobs.iter().any(jobs::Job::is_running) {
+            return Err(jobs::JobError::AlreadyRunning.into());
+        }
+
+        let name = cmd.name().clone();
+        let run = Run::of(
+            cmd,
+            command_path(input, &self.commands, self.use_prefix_matching),
+        );
+        self.permit_run(run)?;
+
+        self.next_job_id += 1;
+        let id = self.next_job_id;
+
+        log_debug!("Starting background job {} for '{}'", id, name);
+        self.jobs.push(jobs::Job::spawn(id, input, func, params));
+
+        Ok(Some(self.translator.translate(Text::JobStarted(id))))
+    }
+
+    /// Displays the notice and the output of a background job, if one
+    /// finished.
+    #[cfg(feature = "background")]
+    fn poll_job(&mut self) -> ReplResult<()> {
+        let index = match self.jobs.iter().position(jobs::Job::is_finished) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        let output = self.finish_job(index);
+        let notice = self
+            .translator
+            .translate(Text::JobDone(self.jobs[index].id()));
+
+        if self.stdout.is_detached() {
+            self.output.push(notice);
+            self.output.push(output);
+            return Ok(());
+        }
+
+        match output.is_empty() {
+            true => self.notify(&notice),
+            false => self.notify(&format!("{}\n{}", notice, output)),
+        }
+    }
+
+    /// Waits for the job at the index in the job table to finish and stores
+    /// its formatted output. Returns the output.
+    #[cfg(feature = "background")]
+    fn finish_job(&mut self, index: usize) -> String {
+        let line = self.jobs[index].line().to_string();
+
+        if let Some(result) = self.jobs[index].join() {
+            let output = self.report(&line, result);
+            self.jobs[index].complete(output);
+        }
+
+        self.jobs[index].output().unwrap_or_default().to_string()
+    }
+
+    /// Executes the builtin command the input refers to. Returns `None` if
+    /// builtins are disabled, the input is no enabled builtin or a command
+    /// with the same name exists.
+    fn builtin(&mut self, input: &str) -> Option<ReplResult<Option<String>>> {
+        let name = input.split_whitespace().next()?;
+
+        if !self.use_builtins || self.commands.contains_key(name) {
+            return None;
+        }
+
+        let builtin = Builtin::from_name(name)?;
+        if self.disabled_builtins.contains(&builtin) {
+            return None;
+        }
+
+        match builtin {
+            Builtin::Docgen => Some(self.docgen_builtin(input)),
+            Builtin::Exit => Some(self.exit_builtin(input)),
+            Builtin::Help => Some(self.help_builtin(input)),
+            Builtin::Version => Some(self.version_builtin(input)),
+            Builtin::Last => Some(self.last_builtin(input)),
+            Builtin::Reset if self.state_reset.is_some() => Some(self.reset_builtin(input)),
+            #[cfg(feature = "background")]
+            Builtin::Jobs => Some(self.list_jobs(input)),
+            #[cfg(feature = "background")]
+            Builtin::Wait => Some(self.wait_job(input)),
+            _ => None,
+        }
+    }
+
+    /// Returns the last `N` outputs for `last N`, oldest first. With fewer
+    /// recent outputs, all of them are returned. `last` returns the most
+    /// recent output only.
+    fn last_builtin(&mut self, input: &str) -> ReplResult<Option<String>> {
+        let count = match input.split_whitespace().collect::<Vec<_>>()[..] {
+            [_] => 1,
+            [_, count] => count.parse().map_err(|_| ParameterError::InvalidValue {
+                value: count.into(),
+                ty: "usize",
+            })?,
+            _ => return Err(ParserError::InvalidArgCount.into()),
+        };
+
+        let skip = self.recent_outputs.len().saturating_sub(count);
+        let outputs: Vec<_> = self
+            .recent_outputs
+            .iter()
+            .skip(skip)
+            .map(|(_, output)| output.as_str())
+            .collect();
+
+        match outputs.is_empty() {
+            true => Ok(None),
+            false => Ok(Some(outputs.join("\n"))),
+        }
+    }
+
+    /// Returns the documentation of all commands, see
+    /// [`Repl::generate_docs`].
+    fn docgen_builtin(&mut self, input: &str) -> ReplResult<Option<String>> {
+        if input.split_whitespace().count() != 1 {
+            return Err(ParserError::InvalidArgCount.into());
+        }
+
+        Ok(Some(self.generate_docs()))
+    }
+
+    /// Requests to leave the REPL loop, see [`Repl::exit_requested`].
+    fn exit_builtin(&mut self, input: &str) -> ReplResult<Option<String>> {
+        if input.split_whitespace().count() != 1 {
+            return Err(ParserError::InvalidArgCount.into());
+        }
+
+        self.exit_requested = true;
+        Ok(None)
+    }
+
+    /// Returns the help of the command for `help COMMAND`, see
+    /// [`ReplBuilder::with_help_flags`]. `help` lists the sorted top-level
+    /// commands with their descriptions instead.
+    fn help_builtin(&mut self, input: &str) -> ReplResult<Option<String>> {
+        let path = input.split_once(' ').map_or("", |(_, path)| path.trim());
+
+        if path.is_empty() {
+            let mut names: Vec<_> = self.commands.keys().collect();
+            names.sort();
+
+            let width = names
+                .iter()
+                .map(|name| ansi::visible_width(name))
+                .max()
+                .unwrap_or(0);
+            let lines: Vec<_> = names
+                .into_iter()
+                .map(|name| match &self.commands[name].description {
+                    Some(description) => {
+                        let padding = " ".repeat(width - ansi::visible_width(name));
+                        format!("{}{}  {}", name, padding, description)
+                    }
+                    None => name.clone(),
+                })
+                .collect();
+
+            return Ok(Some(lines.join("\n")));
+        }
+
+        match resolve(path, &self.commands, self.use_prefix_matching)? {
+            (Some(cmd), "") => {
+                let path = command_path(path, &self.commands, self.use_prefix_matching);
+                Ok(Some(cmd.help(
+                    &path.join(" "),
+                    &self.arg_prefix,
+                    self.translator.as_ref(),
+                )))
+            }
+            _ => Err(ReplError::NoSuchCommandError(path.into())),
+        }
+    }
+
+    /// Returns the version, see [`ReplBuilder::with_version`].
+    fn version_builtin(&mut self, input: &str) -> ReplResult<Option<String>> {
+        if input.split_whitespace().count() != 1 {
+            return Err(ParserError::InvalidArgCount.into());
+        }
+
+        Ok(Some(self.version.clone()))
+    }
+
+    /// Replaces the state with a new one, see [`Repl::reset_state`].
+    fn reset_builtin(&mut self, input: &str) -> ReplResult<Option<String>> {
+        if input.split_whitespace().count() != 1 {
+            return Err(ParserError::InvalidArgCount.into());
+        }
+
+        self.reset_state();
+        Ok(None)
+    }
+
+    /// Lists all background jobs of the session with their number, status
+    /// and input line, like `[1] running  backup /tmp`. Prints nothing if no
+    /// job was started yet.
+    #[cfg(feature = "background")]
+    fn list_jobs(&mut self, input: &str) -> ReplResult<Option<String>> {
+        if input.split_whitespace().count() != 1 {
+            return Err(ParserError::InvalidArgCount.into());
+        }
+
+        let lines: Vec<_> = self
+            .jobs
+            .iter()
+            .map(|job| {
+                let status = match job.is_running() && !job.is_finished() {
+                    true => self.translator.translate(Text::JobRunning),
+                    false => self.translator.translate(Text::JobFinished),
+                };
+
+                format!("[{}] {:<8} {}", job.id(), status, job.line())
+            })
+            .collect();
+
+        match lines.is_empty() {
+            true => Ok(None),
+            false => Ok(Some(lines.join("\n"))),
+        }
+    }
+
+    /// Blocks until the job, like `wait 2`, finished and returns its output.
+    /// The output of jobs which finished already is returned again.
+    #[cfg(feature = "background")]
+    fn wait_job(&mut self, input: &str) -> ReplResult<Option<String>> {
+        let id = match input.split_whitespace().collect::<Vec<_>>()[..] {
+            [_, id] => id,
+            _ => return Err(ParserError::InvalidArgCount.into()),
+        };
+
+        let index = id
+            .parse::<usize>()
+            .ok()
+            .and_then(|id| self.jobs.iter().position(|job| job.id() == id))
+            .ok_or_else(|| jobs::JobError::NoSuchJob(id.into()))?;
+
+        let output = self.finish_job(index);
+
+        match output.is_empty() {
+            true => Ok(None),
+            false => Ok(Some(output)),
+        }
+    }
+
+    /// Spawns the program and writes the output into its stdin. Raw mode is
+    /// left while the program runs, so it can use the terminal as usual.
+    fn pipe(&mut self, program: &str, output: &str) -> ReplResult<()> {
+        write!(self.stdout, "{}\r", termion::clear::CurrentLine)?;
+        self.stdout.flush()?;
+
+        self.stdout.suspend_raw_mode()?;
+        let result = spawn_pipe(program, output);
+        self.stdout.activate_raw_mode()?;
+
+        result.map_err(ReplError::PipeError)
+    }
+
+    /// Executes a single line of input by matching it against the known
+    /// commands and running the matched command.
+    fn execute(&mut self, line: &str) -> ReplResult<Option<String>> {
+        let input = match strip_command_prefix(line, self.command_prefix) {
+            Some(input) => input,
+            None => return self.evaluate(line.trim()),
+        };
+
+        if let Some(result) = self.builtin(input) {
+            return result;
+        }
+
+        if let Some(help) = self.help(input) {
+            return Ok(Some(help));
+        }
+
+        let parsed = parse(
+            input,
+            &self.commands,
+            &self.arg_prefix,
+            self.use_prefix_matching,
+            self.use_strict_args,
+        );
+
+        let (cmd, params) = match parsed {
+            Ok(Some((cmd, Ok(params)))) => (cmd, params),
+            Ok(Some((cmd, Err(err)))) => {
+                log_debug!("Invalid args for command '{}': {}", cmd.name(), err);
+                return Err(err.into());
+            }
+            Ok(None) if self.evaluator.is_some() && self.command_prefix.is_none() => {
+                return self.evaluate(input.trim())
+            }
+            Ok(None) if self.use_fuzzy_matching => return self.execute_fuzzy(input),
+            Ok(None) => {
+                log_debug!("No command matches input '{}'", input);
+                return Err(ReplError::NoSuchCommandError(input.into()));
+            }
+            Err(err) => {
+                log_debug!("Failed to parse input '{}': {}", input, err);
+                return Err(err.into());
+            }
+        };
+
+        // The function is shared with clones of the command, so a handle of
+        // it outlives the borrow of the commands, see `CommandFn`
+        let func = match &cmd.func {
+            Some(func) => func.clone(),
+            None => return Err(ParserError::MissingSubcommand.into()),
+        };
+
+        let name = cmd.name().clone();
+        let run = Run::of(
+            cmd,
+            command_path(input, &self.commands, self.use_prefix_matching),
+        );
+        self.permit_run(run)?;
+
+        log_debug!("Executing command '{}' with {:?}", name, params);
+
+        let (script, events, stdout) = (&mut self.script, self.events.as_ref(), &mut self.stdout);
+        let mut reader = || read_raw_line(script, events, stdout);
+        let ctx = FnContext::new(params, self.state, &mut self.vars).with_reader(&mut reader);
+        let result = (func.borrow_mut())(ctx);
+
+        match &result {
+            Ok(Some(output)) => self.record_output(line, output),
+            Ok(None) => {}
+            Err(err) => {
+                log_debug!("Command '{}' failed: {}", name, err);
+            }
+        }
+
+        result
+    }
+
+    /// Executes the input with its first word replaced by the command path
+    /// which matches it best, see [`ReplBuilder::with_fuzzy_matching`].
+    fn execute_fuzzy(&mut self, input: &str) -> ReplResult<Option<String>> {
+        let input = input.trim_start();
+        let (word, rest) = input.split_once(' ').unwrap_or((input, ""));
+
+        let min_score = word.chars().count() * FUZZY_MIN_SCORE_PER_CHAR;
+
+        let mut matches = Vec::new();
+        self.walk_commands(|path, _| {
+            let path = path.join(" ");
+            match fuzzy::score(word, &path) {
+                Some(score) if score >= min_score => matches.push((score, path)),
+                _ => {}
+            }
+        });
+
+        let best = match matches.iter().map(|(score, _)| *score).max() {
+            Some(best) => best,
+            None => {
+                log_debug!("No command matches input '{}'", input);
+                return Err(ReplError::NoSuchCommandError(input.into()));
+            }
+        };
+
+        // Walked paths are sorted, so a parent precedes its subcommands
+        let mut candidates = matches
+            .into_iter()
+            .filter(|(score, _)| *score == best)
+            .map(|(_, path)| path);
+        let winner = candidates.next().unwrap_or_default();
+        let candidates: Vec<String> = candidates
+            .filter(|path| !path.starts_with(&format!("{} ", winner)))
+            .collect();
+
+        if !candidates.is_empty() {
+            return Err(ParserError::AmbiguousCommand {
+                input: word.into(),
+                candidates: [winner].into_iter().chain(candidates).collect(),
+            }
+            .into());
+        }
+
+        log_debug!("Fuzzy matched '{}' as '{}'", word, winner);
+        let line = format!("{} {}", winner, rest);
+        let line = match self.command_prefix {
+            Some(prefix) => format!("{}{}", prefix, line.trim_end()),
+            None => line.trim_end().to_string(),
+        };
+
+        self.execute(&line)
+    }
+
+    /// Decides if the parsed command may run, in the foreground or in
+    /// the background. A command in its cooldown is rejected, see
+    /// [`Command::with_cooldown`], and a dangerous command runs only if the
+    /// user confirms it, see [`Command::dangerous`]. A permitted run starts
+    /// the cooldown.
+    fn permit_run(&mut self, run: Run) -> ReplResult<()> {
+        let Run {
+            path,
+            name,
+            cooldown,
+            dangerous,
+        } = run;
+
+        // Commands on different levels may share a name, so the last runs are
+        // tracked by path
+        if let Some(cooldown) = cooldown {
+            let elapsed = self.last_runs.get(&path).map(Instant::elapsed);
+
+            if let Some(remaining) = elapsed.and_then(|elapsed| cooldown.checked_sub(elapsed)) {
+                return Err(ReplError::CooldownError {
+                    command: name,
+                    remaining,
+                });
+            }
+        }
+
+        if dangerous && !self.confirm(&self.translator.translate(Text::Confirm(&name)))? {
+            log_debug!("Running command '{}' was declined", name);
+            return Err(ReplError::DeclinedError(name));
+        }
+
+        if cooldown.is_some() {
+            self.last_runs.insert(path, Instant::now());
+        }
+
+        Ok(())
+    }
+
+    /// Asks the question and reads the answer as a raw input line, see
+    /// [`FnContext::read_raw_line`]. Returns if the answer is `y` or `yes`,
+    /// ignoring case. Without an answer, like in a REPL built with
+    /// [`ReplBuilder::build_cooked`], the question is declined.
+    fn confirm(&mut self, question: &str) -> ReplResult<bool> {
+        match self.script.is_some() || self.stdout.is_detached() {
+            true => self.write_line(question.to_string())?,
+            // In raw mode, the question replaces the line below the input.
+            // Reading the answer starts a new line with `\r\n`.
+            false => {
+                write!(
+                    self.stdout,
+                    "{}\r{}",
+                    termion::clear::CurrentLine,
+                    question.replace('\n', "\r\n")
+                )?;
+                self.stdout.flush()?;
+            }
+        }
+
+        let answer = read_raw_line(&mut self.script, self.events.as_ref(), &mut self.stdout)?;
+        let answer = answer.unwrap_or_default().trim().to_lowercase();
+
+        Ok(matches!(answer.as_str(), "y" | "yes"))
+    }
+
+    /// Returns the help of the matched command if the input ends with a help
+    /// flag, see [`ReplBuilder::with_help_flags`].
+    fn help(&self, input: &str) -> Option<String> {
+        if !self.use_help_flags {
+            return None;
+        }
+
+        let (cmd, rest) = resolve(input, &self.commands, self.use_prefix_matching).ok()?;
+        let cmd = cmd?;
+
+        if !cmd.is_help_flag(rest.split_whitespace().last()?, &self.arg_prefix) {
+            return None;
+        }
+
+        let path = command_path(input, &self.commands, self.use_prefix_matching).join(" ");
+        Some(cmd.help(&path, &self.arg_prefix, self.translator.as_ref()))
+    }
+
+    /// Returns if every line is passed to the evaluator instead of matching
+    /// it against the commands, see [`ReplBuilder::with_evaluator`].
+    fn evaluates_all(&self) -> bool {
+        self.evaluator.is_some() && !self.use_evaluator_fallback
+    }
+
+    /// Passes the line to the evaluator, see [`ReplBuilder::with_evaluator`].
+    /// Without an evaluator, the line is an unknown command.
+    fn evaluate(&mut self, line: &str) -> ReplResult<Option<String>> {
+        let evaluator = match &self.evaluator {
+            Some(evaluator) => evaluator,
+            None => return Err(ReplError::NoSuchCommandError(line.into())),
+        };
+
+        log_debug!("Evaluating '{}'", line);
+        let result = evaluator(line, self.state);
+
+        if let Ok(Some(output)) = &result {
+            self.record_output(line, output);
+        }
+
+        result
+    }
+
+    /// Adds the output of the command to the recent outputs, dropping the
+    /// oldest one when the capacity is reached.
+    fn record_output(&mut self, input: &str, output: &str) {
+        if self.recent_outputs_size == 0 {
+            return;
+        }
+
+        if self.recent_outputs.len() == self.recent_outputs_size {
+            self.recent_outputs.pop_front();
+        }
+
+        self.recent_outputs
+            .push_back((input.trim().to_string(), output.to_string()));
+    }
+
+    /// Writes a line of output followed by a newline, or collects it when
+    /// the REPL is detached.
+    fn write_line(&mut self, line: String) -> ReplResult<()> {
+        if self.stdout.is_detached() {
+            self.output.push(line);
+            return Ok(());
+        }
+
+        Ok(writeln!(self.stdout, "{}", line)?)
+    }
+
+    /// Removes the hint of the live validator below the input line, if it is
+    /// visible. The cursor stays where it is.
+    fn clear_hint(&mut self) -> ReplResult<()> {
+        if !self.hint_visible {
+            return Ok(());
+        }
+
+        write!(
+            self.stdout,
+            "{}{}{}",
+            termion::cursor::Down(1),
+            termion::clear::CurrentLine,
+            termion::cursor::Up(1)
+        )?;
+        self.hint_visible = false;
+
+        Ok(())
+    }
+
+    /// Displays the user input on stdout. This is achieved by first erasing
+    /// the contents of the current line, writing the rendered line and
+    /// placing the cursor at its column.
+    fn display_stdin(&mut self) -> ReplResult<()> {
+        if self.vi_mode == ViMode::Search {
+            write!(
+                self.stdout,
+                "{}\r/{}",
+                termion::clear::CurrentLine,
+                self.vi_search
+            )?;
+            return Ok(self.stdout.flush()?);
+        }
+
+        let mut line = self.render_line();
+        let cursor_column = self.cursor_column();
+
+        if self.use_prompt_markers {
+            // The rendered line always starts with the prompt
+            line.insert_str(self.current_prompt().len(), ansi::COMMAND_START);
+            line.insert_str(0, ansi::PROMPT_START);
+        }
+
+        write!(self.stdout, "{}\r{}", termion::clear::CurrentLine, line)?;
+
+        match self.validation_hint() {
+            Some(hint) => {
+                // A wrapped hint would move the input line up
+                let width = self.terminal_dimensions().0 as usize;
+                let hint = ansi::truncate(&hint, width);
+                write!(
+                    self.stdout,
+                    "\r\n{}{}{}{}{}",
+                    termion::clear::CurrentLine,
+                    termion::color::Fg(termion::color::Red),
+                    hint,
+                    termion::color::Fg(termion::color::Reset),
+                    termion::cursor::Up(1)
+                )?;
+                self.hint_visible = true;
+            }
+            None => self.clear_hint()?,
+        }
+
+        write!(self.stdout, "\r")?;
+        if cursor_column != 0 {
+            write!(
+                self.stdout,
+                "{}",
+                termion::cursor::Right(cursor_column as u16)
+            )?;
+        }
+
+        Ok(self.stdout.flush()?)
+    }
+
+    /// Returns the remaining part of the most recent history entry which
+    /// starts with the current input. Suggestions are only made when the
+    /// cursor is at the end of the input.
+    fn suggestion(&self) -> Option<String> {
+        if !self.use_autosuggest
+            || self.buffer.is_empty()
+            || self.buffer.get_pos() != self.buffer.len()
+        {
+            return None;
+        }
+
+        let input = self.buffer.to_string();
+        self.history
+            .find_prefix(&input)
+            .map(|entry| entry[input.len()..].to_string())
+    }
+
+    /// Inserts the current suggestion into the input buffer.
+    fn accept_suggestion(&mut self) -> ReplResult<()> {
+        if let Some(suggestion) = self.suggestion() {
+            let chars: Vec<char> = suggestion.chars().collect();
+            self.buffer.insert(&chars)?;
+            self.display_stdin()?;
+        }
+
+        Ok(())
+    }
+
+    /// Erases the displayed suggestion after the cursor.
+    fn clear_suggestion(&mut self) -> ReplResult<()> {
+        if self.suggestion().is_some() {
+            write!(self.stdout, "{}", termion::clear::UntilNewline)?;
+            self.stdout.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Displays the output of the submitted line. With prompt markers, the
+    /// output is marked together with the exit code of the line.
+    fn display_stdout(&mut self, exit_code: u8) -> ReplResult<()> {
+        match self.use_prompt_markers {
+            true => write!(
+                self.stdout,
+                "{}{}{}",
+                ansi::COMMAND_EXECUTED,
+                self.stdout_output.render_with_prompt(0),
+                ansi::command_finished(exit_code)
+            )?,
+            false => write!(self.stdout, "{}", self.stdout_output.render_with_prompt(0))?,
+        }
+
+        self.stdout.flush()?;
+        self.stdout_output.clear();
+
+        Ok(())
+    }
+
+    /// Inserts a newline into stdout
+    fn newline(&mut self) -> ReplResult<()> {
+        match self.use_prompt_markers {
+            true => write!(
+                self.stdout,
+                "\r\n{}{}{}",
+                ansi::PROMPT_START,
+                self.current_prompt(),
+                ansi::COMMAND_START
+            )?,
+            false => write!(self.stdout, "\r\n{}", self.current_prompt())?,
+        }
+        Ok(self.stdout.flush()?)
+    }
+
+    /// Moves the cursor left. This moves the cursor in the
+    /// terminal and the input buffer.
+    fn left(&mut self) -> ReplResult<()> {
+        if self.buffer.move_left() {
+            write!(self.stdout, "{}", termion::cursor::Left(1))?;
+            self.stdout.flush()?
+        }
+
+        Ok(())
+    }
+
+    /// Moves the cursor to the start of the previous word. This moves the
+    /// cursor in the terminal and the input buffer.
+    fn word_left(&mut self) -> ReplResult<()> {
+        if self.buffer.move_word_left() {
+            self.display_stdin()?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the cursor to the end of the next word. This moves the cursor
+    /// in the terminal and the input buffer.
+    fn word_right(&mut self) -> ReplResult<()> {
+        if self.buffer.move_word_right() {
+            self.display_stdin()?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the cursor right. This moves the cursor in the
+    /// terminal and the input buffer.
+    fn right(&mut self) -> ReplResult<()> {
+        if self.buffer.move_right() {
+            write!(self.stdout, "{}", termion::cursor::Right(1))?;
+            self.stdout.flush()?
+        }
+
+        Ok(())
+    }
+}
+
+/// An output redirection at the end of an input line, like `> out.txt` or
+/// `>> out.txt`.
+#[derive(Debug, PartialEq)]
+struct Redirect<'a> {
+    path: &'a str,
+    append: bool,
+}
+
+impl Redirect<'_> {
+    /// Writes the output into the target file. The file is created if it
+    /// doesn't exist. Existing content is either truncated or appended to.
+    fn write(&self, output: Option<&str>) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(self.append)
+            .truncate(!self.append)
+            .open(self.path)?;
+
+        match output {
+            Some(output) => writeln!(file, "{}", output),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Splits a trailing output redirection off the input. Everything after the
+/// first `>` (or `>>`) outside of quotes is treated as the target path.
+/// Quotes around the path are removed.
+fn split_redirect(input: &str) -> Result<(&str, Option<Redirect<'_>>), ParserError> {
+    let i = match find_unquoted(input, '>') {
+        Some(i) => i,
+        None => return Ok((input, None)),
+    };
+
+    let target = &input[i + 1..];
+    let (target, append) = match target.strip_prefix('>') {
+        Some(target) => (target, true),
+        None => (target, false),
+    };
+
+    let path = unquote(target.trim());
+    if path.is_empty() {
+        return Err(ParserError::MissingRedirectTarget);
+    }
+
+    Ok((input[..i].trim_end(), Some(Redirect { path, append })))
+}
+
+/// How a chained command depends on the previous one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Chain {
+    /// The command runs unconditionally, chained with `;`.
+    Always,
+
+    /// The command runs if the previous one succeeded, chained with `&&`.
+    OnSuccess,
+
+    /// The command runs if the previous one failed, chained with `||`.
+    OnFailure,
+}
+
+/// Splits the input into chained commands, which are separated by `;`, `&&`
+/// or `||` outside of quotes. The first command always runs. A trailing `;`
+/// is allowed, all other separators must be followed by a command.
+fn split_chain(input: &str) -> Result<Vec<(Chain, &str)>, ParserError> {
+    let mut commands = Vec::new();
+    let mut chain = Chain::Always;
+    let mut quote = None;
+    let mut start = 0;
+
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let next = match (quote, c, chars.peek()) {
+            (None, '"' | '\'', _) => {
+                quote = Some(c);
+                continue;
+            }
+            (Some(q), c, _) if q == c => {
+                quote = None;
+                continue;
+            }
+            (None, ';', _) => Chain::Always,
+            (None, '&', Some((_, '&'))) => Chain::OnSuccess,
+            (None, '|', Some((_, '|'))) => Chain::OnFailure,
+            _ => continue,
+        };
+
+        let command = input[start..i].trim();
+        if command.is_empty() {
+            return Err(ParserError::MissingChainCommand);
+        }
+
+        commands.push((chain, command));
+        chain = next;
+        start = i + 1;
+
+        // Skip the second char of && and ||
+        if next != Chain::Always {
+            chars.next();
+            start += 1;
+        }
+    }
+
+    let command = input[start..].trim();
+    match (command.is_empty(), chain) {
+        (true, Chain::Always) if !commands.is_empty() => {}
+        (true, _) => return Err(ParserError::MissingChainCommand),
+        (false, _) => commands.push((chain, command)),
+    }
+
+    Ok(commands)
+}
+
+/// Strips a trailing `&` outside of quotes, which runs the command in the
+/// background. Returns `None` if the input doesn't end with `&`.
+#[cfg(feature = "background")]
+fn strip_background(input: &str) -> Option<&str> {
+    let input = input.trim_end();
+    let rest = input.strip_suffix('&')?;
+
+    match find_unquoted(input, '&') == Some(rest.len()) {
+        true => Some(rest.trim_end()),
+        false => None,
+    }
+}
+
+/// Removes a comment from the input. A comment starts with the comment char
+/// at the start of a word outside of quotes and ends at the end of the line.
+fn strip_comment(input: &str, comment: char) -> &str {
+    let mut quote = None;
+    let mut previous = None;
+
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, c) if c == comment && previous.is_none_or(char::is_whitespace) => {
+                return &input[..i];
+            }
+            _ => {}
+        }
+
+        previous = Some(c);
+    }
+
+    input
+}
+
+/// Strips the continuation char from the end of the input. Returns `None` if
+/// the input doesn't end with it or it is quoted.
+fn strip_continuation(input: &str, continuation: char) -> Option<&str> {
+    let line = input.strip_suffix(continuation)?;
+    let mut quote = None;
+
+    for c in line.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            _ => {}
+        }
+    }
+
+    quote.is_none().then_some(line)
+}
+
+/// Splits a pipe into an external program off the input. Everything after
+/// the first `|` outside of quotes is treated as the program, which is run
+/// by the shell.
+fn split_pipe(input: &str) -> Result<(&str, Option<&str>), ParserError> {
+    let i = match find_unquoted(input, '|') {
+        Some(i) => i,
+        None => return Ok((input, None)),
+    };
+
+    let program = input[i + 1..].trim();
+    if program.is_empty() {
+        return Err(ParserError::MissingPipeTarget);
+    }
+
+    Ok((input[..i].trim_end(), Some(program)))
+}
+
+/// Spawns the program using the shell, writes the output into its stdin
+/// and waits for it to exit. Like in a shell, the pipe fails if the program
+/// fails, e.g. because it doesn't exist.
+fn spawn_pipe(program: &str, output: &str) -> io::Result<()> {
+    let mut child = process::Command::new("sh")
+        .arg("-c")
+        .arg(program)
+        .stdin(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The program might exit before reading all of its input, which is
+        // not an error
+        match writeln!(stdin, "{}", output) {
+            Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err),
+            _ => {}
+        }
+    }
+
+    let status = child.wait()?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!("'{}' {}", program, status))),
+    }
+}
+
+/// Returns the byte index of the first occurrence of the char outside of
+/// single or double quotes.
+fn find_unquoted(input: &str, needle: char) -> Option<usize> {
+    let mut quote = None;
+
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, c) if c == needle => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Removes a matching pair of single or double quotes around the input.
+fn unquote(input: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = input
+            .strip_prefix(quote)
+            .and_then(|input| input.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+
+    input
+}
+
+/// Descends into the command tree as long as the input matches commands and
+/// subcommands. Returns the last matched command and the remaining input.
+/// With prefix matching, a word also matches the only command starting with
+/// it.
+fn resolve<'a, C>(
+    input: &'a str,
+    commands: &'a HashMap<String, Command<C>>,
+    prefix_matching: bool,
+) -> Result<(Option<&'a Command<C>>, &'a str), ParserError> {
+    let mut input = input;
+
+    let mut cmds = commands;
+    let mut cmd = None;
+
+    loop {
+        let (part, rest) = match input.split_once(' ') {
+            Some(split) => split,
+            None => (input, ""),
+        };
+
+        if let Some(c) = lookup(part, cmds, prefix_matching)? {
             cmds = &c.sub;
             cmd = Some(c);
             input = rest;
@@ -307,18 +2997,282 @@ fn parse<'a, C>(
         break;
     }
 
-    if cmd.is_none() {
-        return Ok((None, vec![]));
-    }
+    Ok((cmd, input))
+}
+
+/// Reads the next raw input line, see [`FnContext::read_raw_line`]. While a
+/// script runs, the line is read from the script. While [`Repl::run`] reads
+/// the events, the line is assembled from the key events. Otherwise it is
+/// read from stdin with raw mode suspended, so the terminal echoes the line.
+fn read_raw_line(
+    script: &mut Option<Box<dyn BufRead + '_>>,
+    events: Option<&Events>,
+    stdout: &mut Terminal,
+) -> ReplResult<Option<String>> {
+    let mut line = String::new();
 
-    let (_, args) = match arg_pair_parser(input) {
-        Ok(pairs) => pairs,
-        Err(_) => return Err(ParserError::InvalidArgs),
+    let read = match script {
+        Some(script) => BufRead::read_line(script, &mut line)?,
+        None if !stdout.reads_stdin() => return Ok(None),
+        // The event reader thread consumes stdin while the REPL runs
+        None if events.is_some() => {
+            return read_event_line(events.expect("events are read"), stdout)
+        }
+        None => {
+            write!(stdout, "\r\n")?;
+            stdout.flush()?;
+            stdout.suspend_raw_mode()?;
+            let read = BufRead::read_line(&mut stdin().lock(), &mut line);
+            stdout.activate_raw_mode()?;
+            read?
+        }
     };
 
-    Ok((cmd, args))
+    if read == 0 {
+        return Ok(None);
+    }
+
+    let len = line.trim_end_matches(['\r', '\n']).len();
+    line.truncate(len);
+
+    Ok(Some(line))
+}
+
+/// Reads a raw input line from the events read on the event reader thread,
+/// see [`read_raw_line`]. The typed characters are echoed, as raw mode stays
+/// active. `Ctrl-D` on an empty line ends the input.
+fn read_event_line(events: &Events, stdout: &mut Terminal) -> ReplResult<Option<String>> {
+    let mut line = String::new();
+
+    write!(stdout, "\r\n")?;
+    stdout.flush()?;
+
+    loop {
+        let key = match events.recv() {
+            Ok(Ok(Event::Key(key))) => key,
+            Ok(Ok(_)) => continue,
+            Ok(Err(err)) => return Err(err.into()),
+            Err(_) => return Ok(None),
+        };
+
+        match key {
+            Key::Char('\n' | '\r') => break,
+            Key::Char(c) => {
+                line.push(c);
+                write!(stdout, "{}", c)?;
+            }
+            Key::Backspace => {
+                if line.pop().is_some() {
+                    write!(stdout, "\x08 \x08")?;
+                }
+            }
+            Key::Ctrl('d') if line.is_empty() => return Ok(None),
+            _ => continue,
+        }
+
+        stdout.flush()?;
+    }
+
+    Ok(Some(line))
+}
+
+/// Strips the command prefix, see [`ReplBuilder::with_command_prefix`], from
+/// the input. Returns `None` if the input is no command because the prefix
+/// is missing. Without a prefix, every input is a command.
+fn strip_command_prefix(input: &str, prefix: Option<char>) -> Option<&str> {
+    match prefix {
+        Some(prefix) => input.trim_start().strip_prefix(prefix),
+        None => Some(input),
+    }
+}
+
+/// Returns the names of the matched command and its parent commands, like
+/// `["service", "dns"]`. The path is empty if no command matches.
+fn command_path<C>(
+    input: &str,
+    commands: &HashMap<String, Command<C>>,
+    prefix_matching: bool,
+) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut cmds = commands;
+
+    for word in input.split(' ') {
+        match lookup(word, cmds, prefix_matching) {
+            Ok(Some(cmd)) => {
+                path.push(cmd.name().clone());
+                cmds = &cmd.sub;
+            }
+            _ => break,
+        }
+    }
+
+    path
+}
+
+/// The properties of a parsed command which decide if it may run, see
+/// [`Repl::permit_run`].
+struct Run {
+    path: Vec<String>,
+    name: String,
+    cooldown: Option<Duration>,
+    dangerous: bool,
+}
+
+impl Run {
+    fn of<C>(cmd: &Command<C>, path: Vec<String>) -> Self {
+        Self {
+            path,
+            name: cmd.name().clone(),
+            cooldown: cmd.cooldown,
+            dangerous: cmd.is_dangerous(),
+        }
+    }
+}
+
+/// Returns the command with the name. With prefix matching, the only command
+/// starting with the name is returned if there is no exact match. Multiple
+/// commands starting with the name are reported as ambiguous.
+fn lookup<'a, C>(
+    name: &str,
+    commands: &'a HashMap<String, Command<C>>,
+    prefix_matching: bool,
+) -> Result<Option<&'a Command<C>>, ParserError> {
+    if let Some(cmd) = commands.get(name) {
+        return Ok(Some(cmd));
+    }
+
+    if !prefix_matching || name.is_empty() {
+        return Ok(None);
+    }
+
+    let mut matches: Vec<_> = commands
+        .values()
+        .filter(|cmd| cmd.name().starts_with(name))
+        .collect();
+
+    match matches.len() {
+        0 => Ok(None),
+        1 => Ok(matches.pop()),
+        _ => {
+            let mut candidates: Vec<String> =
+                matches.iter().map(|cmd| cmd.name().clone()).collect();
+            candidates.sort();
+
+            Err(ParserError::AmbiguousCommand {
+                input: name.into(),
+                candidates,
+            })
+        }
+    }
+}
+
+fn walk<'a, C, F>(commands: &'a HashMap<String, Command<C>>, path: &mut Vec<&'a str>, f: &mut F)
+where
+    F: FnMut(&[&str], &Command<C>),
+{
+    let mut names: Vec<_> = commands.keys().collect();
+    names.sort();
+
+    for name in names {
+        let cmd = &commands[name];
+
+        path.push(name);
+        f(path, cmd);
+        walk(&cmd.sub, path, f);
+        path.pop();
+    }
+}
+
+/// Parses the input into the matched command and its parameters. Returns
+/// [`None`] if no command matches. The matched command is returned even if
+/// its args are invalid, so the error can refer to it.
+#[allow(clippy::type_complexity)]
+fn parse<'a, C>(
+    input: &'a str,
+    commands: &'a HashMap<String, Command<C>>,
+    arg_prefix: &str,
+    prefix_matching: bool,
+    strict: bool,
+) -> Result<Option<(&'a Command<C>, Result<Parameters, ParserError>)>, ParserError> {
+    match resolve(input, commands, prefix_matching)? {
+        (Some(cmd), input) => Ok(Some((cmd, cmd.parse_args(input, arg_prefix, strict)))),
+        (None, _) => Ok(None),
+    }
 }
 
-fn arg_pair_parser(input: &str) -> IResult<&str, Vec<(&str, &str)>> {
-    many0(separated_pair(alpha1, cut(char(' ')), cut(alphanumeric1)))(input)
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        io::{self, Write},
+        rc::Rc,
+        sync::mpsc,
+    };
+
+    use termion::{
+        clear, cursor,
+        event::{Event, Key},
+    };
+
+    use super::{read_event_line, terminal::Terminal, Repl};
+
+    /// Collects everything the REPL writes to the terminal.
+    #[derive(Clone, Default)]
+    struct Screen(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Screen {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn handle_event_error() {
+        let mut state = ();
+        let mut repl = Repl::builder(&mut state).build_cooked();
+
+        // A failed read of stdin ends the REPL instead of panicking
+        let err = repl.handle_event(Err(io::Error::other("EIO"))).unwrap_err();
+        assert!(err.is_fatal());
+    }
+
+    #[test]
+    fn greet() {
+        let screen = Screen::default();
+        let mut state = ();
+        let mut repl = Repl::builder(&mut state)
+            .with_prompt("> ")
+            .with_welcome_message("Welcome\nto rupl")
+            .build_with_writer(screen.clone());
+
+        // The prompt is drawn right after the welcome message
+        repl.greet().unwrap();
+        assert_eq!(
+            String::from_utf8(screen.0.take()).unwrap(),
+            format!(
+                "Welcome\r\nto rupl\r\n{}\r> \r{}",
+                clear::CurrentLine,
+                cursor::Right(2)
+            )
+        );
+    }
+
+    #[test]
+    fn read_event_line_enter() {
+        // Depending on the terminal, Enter is read as `\n` or `\r`
+        for enter in ['\n', '\r'] {
+            let (tx, rx) = mpsc::channel();
+            for key in [Key::Char('o'), Key::Char('k'), Key::Char(enter)] {
+                tx.send(Ok(Event::Key(key))).unwrap();
+            }
+
+            let mut stdout = Terminal::Writer(Box::new(io::sink()));
+            let line = read_event_line(&rx, &mut stdout).unwrap();
+            assert_eq!(line.as_deref(), Some("ok"));
+        }
+    }
 }
@@ -0,0 +1,21 @@
+//! Internal log macros, which emit records via the `log` crate when the
+//! `logging` feature is enabled. Without the feature, they expand to nothing
+//! but still type-check their arguments.
+
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "logging")]
+        log::debug!($($arg)*);
+        #[cfg(not(feature = "logging"))]
+        let _ = format_args!($($arg)*);
+    };
+}
+
+macro_rules! log_trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "logging")]
+        log::trace!($($arg)*);
+        #[cfg(not(feature = "logging"))]
+        let _ = format_args!($($arg)*);
+    };
+}
@@ -0,0 +1,74 @@
+//! Output from other threads, see [`Repl::output_handle`]. An
+//! [`OutputHandle`] enqueues messages which the REPL loop prints above the
+//! prompt with [`Repl::notify`], so the terminal is never shared across
+//! threads.
+//!
+//! The queue is unbounded, so sending never blocks and there is no
+//! backpressure. The messages are printed in the order they were sent, also
+//! across clones of the handle, at the latest one poll interval after they
+//! were sent. While a command executes, the messages wait in the queue and
+//! are printed once it returned. Messages which are sent after the REPL was
+//! dropped are discarded.
+//!
+//! [`Repl::output_handle`]: crate::Repl::output_handle
+//! [`Repl::notify`]: crate::Repl::notify
+
+use std::sync::mpsc::{Receiver, Sender};
+
+/// A handle to print messages above the prompt from any thread. The handle
+/// can be cloned and sent to other threads.
+///
+/// ### Example
+///
+/// ```no_run
+/// # use rupl::Repl;
+/// let mut state = ();
+/// let repl = Repl::builder(&mut state).build();
+/// let output = repl.output_handle();
+///
+/// std::thread::spawn(move || {
+///     output.print("Download finished");
+/// });
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutputHandle {
+    sender: Sender<String>,
+}
+
+impl OutputHandle {
+    pub(crate) fn new(sender: Sender<String>) -> Self {
+        Self { sender }
+    }
+
+    /// Enqueues the message to be printed above the prompt. Returns `false`
+    /// if the REPL was dropped, in which case the message is discarded.
+    pub fn print<T>(&self, text: T) -> bool
+    where
+        T: Into<String>,
+    {
+        self.sender.send(text.into()).is_ok()
+    }
+}
+
+/// The queue of messages sent with [`OutputHandle`]s.
+#[derive(Debug)]
+pub(crate) struct OutputQueue {
+    sender: Sender<String>,
+    receiver: Receiver<String>,
+}
+
+impl OutputQueue {
+    pub(crate) fn new() -> Self {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        Self { sender, receiver }
+    }
+
+    pub(crate) fn handle(&self) -> OutputHandle {
+        OutputHandle::new(self.sender.clone())
+    }
+
+    /// Returns the next queued message without blocking.
+    pub(crate) fn next(&self) -> Option<String> {
+        self.receiver.try_recv().ok()
+    }
+}
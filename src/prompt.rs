@@ -0,0 +1,41 @@
+/// The prompts the REPL displays in its different states. All prompts are
+/// used as they are, no trailing space is added.
+#[derive(Debug, Clone)]
+pub struct Prompts {
+    /// The prompt in front of every input line. The default is `>> `.
+    pub primary: String,
+
+    /// The prompt in front of every continued line of multi-line input. The
+    /// default is `.. `.
+    pub continuation: String,
+
+    /// The prompt displayed while searching, like in the command finder, see
+    /// [`ReplBuilder::with_finder_key`](crate::builder::ReplBuilder::with_finder_key).
+    /// The default is `search: `.
+    pub search: String,
+
+    /// The prompt in front of every output line. The default is no output
+    /// prompt.
+    pub output: String,
+
+    /// The indicator in front of the input prompt in vi insert mode, see
+    /// [`EditMode::Vi`](crate::buffer::EditMode::Vi). The default is `[I] `.
+    pub vi_insert: String,
+
+    /// The indicator in front of the input prompt in vi normal mode. The
+    /// default is `[N] `.
+    pub vi_normal: String,
+}
+
+impl Default for Prompts {
+    fn default() -> Self {
+        Self {
+            primary: String::from(">> "),
+            continuation: String::from(".. "),
+            search: String::from("search: "),
+            output: String::new(),
+            vi_insert: String::from("[I] "),
+            vi_normal: String::from("[N] "),
+        }
+    }
+}
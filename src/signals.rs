@@ -0,0 +1,62 @@
+//! Graceful exit on `SIGTERM`, enabled with the `signals` feature. This is
+//! only supported on Unix.
+//!
+//! When the process receives `SIGTERM`, e.g. from a container orchestrator,
+//! [`Repl::run`](crate::Repl::run) leaves its loop, prints the exit message,
+//! restores the terminal and returns. The signal handler only sets a flag,
+//! which the loop checks while waiting for input.
+
+#[cfg(not(unix))]
+compile_error!("The `signals` feature is only supported on Unix");
+
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use signal_hook::{consts::SIGTERM, SigId};
+
+/// A registered `SIGTERM` handler. The handler is unregistered on drop.
+pub(crate) struct Termination {
+    requested: Arc<AtomicBool>,
+    id: SigId,
+}
+
+impl Termination {
+    pub(crate) fn register() -> io::Result<Self> {
+        let requested = Arc::new(AtomicBool::new(false));
+        let id = signal_hook::flag::register(SIGTERM, requested.clone())?;
+
+        Ok(Self { requested, id })
+    }
+
+    /// Returns if `SIGTERM` was received since the handler was registered.
+    pub(crate) fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Termination {
+    fn drop(&mut self) {
+        signal_hook::low_level::unregister(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use signal_hook::{consts::SIGTERM, low_level::raise};
+
+    use super::Termination;
+
+    #[test]
+    fn termination_requested() {
+        let termination = Termination::register().unwrap();
+        assert!(!termination.is_requested());
+
+        raise(SIGTERM).unwrap();
+        assert!(termination.is_requested());
+    }
+}
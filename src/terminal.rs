@@ -0,0 +1,115 @@
+use std::io::{self, Stdout, Write};
+
+use termion::raw::{IntoRawMode, RawTerminal};
+
+/// The width and height used when the size of the terminal is unknown.
+pub(crate) const DEFAULT_DIMENSIONS: (u16, u16) = (80, 24);
+
+/// The terminal the REPL writes to. Raw mode can only be entered when
+/// stdout is a TTY. If it is not, e.g. when the output is piped into
+/// another program, the plain stdout is used instead. A detached terminal
+/// discards everything, as the host application renders the REPL itself.
+/// A writer receives everything which would be written to the terminal.
+pub(crate) enum Terminal {
+    Raw(RawTerminal<Stdout>),
+    Plain(Stdout),
+    Writer(Box<dyn Write>),
+    Detached,
+}
+
+impl Terminal {
+    pub(crate) fn new() -> Self {
+        match io::stdout().into_raw_mode() {
+            Ok(raw) => Self::Raw(raw),
+            Err(_) => Self::Plain(io::stdout()),
+        }
+    }
+
+    /// Returns if the REPL reads its input from stdin, which is the case for
+    /// the terminals created with [`Terminal::new`].
+    pub(crate) fn reads_stdin(&self) -> bool {
+        matches!(self, Self::Raw(_) | Self::Plain(_))
+    }
+
+    /// Returns if stdout is used without raw mode, because it is not a TTY.
+    pub(crate) fn is_plain(&self) -> bool {
+        matches!(self, Self::Plain(_))
+    }
+
+    /// Returns if the terminal is driven by a host application, see
+    /// [`ReplBuilder::build_cooked`](crate::builder::ReplBuilder::build_cooked).
+    pub(crate) fn is_detached(&self) -> bool {
+        matches!(self, Self::Detached)
+    }
+
+    /// Returns the width and height of the terminal in columns and rows.
+    /// Falls back to [`DEFAULT_DIMENSIONS`] if the size can't be queried,
+    /// e.g. because stdout is not a TTY, or if either of them is zero.
+    pub(crate) fn dimensions(&self) -> (u16, u16) {
+        if matches!(self, Self::Writer(_) | Self::Detached) {
+            return DEFAULT_DIMENSIONS;
+        }
+
+        match termion::terminal_size() {
+            Ok((width, height)) if width > 0 && height > 0 => (width, height),
+            _ => DEFAULT_DIMENSIONS,
+        }
+    }
+
+    /// Leaves raw mode and restores the original terminal state. This is a
+    /// no-op when the terminal is not in raw mode.
+    pub(crate) fn suspend_raw_mode(&self) -> io::Result<()> {
+        match self {
+            Self::Raw(raw) => raw.suspend_raw_mode(),
+            Self::Plain(_) | Self::Writer(_) | Self::Detached => Ok(()),
+        }
+    }
+
+    /// Enters raw mode again after it was suspended with
+    /// [`Terminal::suspend_raw_mode`].
+    pub(crate) fn activate_raw_mode(&self) -> io::Result<()> {
+        match self {
+            Self::Raw(raw) => raw.activate_raw_mode(),
+            Self::Plain(_) | Self::Writer(_) | Self::Detached => Ok(()),
+        }
+    }
+}
+
+impl Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Raw(raw) => raw.write(buf),
+            Self::Plain(plain) => plain.write(buf),
+            Self::Writer(writer) => writer.write(buf),
+            Self::Detached => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Raw(raw) => raw.flush(),
+            Self::Plain(plain) => plain.flush(),
+            Self::Writer(writer) => writer.flush(),
+            Self::Detached => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::Terminal;
+
+    #[test]
+    fn is_plain() {
+        // Raw mode is only entered if stdout is a TTY, which depends on how
+        // the tests are run
+        let plain = !termion::is_tty(&io::stdout());
+        assert_eq!(Terminal::new().is_plain(), plain);
+
+        assert!(Terminal::Plain(io::stdout()).is_plain());
+        assert!(!Terminal::Writer(Box::new(io::sink())).is_plain());
+        assert!(!Terminal::Detached.is_plain());
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    ansi,
+    error::{ReplError, ReplResult},
+};
+
+/// A plain-text transcript of the session, see
+/// [`ReplBuilder::with_transcript_file`](crate::builder::ReplBuilder::with_transcript_file).
+/// The file is opened on the first write. Once a write failed, the error is
+/// returned and the transcript is disabled for the rest of the session.
+#[derive(Debug)]
+pub(crate) struct Transcript {
+    path: PathBuf,
+    file: Option<File>,
+    use_timestamps: bool,
+    failed: bool,
+}
+
+impl Transcript {
+    pub(crate) fn new(path: PathBuf, use_timestamps: bool) -> Self {
+        Self {
+            path,
+            file: None,
+            use_timestamps,
+            failed: false,
+        }
+    }
+
+    /// Appends the text line by line, without escape sequences. Returns an
+    /// error for the first failed write only.
+    pub(crate) fn write(&mut self, text: &str) -> ReplResult<()> {
+        if self.failed {
+            return Ok(());
+        }
+
+        self.append(text).map_err(|err| {
+            self.failed = true;
+            ReplError::TranscriptError(err)
+        })
+    }
+
+    fn append(&mut self, text: &str) -> io::Result<()> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self.file.insert(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?,
+            ),
+        };
+
+        for line in ansi::strip(text).lines() {
+            if self.use_timestamps {
+                write!(file, "[{}] ", timestamp(SystemTime::now()))?;
+            }
+
+            writeln!(file, "{}", line)?;
+        }
+
+        file.flush()
+    }
+}
+
+/// Formats the time as an UTC timestamp, like `2024-03-01T12:00:00Z`.
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Converts the days since the epoch into a date of the proleptic
+    // Gregorian calendar, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::timestamp;
+
+    #[test]
+    fn transcript_timestamp() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert_eq!(timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(at(951_782_400)), "2000-02-29T00:00:00Z");
+        assert_eq!(timestamp(at(1_709_251_199)), "2024-02-29T23:59:59Z");
+        assert_eq!(timestamp(at(1_709_251_200)), "2024-03-01T00:00:00Z");
+        assert_eq!(timestamp(at(4_102_444_800)), "2100-01-01T00:00:00Z");
+
+        // Times before the epoch are clamped
+        let before = UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(timestamp(before), "1970-01-01T00:00:00Z");
+        assert!(timestamp(SystemTime::now()).ends_with('Z'));
+    }
+}
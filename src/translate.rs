@@ -0,0 +1,124 @@
+use crate::error::ReplError;
+
+/// A built-in string the REPL writes, which is passed to a [`Translator`].
+/// Prompts and messages configured with the builder are written as they
+/// are and never translated.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Text<'a> {
+    /// The message of an error returned by a command or the parser.
+    Error(&'a ReplError),
+
+    /// Appended to an error message if a similar command exists, like
+    /// `service dns` for `service dsn`.
+    DidYouMean {
+        message: &'a str,
+        suggestion: &'a str,
+    },
+
+    /// The usage line of a namespace, see
+    /// [`Command::namespace`](crate::command::Command::namespace), or of a
+    /// command invoked with a help flag, see
+    /// [`ReplBuilder::with_help_flags`](crate::builder::ReplBuilder::with_help_flags).
+    /// For the latter, the command includes its args, like
+    /// `service dns [--port <port>]`. The subcommands are sorted by name.
+    Usage {
+        command: &'a str,
+        subcommands: &'a [&'a str],
+    },
+
+    /// Written when a background job starts.
+    JobStarted(usize),
+
+    /// Written when a background job finishes.
+    JobDone(usize),
+
+    /// The status of a running job listed by the `jobs` builtin.
+    JobRunning,
+
+    /// The status of a finished job listed by the `jobs` builtin.
+    JobFinished,
+
+    /// The question before a dangerous command runs, see
+    /// [`Command::dangerous`](crate::command::Command::dangerous).
+    Confirm(&'a str),
+
+    /// Written when the REPL exits after the idle timeout, see
+    /// [`ReplBuilder::with_idle_timeout`](crate::builder::ReplBuilder::with_idle_timeout).
+    IdleTimeout,
+
+    /// The title of the generated documentation, see
+    /// [`Repl::generate_docs`](crate::Repl::generate_docs).
+    DocsTitle,
+
+    /// The heading of the parameter list of a command in the generated
+    /// documentation.
+    DocsParameters,
+
+    /// Describes the remaining input of a command in the parameter list of
+    /// the generated documentation, see
+    /// [`Command::with_remainder`](crate::command::Command::with_remainder).
+    DocsRemainder,
+}
+
+/// Translates the built-in strings of the REPL, for example to localize
+/// them, see [`ReplBuilder::with_translator`](crate::builder::ReplBuilder::with_translator).
+/// Unhandled texts can be delegated to [`English`].
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::{error::ReplError, translate::{English, Text, Translator}};
+/// struct German;
+///
+/// impl Translator for German {
+///     fn translate(&self, text: Text<'_>) -> String {
+///         match text {
+///             Text::Error(ReplError::NoSuchCommandError(_)) => "Unbekannter Befehl".into(),
+///             Text::JobStarted(id) => format!("[Job {}] gestartet", id),
+///             text => English.translate(text),
+///         }
+///     }
+/// }
+///
+/// assert_eq!(German.translate(Text::JobStarted(1)), "[Job 1] gestartet");
+/// assert_eq!(German.translate(Text::JobDone(1)), "[job 1] done");
+/// ```
+pub trait Translator {
+    fn translate(&self, text: Text<'_>) -> String;
+}
+
+/// The default translator, which returns the English strings.
+#[derive(Debug, Default)]
+pub struct English;
+
+impl Translator for English {
+    fn translate(&self, text: Text<'_>) -> String {
+        match text {
+            Text::Error(ReplError::NoSuchCommandError(_)) => "Unknown command".into(),
+            Text::Error(ReplError::ParserError(err)) => err.to_string(),
+            Text::Error(err) => err.to_string(),
+            Text::DidYouMean {
+                message,
+                suggestion,
+            } => format!("{}. Did you mean '{}'?", message, suggestion),
+            Text::Usage {
+                command,
+                subcommands: [],
+            } => format!("Usage: {}", command),
+            Text::Usage {
+                command,
+                subcommands,
+            } => format!("Usage: {} <{}>", command, subcommands.join("|")),
+            Text::JobStarted(id) => format!("[job {}] started", id),
+            Text::JobDone(id) => format!("[job {}] done", id),
+            Text::JobRunning => "running".into(),
+            Text::JobFinished => "done".into(),
+            Text::Confirm(command) => format!("Run '{}'? [y/N]", command),
+            Text::IdleTimeout => "Session timed out due to inactivity".into(),
+            Text::DocsTitle => "Commands".into(),
+            Text::DocsParameters => "Parameters".into(),
+            Text::DocsRemainder => "the remaining input".into(),
+        }
+    }
+}
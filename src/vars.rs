@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+/// Defines how references to variables which are not set are expanded.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum UnknownVariable {
+    /// Keeps the reference as it is, like `$NAME`.
+    #[default]
+    Keep,
+
+    /// Replaces the reference with an empty string, like shells do.
+    Empty,
+}
+
+/// The session variables of the REPL. Variables are plain key/value pairs
+/// which commands can read and write, independent of the REPL state. They
+/// only live as long as the REPL.
+///
+/// ### Example
+///
+/// ```
+/// # use rupl::vars::Variables;
+/// let mut vars = Variables::new();
+/// vars.set("NAME", "rupl");
+///
+/// assert_eq!(vars.get("NAME"), Some("rupl"));
+/// assert_eq!(vars.unset("NAME"), Some("rupl".into()));
+/// assert!(vars.is_empty());
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Variables {
+    inner: HashMap<String, String>,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the value of the variable.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.inner.get(name).map(String::as_str)
+    }
+
+    /// Returns if the variable is set.
+    pub fn contains(&self, name: &str) -> bool {
+        self.inner.contains_key(name)
+    }
+
+    /// Sets the variable and returns the previous value, if any.
+    pub fn set<N, V>(&mut self, name: N, value: V) -> Option<String>
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.inner.insert(name.into(), value.into())
+    }
+
+    /// Removes the variable and returns its value, if it was set.
+    pub fn unset(&mut self, name: &str) -> Option<String> {
+        self.inner.remove(name)
+    }
+
+    /// Replaces references to variables in the input with their values.
+    /// Variables are referenced with `$NAME` or `${NAME}`, where names
+    /// consist of ASCII letters, digits and underscores and don't start
+    /// with a digit. References in single quotes are not expanded, while
+    /// references in double quotes are.
+    ///
+    /// ### Example
+    ///
+    /// ```
+    /// # use rupl::vars::{UnknownVariable, Variables};
+    /// let mut vars = Variables::new();
+    /// vars.set("PORT", "53");
+    ///
+    /// let expanded = vars.expand("dns --port ${PORT} '$PORT' $MODE", UnknownVariable::Keep);
+    /// assert_eq!(expanded, "dns --port 53 '$PORT' $MODE");
+    /// ```
+    pub fn expand(&self, input: &str, unknown: UnknownVariable) -> String {
+        let mut output = String::with_capacity(input.len());
+        let mut quote = None;
+        let mut rest = input;
+
+        while let Some(c) = rest.chars().next() {
+            match c {
+                '\'' | '"' if quote.is_none() => quote = Some(c),
+                c if quote == Some(c) => quote = None,
+                '$' if quote != Some('\'') => {
+                    if let Some((name, len)) = reference(&rest[1..]) {
+                        match (self.get(name), unknown) {
+                            (Some(value), _) => output.push_str(value),
+                            (None, UnknownVariable::Keep) => output.push_str(&rest[..=len]),
+                            (None, UnknownVariable::Empty) => {}
+                        }
+
+                        rest = &rest[len + 1..];
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+
+            output.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+
+        output
+    }
+
+    /// Returns an iterator over all variables in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        let mut vars: Vec<_> = self
+            .inner
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+
+        vars.sort();
+        vars.into_iter()
+    }
+}
+
+/// Parses a variable reference after the `$`. Returns the name and the
+/// length of the reference, including braces.
+fn reference(input: &str) -> Option<(&str, usize)> {
+    let (name, len) = match input.strip_prefix('{') {
+        Some(braced) => {
+            let end = braced.find('}')?;
+            (&braced[..end], end + 2)
+        }
+        None => {
+            let end = input
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(input.len());
+            (&input[..end], end)
+        }
+    };
+
+    let valid = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.chars().next().is_some_and(|c| !c.is_ascii_digit());
+
+    valid.then_some((name, len))
+}
@@ -0,0 +1,45 @@
+use rupl::ansi::{strip, truncate, visible_width};
+use termion::color;
+
+#[test]
+fn ansi_strip() {
+    let input = format!("{}>>{} ", color::Fg(color::Red), color::Fg(color::Reset));
+    assert_eq!(strip(&input), ">> ");
+
+    let input = "\x1b]133;A\x07>> \x1b]0;title\x1b\\";
+    assert_eq!(strip(input), ">> ");
+}
+
+#[test]
+fn ansi_visible_width() {
+    let input = format!("{}λ>{} ", color::Fg(color::Green), color::Fg(color::Reset));
+    assert_eq!(visible_width(&input), 3);
+
+    assert_eq!(visible_width("日本> "), 6);
+    assert_eq!(visible_width(""), 0);
+}
+
+#[test]
+fn ansi_truncate() {
+    let red = color::Fg(color::Red).to_string();
+    let reset = color::Fg(color::Reset).to_string();
+    let input = format!("{}dns{} ok", red, reset);
+
+    assert_eq!(truncate(&input, 10), input);
+    assert_eq!(truncate(&input, 6), input);
+    assert_eq!(truncate(&input, 4), format!("{}dns{} \x1b[0m", red, reset));
+    assert_eq!(truncate(&input, 2), format!("{}dn\x1b[0m", red));
+    assert_eq!(truncate(&input, 0), format!("{}\x1b[0m", red));
+
+    // Wide chars are never split
+    assert_eq!(truncate("日本語", 3), "日");
+    assert_eq!(truncate("日本語", 4), "日本");
+
+    // Without escape sequences, no reset is appended
+    assert_eq!(truncate("service", 3), "ser");
+
+    let input = "\x1b]0;title\x07service";
+    assert_eq!(truncate(input, 3), "\x1b]0;title\x07ser\x1b[0m");
+    let input = "\x1b]0;title\x1b\\service";
+    assert_eq!(truncate(input, 3), "\x1b]0;title\x1b\\ser\x1b[0m");
+}
@@ -1,37 +1,219 @@
-// use std::net::Ipv4Addr;
+use std::{net::Ipv4Addr, time::Duration};
 
-// use rupl::args::{Arg, Args};
+use rupl::{
+    args::{ConvertFrom, FromParameters, ParameterError, Parameters},
+    error::{ReplError, ReplResult},
+};
 
-// #[test]
-// fn test_args_simple() {
-//     let input = String::from("--arg value");
+#[test]
+fn test_args_simple() {
+    let params = Parameters::new(vec![("arg", "value")]);
 
-//     let args = match Args::new(input, vec![Arg::new("arg")]) {
-//         Ok(p) => p,
-//         Err(err) => panic!("{}", err),
-//     };
+    let arg: String = match params.get("arg") {
+        Ok(p) => p,
+        Err(err) => panic!("{}", err),
+    };
 
-//     let arg: String = match args.get("arg") {
-//         Ok(p) => p,
-//         Err(err) => panic!("{}", err),
-//     };
+    assert_eq!(arg, String::from("value"))
+}
 
-//     assert_eq!(arg, String::from("value"))
-// }
+#[test]
+fn test_args_ipaddr() {
+    let params = Parameters::new(vec![("ip", "10.10.10.10")]);
 
-// #[test]
-// fn test_args_ipaddr() {
-//     let input = String::from("--ip 10.10.10.10");
+    let ip: Ipv4Addr = match params.get("ip") {
+        Ok(p) => p,
+        Err(err) => panic!("{}", err),
+    };
 
-//     let args = match Args::new(input, vec![Arg::new("ip")]) {
-//         Ok(p) => p,
-//         Err(err) => panic!("{}", err),
-//     };
+    assert_eq!(ip, Ipv4Addr::new(10, 10, 10, 10))
+}
 
-//     let ip: Ipv4Addr = match args.get("ip") {
-//         Ok(p) => p,
-//         Err(err) => panic!("{}", err),
-//     };
+#[test]
+fn test_args_errors() {
+    let params = Parameters::new(vec![("port", "http")]);
 
-//     assert_eq!(ip, Ipv4Addr::new(10, 10, 10, 10))
-// }
+    match params.get::<u16>("port") {
+        Err(ReplError::ParameterError(err)) => assert_eq!(
+            err,
+            ParameterError::ParseError {
+                name: "port".into(),
+                value: "http".into(),
+                ty: "u16"
+            }
+        ),
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    match params.get::<u16>("mode") {
+        Err(ReplError::ParameterError(err)) => {
+            assert_eq!(err, ParameterError::NoSuchParameter("mode".into()))
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+struct DnsArgs {
+    port: u16,
+    mode: String,
+}
+
+impl FromParameters for DnsArgs {
+    fn from_parameters(params: &Parameters) -> ReplResult<Self> {
+        Ok(Self {
+            port: params.get("port")?,
+            mode: params.get("mode")?,
+        })
+    }
+}
+
+#[test]
+fn test_args_from_parameters() {
+    let params = Parameters::new(vec![("port", "53"), ("mode", "udp")]);
+
+    let args: DnsArgs = params.parse().unwrap();
+    assert_eq!(args.port, 53);
+    assert_eq!(args.mode, "udp");
+
+    let params = Parameters::new(vec![("port", "53")]);
+    assert!(params.parse::<DnsArgs>().is_err());
+}
+
+#[test]
+fn test_args_contains_and_iter() {
+    let params = Parameters::new(vec![("port", "53"), ("mode", "udp")]);
+
+    assert!(params.contains("port"));
+    assert!(params.contains("mode"));
+    assert!(!params.contains("verbose"));
+
+    let pairs: Vec<_> = params.iter().collect();
+    assert_eq!(pairs, vec![("port", "53"), ("mode", "udp")]);
+
+    let params = Parameters::default();
+    assert!(!params.contains("port"));
+    assert_eq!(params.iter().count(), 0);
+}
+
+#[test]
+fn test_args_vec() {
+    let params = Parameters::new(vec![
+        ("ports", "80,443, 8080"),
+        ("names", "dns,dhcp"),
+        ("empty", ""),
+        ("invalid", "80,http,443"),
+    ]);
+
+    let ports: Vec<u16> = params.get("ports").unwrap();
+    assert_eq!(ports, vec![80, 443, 8080]);
+
+    let names: Vec<String> = params.get("names").unwrap();
+    assert_eq!(names, vec!["dns", "dhcp"]);
+
+    let empty: Vec<u16> = params.get("empty").unwrap();
+    assert!(empty.is_empty());
+
+    match params.get::<Vec<u16>>("invalid") {
+        Err(ReplError::ParameterError(err)) => assert_eq!(
+            err,
+            ParameterError::ParseError {
+                name: "invalid".into(),
+                value: "http".into(),
+                ty: "u16"
+            }
+        ),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn test_args_option() {
+    let params = Parameters::new(vec![("port", "53"), ("mode", ""), ("ttl", "abc")])
+        .with_declared(["port", "mode", "ttl", "timeout"]);
+
+    assert_eq!(params.get::<Option<u16>>("port").unwrap(), Some(53));
+    assert_eq!(params.get::<Option<String>>("mode").unwrap(), None);
+    assert!(params.get::<Option<u32>>("ttl").is_err());
+
+    // Declared, but omitted
+    assert_eq!(params.get::<Option<u16>>("timeout").unwrap(), None);
+    assert!(params.get::<u16>("timeout").is_err());
+
+    // Not declared at all
+    match params.get::<Option<u16>>("retries") {
+        Err(ReplError::ParameterError(err)) => {
+            assert_eq!(err, ParameterError::NoSuchParameter("retries".into()))
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
+
+#[test]
+fn test_args_duration() {
+    let params = Parameters::new(vec![
+        ("timeout", "30s"),
+        ("interval", "1h30m"),
+        ("delay", "1m500ms"),
+        ("ttl", "2d"),
+        ("unit", "5y"),
+        ("bare", "30"),
+        ("fraction", "1.5h"),
+    ]);
+
+    let duration: Duration = params.get("timeout").unwrap();
+    assert_eq!(duration, Duration::from_secs(30));
+
+    let duration: Duration = params.get("interval").unwrap();
+    assert_eq!(duration, Duration::from_secs(90 * 60));
+
+    let duration: Duration = params.get("delay").unwrap();
+    assert_eq!(duration, Duration::from_millis(60_500));
+
+    let duration: Duration = params.get("ttl").unwrap();
+    assert_eq!(duration, Duration::from_secs(2 * 24 * 60 * 60));
+
+    for name in ["unit", "bare", "fraction"] {
+        match params.get::<Duration>(name) {
+            Err(ReplError::ParameterError(ParameterError::ParseError { ty, .. })) => {
+                assert_eq!(ty, "Duration")
+            }
+            res => panic!("unexpected result for {}: {:?}", name, res),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl ConvertFrom<String> for Protocol {
+    fn convert(value: String) -> ReplResult<Self> {
+        match value.as_str() {
+            "tcp" => Ok(Self::Tcp),
+            "udp" => Ok(Self::Udp),
+            _ => Err(ParameterError::Invalid("expected one of: tcp, udp".into()).into()),
+        }
+    }
+}
+
+#[test]
+fn test_args_custom_error() {
+    let params = Parameters::new(vec![("proto", "tcp"), ("fallbacks", "udp,icmp")]);
+
+    assert_eq!(params.get::<Protocol>("proto").unwrap(), Protocol::Tcp);
+
+    // The message of the implementor is kept, also for lists
+    match params.get::<Vec<Protocol>>("fallbacks") {
+        Err(ReplError::ParameterError(err)) => assert_eq!(
+            err,
+            ParameterError::InvalidParameter {
+                name: "fallbacks".into(),
+                value: "udp,icmp".into(),
+                message: "expected one of: tcp, udp".into()
+            }
+        ),
+        res => panic!("unexpected result: {:?}", res),
+    }
+}
@@ -1,4 +1,4 @@
-use rupl::buffer::{Buffer, BufferError, CursorBuffer, Direction};
+use rupl::buffer::{Buffer, BufferError, CursorBuffer, Direction, OutputBuffer, WordStyle};
 
 #[test]
 fn buffer_basic() {
@@ -64,10 +64,253 @@ fn cursor_buffer_basic() {
     assert_eq!(buf.as_bytes(), vec![97, 98]);
 
     let moved = buf.move_left();
-    assert_eq!(moved, true);
+    assert!(moved);
 
     buf.insert(&['x', 'y', 'z']).unwrap();
     assert_eq!(buf.len(), 5);
     assert_eq!(buf.get_pos(), 4);
     assert_eq!(buf.as_bytes(), vec![97, 120, 121, 122, 98]);
 }
+
+#[test]
+fn buffer_replace_range() {
+    let mut buf = Buffer::new();
+
+    buf.insert(0, &['a', 'b', 'c']).unwrap();
+    assert_eq!(buf.replace_range(1, 2, &['x', 'y']), Ok(vec!['b']));
+    assert_eq!(buf.to_string(), "axyc");
+
+    assert_eq!(
+        buf.replace_range(2, 5, &[]),
+        Err(BufferError::InvalidRange { from: 2, to: 5 })
+    );
+}
+
+#[test]
+fn cursor_buffer_word_case() {
+    let mut buf = CursorBuffer::new();
+
+    buf.insert(&"grüße  welt".chars().collect::<Vec<_>>())
+        .unwrap();
+    while buf.move_left() {}
+
+    buf.upcase_word().unwrap();
+    assert_eq!(buf.to_string(), "GRÜSSE  welt");
+    assert_eq!(buf.get_pos(), 6);
+
+    buf.capitalize_word().unwrap();
+    assert_eq!(buf.to_string(), "GRÜSSE  Welt");
+    assert_eq!(buf.get_pos(), 12);
+
+    while buf.move_left() {}
+    buf.downcase_word().unwrap();
+    assert_eq!(buf.to_string(), "grüsse  Welt");
+    assert_eq!(buf.get_pos(), 6);
+}
+
+#[test]
+fn cursor_buffer_transpose() {
+    let mut buf = CursorBuffer::new();
+    assert_eq!(buf.transpose(), Ok(false));
+
+    buf.insert(&['a', 'b', 'c']).unwrap();
+    buf.move_left();
+    assert_eq!(buf.transpose(), Ok(true));
+    assert_eq!(buf.to_string(), "acb");
+    assert_eq!(buf.get_pos(), 3);
+
+    // At the end of the input, the last two chars are swapped
+    assert_eq!(buf.transpose(), Ok(true));
+    assert_eq!(buf.to_string(), "abc");
+    assert_eq!(buf.get_pos(), 3);
+
+    while buf.move_left() {}
+    assert_eq!(buf.transpose(), Ok(false));
+    assert_eq!(buf.to_string(), "abc");
+}
+
+#[test]
+fn cursor_buffer_word_style() {
+    let input: Vec<char> = "service.dns status".chars().collect();
+
+    let mut buf = CursorBuffer::new();
+    buf.insert(&input).unwrap();
+    assert!(buf.move_word_left());
+    assert_eq!(buf.get_pos(), 12);
+    assert!(buf.move_word_left());
+    assert_eq!(buf.get_pos(), 8);
+
+    let mut buf = CursorBuffer::new().with_word_style(WordStyle::Whitespace);
+    buf.insert(&input).unwrap();
+    assert!(buf.move_word_left());
+    assert!(buf.move_word_left());
+    assert_eq!(buf.get_pos(), 0);
+    assert!(!buf.move_word_left());
+
+    assert!(buf.move_word_right());
+    assert_eq!(buf.get_pos(), 11);
+
+    let mut buf = CursorBuffer::new().with_word_style(WordStyle::Custom(|c| c != '.'));
+    buf.insert(&input).unwrap();
+    assert_eq!(buf.remove_word_left().unwrap().len(), 10);
+    assert_eq!(buf.to_string(), "service.");
+}
+
+#[test]
+fn cursor_buffer_next_word_start() {
+    let mut buf = CursorBuffer::new();
+    buf.insert(&"service  dns".chars().collect::<Vec<_>>())
+        .unwrap();
+
+    assert!(buf.move_to_start());
+    assert!(!buf.move_to_start());
+
+    assert!(buf.move_next_word_start());
+    assert_eq!(buf.get_pos(), 9);
+    assert!(buf.move_next_word_start());
+    assert_eq!(buf.get_pos(), 12);
+    assert!(!buf.move_next_word_start());
+}
+
+#[test]
+fn cursor_buffer_undo_insert() {
+    let mut buf = CursorBuffer::new();
+
+    for c in "service dns".chars() {
+        buf.insert(&[c]).unwrap();
+    }
+
+    assert!(buf.undo().unwrap());
+    assert_eq!(buf.to_string(), "service");
+    assert_eq!(buf.get_pos(), 7);
+
+    assert!(buf.undo().unwrap());
+    assert!(buf.is_empty());
+    assert_eq!(buf.get_pos(), 0);
+    assert!(!buf.undo().unwrap());
+
+    assert!(buf.redo().unwrap());
+    assert_eq!(buf.to_string(), "service");
+    assert!(buf.redo().unwrap());
+    assert_eq!(buf.to_string(), "service dns");
+    assert_eq!(buf.get_pos(), 11);
+    assert!(!buf.redo().unwrap());
+}
+
+#[test]
+fn cursor_buffer_undo_remove() {
+    let mut buf = CursorBuffer::new();
+
+    buf.insert(&['a', 'b', 'c', 'd']).unwrap();
+    buf.move_left();
+    buf.move_left();
+
+    buf.remove_one(Direction::Left).unwrap();
+    assert_eq!(buf.to_string(), "acd");
+    assert_eq!(buf.get_pos(), 1);
+
+    buf.remove_one(Direction::Right).unwrap();
+    assert_eq!(buf.to_string(), "ad");
+
+    assert!(buf.undo().unwrap());
+    assert_eq!(buf.to_string(), "acd");
+    assert_eq!(buf.get_pos(), 1);
+
+    assert!(buf.undo().unwrap());
+    assert_eq!(buf.to_string(), "abcd");
+    assert_eq!(buf.get_pos(), 2);
+
+    // A new edit discards the reverted edits
+    buf.insert(&['x']).unwrap();
+    assert!(!buf.redo().unwrap());
+    assert_eq!(buf.to_string(), "abxcd");
+}
+
+#[test]
+fn cursor_buffer_undo_transform() {
+    let mut buf = CursorBuffer::new();
+
+    buf.insert(&['a', 'b']).unwrap();
+    buf.transpose().unwrap();
+    assert_eq!(buf.to_string(), "ba");
+
+    assert!(buf.undo().unwrap());
+    assert_eq!(buf.to_string(), "ab");
+
+    buf.clear();
+    assert!(!buf.undo().unwrap());
+}
+
+#[test]
+fn output_buffer_render() {
+    let mut buf = OutputBuffer::new(">> ".into(), "!".into());
+    buf.add_to_buffer("hello");
+
+    let clear = termion::clear::CurrentLine;
+    assert_eq!(buf.render_with_prompt(0), format!("{clear}\r>> hello!\r"));
+    assert_eq!(
+        buf.render_with_prompt(3),
+        format!("{clear}\r>> hello!\r{}", termion::cursor::Right(3))
+    );
+    assert_eq!(buf.render_plain(), ">> hello!\r");
+    assert_eq!(buf.render_plain(), buf.render(false, 0));
+    assert_eq!(buf.newline(), "\r\n>> ");
+
+    buf.clear();
+    assert_eq!(buf.render_plain(), ">> !\r");
+}
+
+#[test]
+fn cursor_buffer_remove_one_at_start() {
+    let mut buf = CursorBuffer::new();
+    assert_eq!(
+        buf.remove_one(Direction::Left),
+        Err(BufferError::DeleteCountUnderflow { at: 0, count: 1 })
+    );
+
+    buf.insert(&['a', 'b']).unwrap();
+    buf.move_left();
+    buf.move_left();
+    assert_eq!(
+        buf.remove_one(Direction::Left),
+        Err(BufferError::DeleteCountUnderflow { at: 0, count: 1 })
+    );
+    assert_eq!(buf.as_bytes(), vec![97, 98]);
+    assert_eq!(buf.get_pos(), 0);
+
+    assert_eq!(buf.remove_one(Direction::Right), Ok(vec!['a']));
+    assert_eq!(buf.as_bytes(), vec![98]);
+}
+
+#[test]
+fn cursor_buffer_remove_many_underflow() {
+    let mut buf = CursorBuffer::new();
+    buf.insert(&['a', 'b', 'c']).unwrap();
+    buf.move_left();
+
+    assert_eq!(
+        buf.remove_many(3, Direction::Left),
+        Err(BufferError::DeleteCountUnderflow { at: 2, count: 3 })
+    );
+    assert_eq!(buf.as_bytes(), vec![97, 98, 99]);
+    assert_eq!(buf.get_pos(), 2);
+
+    assert_eq!(buf.remove_many(2, Direction::Left), Ok(vec!['a', 'b']));
+    assert_eq!(buf.as_bytes(), vec![99]);
+    assert_eq!(buf.get_pos(), 0);
+}
+
+#[test]
+fn cursor_buffer_set_contents() {
+    let mut buf = CursorBuffer::new();
+    buf.insert(&['x', 'y']).unwrap();
+    buf.move_left();
+
+    buf.set_contents("abc").unwrap();
+    assert_eq!(buf.to_string(), "abc");
+    assert_eq!(buf.get_pos(), 3);
+
+    buf.undo().unwrap();
+    assert_eq!(buf.to_string(), "xy");
+    assert_eq!(buf.get_pos(), 1);
+}
@@ -0,0 +1,349 @@
+use rupl::{
+    args::Arg,
+    command::{Command, CommandError, FnContext},
+    format::ExecStatus,
+    Repl,
+};
+
+fn noop(name: &str) -> Command<()> {
+    Command::new(name, |_| Ok(None))
+}
+
+#[test]
+fn command_validate() {
+    let cmd = noop("service")
+        .with_argument(Arg::new("port", false).with_short('p'))
+        .with_arg("verbose", true)
+        .with_subcommand(noop("dns").with_arg("port", false));
+    assert_eq!(cmd.validate(), Ok(()));
+
+    assert_eq!(noop("").validate(), Err(CommandError::EmptyName));
+    assert_eq!(
+        noop("service dns").validate(),
+        Err(CommandError::InvalidName("service dns".into()))
+    );
+    assert_eq!(
+        noop("hello").with_arg("", false).validate(),
+        Err(CommandError::EmptyArgName("hello".into()))
+    );
+}
+
+#[test]
+fn command_validate_duplicates() {
+    let cmd = noop("hello").with_arg("name", false).with_arg("name", true);
+    assert_eq!(
+        cmd.validate(),
+        Err(CommandError::DuplicateArg {
+            command: "hello".into(),
+            name: "name".into()
+        })
+    );
+
+    let cmd = noop("hello")
+        .with_argument(Arg::new("name", false).with_short('n'))
+        .with_argument(Arg::new("number", false).with_short('n'));
+    assert_eq!(
+        cmd.validate(),
+        Err(CommandError::DuplicateShort {
+            command: "hello".into(),
+            short: 'n'
+        })
+    );
+
+    let cmd = noop("service")
+        .with_subcommand(noop("dns").with_arg("port", false).with_arg("port", false));
+    assert_eq!(
+        cmd.validate(),
+        Err(CommandError::DuplicateArg {
+            command: "dns".into(),
+            name: "port".into()
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "Invalid command 'hello'")]
+fn command_validate_on_build() {
+    let mut state = ();
+    Repl::builder(&mut state).with_command(
+        noop("hello")
+            .with_arg("name", false)
+            .with_arg("name", false),
+    );
+}
+
+#[test]
+fn command_walk() {
+    let mut state = ();
+    let repl = Repl::builder(&mut state)
+        .with_command(
+            noop("service").with_subcommand(
+                noop("dns")
+                    .with_subcommand(noop("status"))
+                    .with_subcommand(noop("restart")),
+            ),
+        )
+        .with_command(noop("exit"))
+        .build();
+
+    let mut visited = Vec::new();
+    repl.walk_commands(|path, cmd| {
+        assert_eq!(path.last(), Some(&cmd.name().as_str()));
+        visited.push(path.join(" "));
+    });
+
+    assert_eq!(
+        visited,
+        vec![
+            "exit",
+            "service",
+            "service dns",
+            "service dns restart",
+            "service dns status"
+        ]
+    );
+}
+
+#[test]
+fn command_has_command() {
+    let mut state = ();
+    let repl = Repl::builder(&mut state)
+        .with_command(noop("service").with_subcommand(noop("dns").with_subcommand(noop("status"))))
+        .with_command(noop("exit"))
+        .build();
+
+    for path in ["exit", "service", "service dns", "service  dns status "] {
+        assert!(repl.has_command(path), "{}", path);
+    }
+
+    for path in [
+        "",
+        "status",
+        "serv",
+        "service http",
+        "service dns status all",
+    ] {
+        assert!(!repl.has_command(path), "{}", path);
+    }
+}
+
+#[test]
+fn command_generate_docs() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::namespace("service")
+                .with_description("Manages services")
+                .with_subcommand(
+                    noop("dns")
+                        .with_arg("port", false)
+                        .with_arg("verbose", true)
+                        .with_subcommand(noop("status")),
+                ),
+        )
+        .with_command(noop("exit"))
+        .build_cooked();
+
+    let docs = "\
+# Commands
+
+## exit
+
+```text
+Usage: exit
+```
+
+## service
+
+Manages services
+
+```text
+Usage: service <dns>
+```
+
+### service dns
+
+```text
+Usage: service dns [--port <port>] [--verbose] <status>
+```
+
+Parameters:
+
+- `--port <port>`
+- `--verbose`
+
+#### service dns status
+
+```text
+Usage: service dns status
+```
+";
+
+    assert_eq!(repl.generate_docs(), docs);
+    assert_eq!(repl.feed_line("docgen")[0].output.as_deref(), Some(docs));
+}
+
+#[test]
+fn command_arg_choices() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::new("dns", |ctx: FnContext<()>| ctx.args().get("mode").map(Some))
+                .with_argument(Arg::new("mode", false).one_of(&["tcp", "udp"])),
+        )
+        .build_cooked();
+
+    assert_eq!(
+        repl.feed_line("dns --mode udp")[0].output.as_deref(),
+        Some("udp")
+    );
+    assert_eq!(
+        repl.feed_line("dns --mode icmp")[0].status,
+        ExecStatus::Failure(
+            "Parser error: Invalid value 'icmp' of parameter mode: expected one of: tcp, udp"
+                .into()
+        )
+    );
+    assert_eq!(
+        repl.feed_line("help dns")[0].output.as_deref(),
+        Some("Usage: dns [--mode <tcp|udp>]")
+    );
+}
+
+#[test]
+fn command_namespace() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::namespace("service")
+                .with_subcommand(noop("http"))
+                .with_subcommand(Command::new("dns", |_| Ok(Some("dns".into())))),
+        )
+        .with_command(Command::namespace("empty"))
+        .with_chaining(true)
+        .build_cooked();
+
+    assert_eq!(
+        repl.feed_line("service")[0].status,
+        ExecStatus::Failure("Parser error: Missing subcommand".into())
+    );
+    assert_eq!(
+        repl.feed_line("service dns")[0].output.as_deref(),
+        Some("dns")
+    );
+
+    // The usage lists the subcommands, and chained commands don't run
+    repl.run_script("service && service dns\nempty".as_bytes())
+        .unwrap();
+    assert_eq!(
+        repl.take_output(),
+        vec![
+            "Missing subcommand\nUsage: service <dns|http>",
+            "Missing subcommand\nUsage: empty",
+        ]
+    );
+    assert_ne!(
+        repl.feed_line("service --port 53")[0].status,
+        ExecStatus::Success
+    );
+}
+
+#[test]
+fn command_clone() {
+    let mut count = 0;
+    let status = Command::new("status", move |_| {
+        count += 1;
+        Ok(Some(count.to_string()))
+    })
+    .with_arg("verbose", true);
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(Command::namespace("dns").with_subcommand(status.clone()))
+        .with_command(
+            Command::namespace("dhcp").with_subcommand(status.clone().with_description("DHCP")),
+        )
+        .with_command(status)
+        .build_cooked();
+
+    // The clones share the captured counter
+    for (line, output) in [
+        ("dns status", "1"),
+        ("dhcp status --verbose", "2"),
+        ("status", "3"),
+    ] {
+        assert_eq!(repl.feed_line(line)[0].output.as_deref(), Some(output));
+    }
+
+    assert!(repl.has_command("dhcp status"));
+}
+
+#[test]
+fn command_captured_state() {
+    let mut total = 0;
+    let mut count = 0;
+    let mut repl = Repl::builder(&mut total)
+        .with_command(Command::new("count", move |mut ctx: FnContext<usize>| {
+            count += 1;
+            *ctx.state() += 10;
+            Ok(Some(count.to_string()))
+        }))
+        .build_cooked();
+
+    assert_eq!(repl.feed_line("count")[0].output.as_deref(), Some("1"));
+    assert_eq!(repl.feed_line("count")[0].output.as_deref(), Some("2"));
+    drop(repl);
+
+    assert_eq!(total, 20);
+}
+
+fn help_repl(state: &mut (), use_help_flags: bool) -> Repl<'_, ()> {
+    Repl::builder(state)
+        .with_command(
+            Command::namespace("service").with_subcommand(
+                noop("dns")
+                    .with_description("Manages the DNS service")
+                    .with_argument(Arg::new("port", false).with_short('p'))
+                    .with_arg("verbose", true)
+                    .with_subcommand(noop("status")),
+            ),
+        )
+        .with_command(noop("grep").with_arg("help", true))
+        .with_help_flags(use_help_flags)
+        .build_cooked()
+}
+
+#[test]
+fn command_help_flags() {
+    let mut state = ();
+    let mut repl = help_repl(&mut state, true);
+    let help =
+        "Usage: service dns [-p|--port <port>] [--verbose] <status>\nManages the DNS service";
+
+    assert_eq!(
+        repl.feed_line("service dns --help")[0].output.as_deref(),
+        Some(help)
+    );
+    assert_eq!(
+        repl.feed_line("service dns --port 53 -h")[0]
+            .output
+            .as_deref(),
+        Some(help)
+    );
+    assert_eq!(
+        repl.feed_line("service -h")[0].output.as_deref(),
+        Some("Usage: service <dns>")
+    );
+
+    // Declared args take precedence
+    let execution = &repl.feed_line("grep --help")[0];
+    assert_eq!(execution.output, None);
+    assert_eq!(execution.status, ExecStatus::Success);
+
+    let mut state = ();
+    let mut repl = help_repl(&mut state, false);
+    assert_ne!(
+        repl.feed_line("service dns --help")[0].status,
+        ExecStatus::Success
+    );
+}
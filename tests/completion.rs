@@ -0,0 +1,342 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    process,
+    rc::Rc,
+};
+
+use rupl::{
+    args::Arg,
+    command::Command,
+    completion::{CompletionStyle, Shell},
+    format::Messages,
+    Repl,
+};
+use termion::event::Key;
+
+fn noop(name: &str) -> Command<()> {
+    Command::new(name, |_| Ok(None))
+}
+
+/// Collects everything the REPL writes to the terminal.
+#[derive(Clone, Default)]
+struct Screen(Rc<RefCell<Vec<u8>>>);
+
+impl Screen {
+    fn take(&self) -> String {
+        String::from_utf8(self.0.take()).unwrap()
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn type_keys(repl: &mut Repl<()>, input: &str) {
+    for c in input.chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+}
+
+#[test]
+fn completion_commands() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            noop("service")
+                .with_subcommand(noop("dns"))
+                .with_subcommand(noop("dhcp")),
+        )
+        .with_command(noop("status"))
+        .with_command(noop("stats"))
+        .build_cooked();
+
+    type_keys(&mut repl, "se\t");
+    assert_eq!(repl.current_input(), "service ");
+
+    type_keys(&mut repl, "d\t");
+    assert_eq!(repl.current_input(), "service d");
+
+    type_keys(&mut repl, "n\t");
+    assert_eq!(repl.current_input(), "service dns ");
+
+    repl.handle_key(Key::Ctrl('w')).unwrap();
+    repl.handle_key(Key::Ctrl('w')).unwrap();
+    assert_eq!(repl.current_input(), "");
+
+    // Multiple candidates are completed up to their common prefix
+    type_keys(&mut repl, "s\t");
+    assert_eq!(repl.current_input(), "s");
+    type_keys(&mut repl, "t\t");
+    assert_eq!(repl.current_input(), "stat");
+
+    // Unknown parent commands have no candidates
+    repl.handle_key(Key::Ctrl('w')).unwrap();
+    type_keys(&mut repl, "foo d\t");
+    assert_eq!(repl.current_input(), "foo d");
+}
+
+#[test]
+fn completion_style() {
+    let list = "\r\nstats  status\r\n";
+
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_completion_style(CompletionStyle::BellThenList)
+        .with_command(noop("status"))
+        .with_command(noop("stats"))
+        .build_with_writer(screen.clone());
+
+    type_keys(&mut repl, "stat");
+    screen.take();
+
+    // The first Tab only rings the bell, the second one lists
+    type_keys(&mut repl, "\t");
+    assert_eq!(screen.take(), "\x07");
+    type_keys(&mut repl, "\t");
+    let output = screen.take();
+    assert!(output.starts_with(list));
+    assert!(!output.contains('\x07'));
+    assert_eq!(repl.current_input(), "stat");
+
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_completion_style(CompletionStyle::ListImmediately)
+        .with_command(noop("status"))
+        .with_command(noop("stats"))
+        .build_with_writer(screen.clone());
+
+    type_keys(&mut repl, "stat");
+    screen.take();
+
+    type_keys(&mut repl, "\t");
+    let output = screen.take();
+    assert!(output.starts_with(list));
+    assert!(!output.contains('\x07'));
+
+    assert_eq!(CompletionStyle::default(), CompletionStyle::ListImmediately);
+}
+
+#[test]
+fn completion_tab_inserts_spaces() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(noop("status"))
+        .build_cooked();
+
+    type_keys(&mut repl, "x\t");
+    assert_eq!(repl.current_input(), "x");
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_tab_inserts_spaces(Some(2))
+        .with_command(noop("status"))
+        .build_cooked();
+
+    type_keys(&mut repl, "sta\t");
+    assert_eq!(repl.current_input(), "status ");
+
+    type_keys(&mut repl, "x\t");
+    assert_eq!(repl.current_input(), "status x  ");
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_tab_inserts_spaces(Some(0))
+        .build_cooked();
+
+    type_keys(&mut repl, "a\tb");
+    assert_eq!(repl.current_input(), "a\tb");
+}
+
+#[test]
+fn completion_arg_choices() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            noop("dns")
+                .with_argument(
+                    Arg::new("mode", false)
+                        .with_short('m')
+                        .one_of(&["udp", "tcp", "tls"]),
+                )
+                .with_arg("port", false),
+        )
+        .build_cooked();
+
+    type_keys(&mut repl, "dns --mode u\t");
+    assert_eq!(repl.current_input(), "dns --mode udp ");
+
+    repl.set_input("dns -m t").unwrap();
+    type_keys(&mut repl, "\t");
+    assert_eq!(repl.current_input(), "dns -m t");
+    type_keys(&mut repl, "c\t");
+    assert_eq!(repl.current_input(), "dns -m tcp ");
+
+    // Args without choices complete nothing
+    repl.set_input("dns --port ").unwrap();
+    type_keys(&mut repl, "\t");
+    assert_eq!(repl.current_input(), "dns --port ");
+}
+
+#[test]
+fn completion_suggestion() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            noop("service")
+                .with_subcommand(noop("dns").with_subcommand(noop("status")))
+                .with_subcommand(noop("dhcp")),
+        )
+        .build_cooked();
+
+    type_keys(&mut repl, "servce\n");
+    assert_eq!(
+        repl.take_output(),
+        vec!["Unknown command. Did you mean 'service'?"]
+    );
+
+    type_keys(&mut repl, "service dsn\n");
+    assert_eq!(
+        repl.take_output(),
+        vec!["Invalid arguments. Did you mean 'service dns'?"]
+    );
+
+    type_keys(&mut repl, "service dns stauts\n");
+    assert_eq!(
+        repl.take_output(),
+        vec!["Invalid arguments. Did you mean 'service dns status'?"]
+    );
+
+    // Without a suggestion, the usage of the matched command is printed
+    type_keys(&mut repl, "service foo\n");
+    assert_eq!(
+        repl.take_output(),
+        vec!["Invalid arguments\nUsage: service <dhcp|dns>"]
+    );
+}
+
+#[test]
+fn completion_suggestion_messages() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(noop("service"))
+        .with_messages(Messages {
+            unknown_command: "Unbekannter Befehl".into(),
+            did_you_mean: "{message}. Meinten Sie '{suggestion}'?".into(),
+            ..Default::default()
+        })
+        .build_cooked();
+
+    type_keys(&mut repl, "servce\n");
+    assert_eq!(
+        repl.take_output(),
+        vec!["Unbekannter Befehl. Meinten Sie 'service'?"]
+    );
+}
+
+#[test]
+fn completion_bash_script() {
+    let mut state = ();
+    let repl = Repl::builder(&mut state)
+        .with_command(
+            noop("service").with_subcommand(
+                noop("dns")
+                    .with_arg("port", false)
+                    .with_argument(
+                        Arg::new("mode", false)
+                            .with_short('m')
+                            .one_of(&["udp", "tcp"]),
+                    )
+                    .with_subcommand(noop("status")),
+            ),
+        )
+        .with_command(noop("exit"))
+        .build_cooked();
+
+    let script = repl.generate_completions(Shell::Bash, "my-repl");
+
+    // Words which continue no command path, like args, are skipped
+    assert!(script.contains(
+        r#"    for word in "${COMP_WORDS[@]:1:COMP_CWORD-1}"; do
+        case "${path:+$path }$word" in
+            'exit'|'service'|'service dns'|'service dns status') path="${path:+$path }$word" ;;
+        esac
+    done"#
+    ));
+    assert!(script.contains(
+        r#"    key="$path"
+    case "${path:+$path }$prev" in
+        'service dns -m'|'service dns --mode') key="${path:+$path }$prev" ;;
+    esac
+    case "$key" in
+        '') words=('exit' 'service') ;;
+        'service') words=('dns') ;;
+        'service dns') words=('status' '--port' '-m' '--mode') ;;
+        'service dns -m') words=('tcp' 'udp') ;;
+        'service dns --mode') words=('tcp' 'udp') ;;
+    esac"#
+    ));
+    assert!(script.starts_with("_my_repl() {\n"));
+    assert!(script.ends_with("complete -F _my_repl 'my-repl'\n"));
+}
+
+/// Sources the completion script in bash and completes the line, whose last
+/// word is completed. Returns `None` if bash is not available.
+fn bash_complete(script: &str, line: &str) -> Option<String> {
+    let test = format!(
+        "{}\n\
+         COMP_WORDS=({})\n\
+         COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1))\n\
+         _rupl\n\
+         echo \"${{COMPREPLY[*]}}\"",
+        script, line
+    );
+    let output = process::Command::new("bash")
+        .arg("-c")
+        .arg(test)
+        .output()
+        .ok()?;
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    Some(String::from_utf8(output.stdout).unwrap().trim_end().into())
+}
+
+#[test]
+fn completion_bash_script_quoting() {
+    let mut state = ();
+    let repl = Repl::builder(&mut state)
+        .with_command(
+            noop("service").with_argument(Arg::new("mode", false).one_of(&["it's", "$(exit 1)"])),
+        )
+        .with_command(noop("`exit`"))
+        .with_command(noop("\"quoted\""))
+        .build_cooked();
+
+    let script = repl.generate_completions(Shell::Bash, "rupl");
+    assert!(script.ends_with("complete -F _rupl 'rupl'\n"));
+
+    // Names are completed literally instead of being expanded
+    let complete = |line| bash_complete(&script, line);
+    let Some(top_level) = complete("rupl ''") else {
+        return;
+    };
+    assert_eq!(top_level, "\"quoted\" `exit` service");
+    assert_eq!(complete("rupl '`'").unwrap(), "`exit`");
+    assert_eq!(
+        complete("rupl service --mode ''").unwrap(),
+        "$(exit 1) it's"
+    );
+    assert_eq!(complete("rupl service --mode i").unwrap(), "it's");
+}
@@ -0,0 +1,15 @@
+use rupl::cursor::CursorShape;
+use termion::cursor;
+
+#[test]
+fn cursor_shapes() {
+    assert_eq!(
+        CursorShape::Block.to_string(),
+        cursor::SteadyBlock.to_string()
+    );
+    assert_eq!(
+        CursorShape::Underline.to_string(),
+        cursor::SteadyUnderline.to_string()
+    );
+    assert_eq!(CursorShape::Bar.to_string(), cursor::SteadyBar.to_string());
+}
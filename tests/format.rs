@@ -0,0 +1,115 @@
+use rupl::{
+    error::ReplError,
+    format::{DefaultFormatter, FormatContext, Messages, OutputFormatter},
+    translate::English,
+    ParserError,
+};
+
+#[test]
+fn default_formatter() {
+    let formatter = DefaultFormatter;
+    let ctx = FormatContext {
+        input: "service dns",
+        output_prompt: "# ",
+        suggestion: None,
+        usage: None,
+        error_formatter: None,
+        translator: &English,
+    };
+
+    let output = formatter.format(Some("dns"), &Ok(Some("Hello".into())), &ctx);
+    assert_eq!(output, "# Hello");
+
+    let output = formatter.format(Some("dns"), &Ok(None), &ctx);
+    assert_eq!(output, "# ");
+
+    let result = Err(ReplError::NoSuchCommandError("servic".into()));
+    assert_eq!(formatter.format(None, &result, &ctx), "# Unknown command");
+
+    let result = Err(ParserError::InvalidArgs.into());
+    assert_eq!(
+        formatter.format(Some("dns"), &result, &ctx),
+        "# Invalid arguments"
+    );
+}
+
+#[test]
+fn default_formatter_suggestion() {
+    let formatter = DefaultFormatter;
+    let ctx = FormatContext {
+        input: "service dsn",
+        output_prompt: "",
+        suggestion: Some("service dns"),
+        usage: None,
+        error_formatter: None,
+        translator: &English,
+    };
+
+    let result = Err(ParserError::InvalidArgs.into());
+    assert_eq!(
+        formatter.format(Some("service"), &result, &ctx),
+        "Invalid arguments. Did you mean 'service dns'?"
+    );
+
+    let output = formatter.format(Some("service"), &Ok(Some("Hello".into())), &ctx);
+    assert_eq!(output, "Hello");
+}
+
+#[test]
+fn default_formatter_messages() {
+    let formatter = DefaultFormatter;
+    let messages = Messages {
+        unknown_command: "Unbekannter Befehl".into(),
+        invalid_arg_count: "Falsche Anzahl an Argumenten".into(),
+        did_you_mean: "{message} ({suggestion}?)".into(),
+        ..Default::default()
+    };
+    let ctx = FormatContext {
+        input: "servic",
+        output_prompt: "",
+        suggestion: Some("service"),
+        usage: None,
+        error_formatter: None,
+        translator: &messages,
+    };
+
+    let result = Err(ReplError::NoSuchCommandError("servic".into()));
+    assert_eq!(
+        formatter.format(None, &result, &ctx),
+        "Unbekannter Befehl (service?)"
+    );
+
+    let result = Err(ParserError::InvalidArgCount.into());
+    assert_eq!(
+        formatter.format(None, &result, &ctx),
+        "Falsche Anzahl an Argumenten (service?)"
+    );
+
+    let result = Err(ParserError::InvalidArgs.into());
+    assert_eq!(
+        formatter.format(None, &result, &ctx),
+        "Invalid arguments (service?)"
+    );
+}
+
+#[test]
+fn default_formatter_usage() {
+    let ctx = FormatContext {
+        input: "service dns --prot 53",
+        output_prompt: "",
+        suggestion: None,
+        usage: Some("Usage: service dns [--port <port>]"),
+        error_formatter: None,
+        translator: &English,
+    };
+
+    let result = Err(ParserError::UnknownArg("--prot".into()).into());
+    assert_eq!(
+        DefaultFormatter.format(Some("dns"), &result, &ctx),
+        "Unknown argument '--prot'\nUsage: service dns [--port <port>]"
+    );
+    assert_eq!(
+        DefaultFormatter.format(Some("dns"), &Ok(Some("53".into())), &ctx),
+        "53"
+    );
+}
@@ -0,0 +1,14 @@
+use rupl::fuzzy::score;
+
+#[test]
+fn fuzzy_score() {
+    assert_eq!(score("", "service"), Some(0));
+    assert_eq!(score("svc", "service"), score("SVC", "service"));
+    assert_eq!(score("svcx", "service"), None);
+    assert_eq!(score("ecivres", "service"), None);
+
+    // Word starts and consecutive chars score higher
+    assert!(score("sd", "service dns") > score("sd", "sued"));
+    assert!(score("ser", "service") > score("ser", "sucker"));
+    assert!(score("dns", "service dns") > score("dns", "service dhcp dns"));
+}
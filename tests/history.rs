@@ -0,0 +1,60 @@
+use rupl::history::{History, LongHistoryEntry};
+
+#[test]
+fn history_basic() {
+    let mut history = History::new(2);
+
+    history.push("a");
+    history.push("b");
+    history.push("b");
+    assert_eq!(history.len(), 2);
+
+    history.push("c");
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.iter().collect::<Vec<_>>(), vec!["c", "b"]);
+}
+
+#[test]
+fn history_find_prefix() {
+    let mut history = History::new(10);
+
+    history.push("service dns status");
+    history.push("service dns");
+    history.push("version");
+
+    assert_eq!(
+        history.find_prefix("service"),
+        Some(&String::from("service dns"))
+    );
+    assert_eq!(
+        history.find_prefix("service dns s"),
+        Some(&String::from("service dns status"))
+    );
+    assert_eq!(history.find_prefix("version"), None);
+    assert_eq!(history.find_prefix("help"), None);
+}
+
+#[test]
+fn history_max_entry_length() {
+    let mut history = History::new(10).with_max_entry_length(5);
+
+    history.push("hello");
+    history.push("héllo world");
+    history.push("hello world");
+    assert_eq!(
+        history.iter().collect::<Vec<_>>(),
+        vec!["hell…", "héll…", "hello"]
+    );
+
+    let mut history = History::new(10)
+        .with_max_entry_length(5)
+        .with_long_entry(LongHistoryEntry::Skip);
+
+    history.push("hello");
+    history.push("hello world");
+    assert_eq!(history.iter().collect::<Vec<_>>(), vec!["hello"]);
+
+    let mut history = History::new(10).with_max_entry_length(0);
+    history.push("a");
+    assert!(history.is_empty());
+}
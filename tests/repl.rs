@@ -0,0 +1,2459 @@
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, Write},
+    process,
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+use rupl::{
+    args::Arg,
+    buffer::{EditMode, ViMode},
+    builder::ReplBuilder,
+    builtin::Builtin,
+    command::{Command, FnContext},
+    error::ReplError,
+    format::{ExecStatus, Execution},
+    history::HistorySearchMode,
+    prompt::Prompts,
+    ParserError, Repl, ReplStats,
+};
+use termion::{clear, color, cursor, event::Key, screen, style};
+
+#[test]
+fn repl_prompt_len() {
+    let mut state = ();
+    let repl = Repl::builder(&mut state)
+        .with_prompt(format!(
+            "{}rupl{} >",
+            color::Fg(color::Blue),
+            color::Fg(color::Reset)
+        ))
+        .build();
+
+    assert_eq!(repl.prompt_len(), 7);
+}
+
+#[test]
+fn repl_remainder() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::new("run", |ctx: FnContext<()>| {
+                let timeout: u8 = ctx.args().get("timeout")?;
+                Ok(Some(format!("{} {}", timeout, ctx.args().remainder())))
+            })
+            .with_arg("timeout", false)
+            .with_remainder(true),
+        )
+        .with_command(Command::new("echo", |_| Ok(None)).with_arg("text", false))
+        .build();
+
+    let output = repl
+        .exec_and_exit("run --timeout 5 ls  -la \"/tmp\"")
+        .unwrap();
+    assert_eq!(output.as_deref(), Some("5 ls  -la \"/tmp\""));
+
+    let output = repl.exec_and_exit("run --timeout 5").unwrap();
+    assert_eq!(output.as_deref(), Some("5 "));
+
+    let err = repl.exec_and_exit("echo --text hi extra").unwrap_err();
+    assert!(matches!(
+        err,
+        ReplError::ParserError(ParserError::InvalidArgs)
+    ));
+
+    let err = repl.exec_and_exit("echo --text").unwrap_err();
+    assert!(matches!(
+        err,
+        ReplError::ParserError(ParserError::InvalidArgCount)
+    ));
+}
+
+#[test]
+fn repl_numeric_values() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::new("float", |ctx: FnContext<()>| {
+                let value: f64 = ctx.args().get("value")?;
+                Ok(Some(value.to_string()))
+            })
+            .with_arg("value", false),
+        )
+        .with_command(
+            Command::new("int", |ctx: FnContext<()>| {
+                let value: i64 = ctx.args().get("value")?;
+                Ok(Some(value.to_string()))
+            })
+            .with_arg("value", false),
+        )
+        .build();
+
+    let cases = [
+        ("int --value 42", "42"),
+        ("int --value -5", "-5"),
+        ("int --value +7", "7"),
+        ("float --value 3.14", "3.14"),
+        ("float --value -0.5", "-0.5"),
+        ("float --value .25", "0.25"),
+        ("float --value 1e3", "1000"),
+        ("float --value -1.5e3", "-1500"),
+        ("float --value 2.5E-2", "0.025"),
+    ];
+
+    for (input, expected) in cases {
+        let output = repl.exec_and_exit(input).unwrap();
+        assert_eq!(output.as_deref(), Some(expected), "input: {input}");
+    }
+}
+
+#[test]
+fn repl_arg_prefix() {
+    let hello = || {
+        Command::new("hello", |ctx: FnContext<()>| {
+            let name: String = ctx.args().get("name")?;
+            Ok(Some(format!("{} {}", name, ctx.args().contains("loud"))))
+        })
+        .with_arg("name", false)
+        .with_arg("loud", true)
+    };
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).with_command(hello()).build();
+    let output = repl.exec_and_exit("hello --loud --name rupl").unwrap();
+    assert_eq!(output.as_deref(), Some("rupl true"));
+    assert!(repl.exec_and_exit("hello name rupl").is_err());
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(hello())
+        .with_arg_prefix("/")
+        .build();
+    let output = repl.exec_and_exit("hello /name rupl").unwrap();
+    assert_eq!(output.as_deref(), Some("rupl false"));
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(hello())
+        .with_arg_prefix("")
+        .build();
+    let output = repl.exec_and_exit("hello name rupl loud").unwrap();
+    assert_eq!(output.as_deref(), Some("rupl true"));
+}
+
+#[test]
+fn repl_short_args() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::new("ls", |ctx: FnContext<()>| {
+                let depth: u8 = ctx.args().get("depth")?;
+                Ok(Some(format!("{} {}", depth, ctx.args().contains("all"))))
+            })
+            .with_argument(Arg::new("all", true).with_short('a'))
+            .with_argument(Arg::new("depth", false).with_short('d')),
+        )
+        .build();
+
+    let output = repl.exec_and_exit("ls -a -d 2").unwrap();
+    assert_eq!(output.as_deref(), Some("2 true"));
+
+    let output = repl.exec_and_exit("ls --depth 3 -a").unwrap();
+    assert_eq!(output.as_deref(), Some("3 true"));
+
+    assert!(repl.exec_and_exit("ls -ad 2").is_err());
+    assert!(repl.exec_and_exit("ls --d 2").is_err());
+}
+
+#[test]
+fn repl_strict_args() {
+    let hello = || {
+        Command::new("hello", |ctx: FnContext<()>| {
+            let name: String = ctx.args().get("name")?;
+            let unknown: Vec<_> = ctx
+                .args()
+                .unknown()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            Ok(Some(format!("{} {}", name, unknown.join(" "))))
+        })
+        .with_arg("name", false)
+    };
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).with_command(hello()).build();
+    let err = repl
+        .exec_and_exit("hello --name rupl --nmae x")
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ReplError::ParserError(ParserError::UnknownArg(arg)) if arg == "--nmae"
+    ));
+    let err = repl.exec_and_exit("hello -x --name rupl").unwrap_err();
+    assert!(matches!(
+        err,
+        ReplError::ParserError(ParserError::UnknownArg(arg)) if arg == "-x"
+    ));
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(hello())
+        .with_strict_args(false)
+        .build();
+    let output = repl
+        .exec_and_exit("hello --color red --name rupl -v")
+        .unwrap();
+    assert_eq!(output.as_deref(), Some("rupl --color=red -v="));
+    assert!(matches!(
+        repl.exec_and_exit("hello --name rupl extra").unwrap_err(),
+        ReplError::ParserError(ParserError::InvalidArgs)
+    ));
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(hello().with_strict(false))
+        .build();
+    let output = repl.exec_and_exit("hello --name rupl --quiet").unwrap();
+    assert_eq!(output.as_deref(), Some("rupl --quiet="));
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(hello().with_strict(true))
+        .with_strict_args(false)
+        .build();
+    assert!(repl.exec_and_exit("hello --name rupl --quiet").is_err());
+}
+
+#[test]
+fn repl_terminal_dimensions() {
+    let mut state = ();
+    let repl = Repl::builder(&mut state).build_cooked();
+    assert_eq!(repl.terminal_dimensions(), (80, 24));
+
+    let mut state = ();
+    let repl = Repl::builder(&mut state).build();
+    let (width, height) = repl.terminal_dimensions();
+    assert!(width > 0 && height > 0);
+}
+
+#[test]
+fn repl_cooked() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_output_prompt("#")
+        .with_command(
+            Command::new("echo", |ctx: FnContext<()>| {
+                Ok(Some(ctx.args().remainder().to_string()))
+            })
+            .with_remainder(true),
+        )
+        .build_cooked();
+
+    for c in "echo helo".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+
+    repl.handle_key(Key::Left).unwrap();
+    repl.handle_key(Key::Char('l')).unwrap();
+    assert_eq!(repl.current_input(), "echo hello");
+    assert!(repl.take_output().is_empty());
+
+    repl.handle_key(Key::Char('\n')).unwrap();
+    assert_eq!(repl.current_input(), "");
+    assert_eq!(repl.take_output(), vec!["# hello"]);
+    assert!(repl.take_output().is_empty());
+}
+
+#[test]
+fn repl_feed_keys() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).build_cooked();
+
+    repl.feed_keys(&[
+        Key::Char('a'),
+        Key::Char('b'),
+        Key::Char('c'),
+        Key::Left,
+        Key::Left,
+        Key::Char('X'),
+    ])
+    .unwrap();
+
+    assert_eq!(repl.buffer().to_string(), "aXbc");
+    assert_eq!(repl.buffer().get_pos(), 2);
+    assert_eq!(repl.stats().keys, 6);
+}
+
+#[test]
+fn repl_input_accessors() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).with_prompt("> ").build_cooked();
+    assert_eq!(repl.current_input(), "");
+    assert_eq!(repl.cursor_position(), 0);
+
+    for c in "héllo".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    assert_eq!(repl.current_input(), "héllo");
+    assert_eq!(repl.cursor_position(), 5);
+
+    repl.feed_keys(&[Key::Left, Key::Left, Key::Backspace])
+        .unwrap();
+    assert_eq!(repl.current_input(), "hélo");
+    assert_eq!(repl.cursor_position(), 2);
+    assert_eq!(repl.cursor_column(), 4);
+}
+
+#[test]
+fn repl_live_validator() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("port"))
+        .with_live_validator(|input| match input.chars().all(|c| c.is_ascii_digit()) {
+            true => Ok(()),
+            false => Err("Only digits".into()),
+        })
+        .build_cooked();
+
+    assert_eq!(repl.validation_hint(), None);
+
+    repl.feed_keys(&[Key::Char('1'), Key::Char('x')]).unwrap();
+    assert_eq!(repl.validation_hint(), Some("Only digits".into()));
+    assert_eq!(repl.current_input(), "1x");
+
+    repl.handle_key(Key::Backspace).unwrap();
+    assert_eq!(repl.validation_hint(), None);
+
+    let mut state = ();
+    let repl = Repl::builder(&mut state).build_cooked();
+    assert_eq!(repl.validation_hint(), None);
+}
+
+#[test]
+fn repl_live_validator_hint() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .with_live_validator(|input| match input.chars().all(|c| c.is_ascii_digit()) {
+            true => Ok(()),
+            false => Err("Only digits".into()),
+        })
+        .build_with_writer(screen.clone());
+
+    repl.handle_key(Key::Char('1')).unwrap();
+    screen.take();
+
+    // The hint is drawn in red below the input line
+    repl.handle_key(Key::Char('x')).unwrap();
+    assert_eq!(
+        screen.take(),
+        format!(
+            "{clear}\r> 1x\r\n{clear}{}Only digits{}{}\r{}",
+            color::Fg(color::Red),
+            color::Fg(color::Reset),
+            cursor::Up(1),
+            cursor::Right(4),
+            clear = clear::CurrentLine,
+        )
+    );
+
+    // Valid input removes the hint again
+    repl.handle_key(Key::Backspace).unwrap();
+    assert_eq!(
+        screen.take(),
+        format!(
+            "{clear}\r> 1{}{clear}{}\r{}",
+            cursor::Down(1),
+            cursor::Up(1),
+            cursor::Right(3),
+            clear = clear::CurrentLine,
+        )
+    );
+}
+
+#[test]
+fn repl_submit_validator() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("say").with_remainder(true))
+        .with_submit_validator(|input| match input.matches('"').count() % 2 {
+            0 => Ok(()),
+            _ => Err("Unterminated quote".into()),
+        })
+        .build_cooked();
+
+    for c in "say \"hi".chars().chain(['\n']) {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    assert_eq!(repl.take_output(), vec!["Unterminated quote"]);
+    assert_eq!(repl.current_input(), "say \"hi");
+    assert_eq!(repl.stats().commands, 0);
+
+    repl.feed_keys(&[Key::Char('"'), Key::Char('\n')]).unwrap();
+    assert_eq!(repl.take_output(), vec!["say"]);
+    assert_eq!(repl.current_input(), "");
+    assert_eq!(repl.stats().commands, 1);
+}
+
+#[test]
+fn repl_utf8_input() {
+    use termion::input::TermRead;
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).build_cooked();
+
+    // termion assembles the bytes of multi-byte chars into a single key
+    let bytes: &[u8] = "h\u{e9}\u{1f980}!".as_bytes();
+    assert_eq!(bytes.len(), 8);
+
+    for key in bytes.keys() {
+        repl.handle_key(key.unwrap()).unwrap();
+    }
+
+    assert_eq!(repl.current_input(), "h\u{e9}\u{1f980}!");
+    assert_eq!(repl.buffer().len(), 4);
+    assert_eq!(repl.cursor_position(), 4);
+
+    repl.feed_keys(&[Key::Left, Key::Backspace]).unwrap();
+    assert_eq!(repl.current_input(), "h\u{e9}!");
+}
+
+#[test]
+fn repl_run_script() {
+    let script = "ok\n\n  \nfail\nok  \n";
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("ok"))
+        .with_command(Command::new("fail", |_| {
+            Err(ReplError::EditorError("failed".into()))
+        }))
+        .build_cooked();
+
+    repl.run_script(script.as_bytes()).unwrap();
+    assert_eq!(
+        repl.take_output(),
+        vec!["ok", "Unrecoverable readline error: failed", "ok"]
+    );
+    assert_eq!(repl.stats().commands, 3);
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("$ ")
+        .with_echo_commands(true)
+        .with_command(named("ok"))
+        .build_cooked();
+
+    repl.run_script(script.as_bytes()).unwrap();
+    assert_eq!(
+        repl.take_output(),
+        vec!["$ ok", "ok", "$ fail", "Unknown command", "$ ok", "ok"]
+    );
+}
+
+#[test]
+fn repl_read_raw_line() {
+    let cat = || {
+        Command::new("cat", |mut ctx: FnContext<()>| {
+            let mut lines = Vec::new();
+
+            while let Some(line) = ctx.read_raw_line()? {
+                if line == "EOF" {
+                    break;
+                }
+                lines.push(line);
+            }
+
+            Ok(Some(lines.join("|")))
+        })
+    };
+
+    let script = "cat\nok\n  # not a comment\r\nEOF\nok\ncat\nunterminated\n";
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(cat())
+        .with_command(named("ok"))
+        .build_cooked();
+
+    repl.run_script(script.as_bytes()).unwrap();
+    assert_eq!(
+        repl.take_output(),
+        vec!["ok|  # not a comment", "ok", "unterminated"]
+    );
+
+    // Raw lines are not added to the history
+    repl.feed_keys(&[Key::Up, Key::Up]).unwrap();
+    assert_eq!(repl.current_input(), "ok");
+    repl.feed_keys(&[Key::Up, Key::Up]).unwrap();
+    assert_eq!(repl.current_input(), "cat");
+    repl.set_input("").unwrap();
+
+    // Outside of scripts, a cooked REPL has no input to read from
+    assert_eq!(repl.feed_line("cat")[0].output.as_deref(), Some(""));
+}
+
+#[test]
+fn repl_feed_line() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_chaining(true)
+        .with_command(named("service").with_subcommand(named("dns")))
+        .with_command(Command::new("fail", |_| {
+            Err(ReplError::EditorError("failed".into()))
+        }))
+        .build_cooked();
+
+    assert_eq!(
+        repl.feed_line("service dns; fail || nope"),
+        vec![
+            Execution {
+                command_path: vec!["service".into(), "dns".into()],
+                output: Some("dns".into()),
+                status: ExecStatus::Success,
+            },
+            Execution {
+                command_path: vec!["fail".into()],
+                output: None,
+                status: ExecStatus::Failure("Unrecoverable readline error: failed".into()),
+            },
+            Execution {
+                command_path: vec![],
+                output: None,
+                status: ExecStatus::Failure("No such command: nope".into()),
+            },
+        ]
+    );
+    assert!(repl.take_output().is_empty());
+    assert_eq!(repl.stats().commands, 3);
+    assert_eq!(repl.stats().failed, 2);
+}
+
+#[test]
+fn repl_state_reset() {
+    let mut counter = 5;
+    let mut repl = Repl::builder(&mut counter)
+        .with_command(Command::new("inc", |mut ctx: FnContext<usize>| {
+            *ctx.state() += 1;
+            Ok(Some(ctx.state().to_string()))
+        }))
+        .with_state_reset(|| 0)
+        .build_cooked();
+
+    assert_eq!(repl.feed_line("inc")[0].output.as_deref(), Some("6"));
+    assert_eq!(repl.feed_line("reset")[0].status, ExecStatus::Success);
+    assert_eq!(repl.feed_line("inc")[0].output.as_deref(), Some("1"));
+    assert!(repl.reset_state());
+    drop(repl);
+    assert_eq!(counter, 0);
+
+    let mut counter = 5;
+    let mut repl = Repl::builder(&mut counter).build_cooked();
+    assert!(!repl.reset_state());
+    assert_eq!(
+        repl.feed_line("reset")[0].status,
+        ExecStatus::Failure("No such command: reset".into())
+    );
+}
+
+#[test]
+fn repl_recent_outputs() {
+    let mut counter = 0;
+    let mut repl = Repl::builder(&mut counter)
+        .with_command(Command::new("inc", |mut ctx: FnContext<usize>| {
+            *ctx.state() += 1;
+            Ok(Some(ctx.state().to_string()))
+        }))
+        .with_command(Command::new("quiet", |_| Ok(None)))
+        .with_recent_outputs(3)
+        .build_cooked();
+
+    assert_eq!(repl.last_output(), None);
+    assert_eq!(repl.feed_line("last 2")[0].output, None);
+
+    repl.feed_line("inc");
+    repl.feed_line("inc");
+    assert_eq!(repl.feed_line("last 5")[0].output.as_deref(), Some("1\n2"));
+
+    repl.feed_line("inc");
+    repl.feed_line("inc");
+    repl.feed_line("quiet");
+    assert_eq!(repl.last_output(), Some(("inc", "4")));
+    assert_eq!(repl.feed_line("last")[0].output.as_deref(), Some("4"));
+    assert_eq!(
+        repl.feed_line("last 3")[0].output.as_deref(),
+        Some("2\n3\n4")
+    );
+    assert_ne!(repl.feed_line("last x")[0].status, ExecStatus::Success);
+    assert_ne!(repl.feed_line("last 1 2")[0].status, ExecStatus::Success);
+
+    let mut counter = 0;
+    let mut repl = Repl::builder(&mut counter)
+        .with_command(Command::new("inc", |_| Ok(Some("1".into()))))
+        .with_recent_outputs(0)
+        .build_cooked();
+
+    repl.feed_line("inc");
+    assert_eq!(repl.last_output(), None);
+}
+
+#[test]
+fn repl_evaluator() {
+    let mut sum = 0;
+    let mut repl = Repl::builder(&mut sum)
+        .with_command(Command::new("inc", |_| Ok(Some("command".into()))))
+        .with_evaluator(|line, sum: &mut i64| match line.parse::<i64>() {
+            Ok(value) => {
+                *sum += value;
+                Ok(Some(sum.to_string()))
+            }
+            Err(_) => Err(ReplError::EditorError(format!("not a number: {}", line))),
+        })
+        .build_cooked();
+
+    assert_eq!(repl.feed_line("5")[0].output.as_deref(), Some("5"));
+    assert_eq!(
+        repl.feed_line(" 7 # seven")[0].output.as_deref(),
+        Some("12")
+    );
+    assert_eq!(
+        repl.feed_line("inc")[0].status,
+        ExecStatus::Failure("Unrecoverable readline error: not a number: inc".into())
+    );
+    assert_eq!(
+        repl.feed_line("1 && 2")[0].status,
+        ExecStatus::Failure("Unrecoverable readline error: not a number: 1 && 2".into())
+    );
+    assert!(repl.feed_line("inc")[0].command_path.is_empty());
+    assert!(repl.feed_line("# only a comment").is_empty());
+    assert_eq!(repl.last_output(), Some(("7", "12")));
+    drop(repl);
+    assert_eq!(sum, 12);
+}
+
+#[test]
+fn repl_fallback_evaluator() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::new(":echo", |ctx: FnContext<()>| {
+                let text: String = ctx.args().get("text")?;
+                Ok(Some(text))
+            })
+            .with_arg("text", false),
+        )
+        .with_fallback_evaluator(|line, _| Ok(Some(line.to_uppercase())))
+        .with_chaining(true)
+        .build_cooked();
+
+    let executions = repl.feed_line(":echo --text hi");
+    assert_eq!(executions[0].output.as_deref(), Some("hi"));
+    assert_eq!(executions[0].command_path, vec![":echo"]);
+
+    let executions = repl.feed_line("print 1");
+    assert_eq!(executions[0].output.as_deref(), Some("PRINT 1"));
+    assert!(executions[0].command_path.is_empty());
+
+    // Invalid args of a matched command are not evaluated
+    assert_eq!(
+        repl.feed_line(":echo --txt hi")[0].status,
+        ExecStatus::Failure("Parser error: Unknown argument '--txt'".into())
+    );
+
+    let outputs: Vec<_> = repl
+        .feed_line("a && :echo --text b")
+        .into_iter()
+        .map(|execution| execution.output.unwrap())
+        .collect();
+    assert_eq!(outputs, vec!["A", "b"]);
+}
+
+#[test]
+fn repl_command_prefix() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(Command::new("help", |_| Ok(Some("usage".into()))))
+        .with_fallback_evaluator(|line, _| Ok(Some(format!("eval {}", line))))
+        .with_command_prefix(Some(':'))
+        .build_cooked();
+
+    let executions = repl.feed_line(":help");
+    assert_eq!(executions[0].output.as_deref(), Some("usage"));
+    assert_eq!(executions[0].command_path, vec!["help"]);
+    assert_eq!(
+        repl.feed_line("help")[0].output.as_deref(),
+        Some("eval help")
+    );
+    assert_eq!(
+        repl.feed_line(":hlep")[0].status,
+        ExecStatus::Failure("No such command: hlep".into())
+    );
+
+    for c in ":hlep\n".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    assert_eq!(
+        repl.take_output(),
+        vec!["Unknown command. Did you mean ':help'?"]
+    );
+
+    for c in ":he".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    repl.handle_key(Key::Char('\t')).unwrap();
+    assert_eq!(repl.current_input(), ":help ");
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(Command::new("help", |_| Ok(Some("usage".into()))))
+        .with_command_prefix(Some(':'))
+        .build_cooked();
+
+    assert_eq!(
+        repl.feed_line("help")[0].status,
+        ExecStatus::Failure("No such command: help".into())
+    );
+}
+
+#[test]
+fn repl_carriage_return() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("ok"))
+        .build_cooked();
+
+    repl.feed_keys(&[Key::Char('o'), Key::Char('k'), Key::Char('\r')])
+        .unwrap();
+    assert_eq!(repl.current_input(), "");
+    assert_eq!(repl.take_output(), vec!["ok"]);
+    assert_eq!(repl.stats().commands, 1);
+}
+
+#[test]
+fn repl_set_input() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("ok"))
+        .build_cooked();
+
+    repl.set_input("abc").unwrap();
+    assert_eq!(repl.current_input(), "abc");
+    assert_eq!(repl.cursor_position(), 3);
+
+    repl.set_input("o").unwrap();
+    repl.feed_keys(&[Key::Char('k'), Key::Char('\n')]).unwrap();
+    assert_eq!(repl.take_output(), vec!["ok"]);
+}
+
+#[test]
+fn repl_comments() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("say").with_remainder(true))
+        .with_command(
+            Command::new("echo", |ctx: FnContext<()>| {
+                Ok(Some(ctx.args().remainder().to_string()))
+            })
+            .with_remainder(true),
+        )
+        .build_cooked();
+
+    let mut run = |line: &str| -> Vec<String> {
+        repl.feed_line(line)
+            .into_iter()
+            .map(|execution| execution.output.unwrap_or_default())
+            .collect()
+    };
+
+    assert_eq!(run("# just a comment"), Vec::<String>::new());
+    assert_eq!(run("echo a # b"), vec!["a "]);
+    assert_eq!(run("echo a#b '# c' \"#d\""), vec!["a#b '# c' \"#d\""]);
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_comment_char(Some(';'))
+        .with_command(
+            Command::new("echo", |ctx: FnContext<()>| {
+                Ok(Some(ctx.args().remainder().to_string()))
+            })
+            .with_remainder(true),
+        )
+        .build_cooked();
+
+    assert_eq!(
+        repl.feed_line("echo #a ;b")[0].output.as_deref(),
+        Some("#a ")
+    );
+}
+
+#[test]
+fn repl_ctrl_keys() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).build_cooked();
+
+    repl.feed_keys(&[Key::Char('a'), Key::Char('b'), Key::Ctrl('t')])
+        .unwrap();
+    assert_eq!(repl.current_input(), "ba");
+
+    // Unbound keys are ignored
+    repl.feed_keys(&[Key::Ctrl('q'), Key::Esc]).unwrap();
+    assert_eq!(repl.current_input(), "ba");
+    assert_eq!(repl.cursor_position(), 2);
+}
+
+#[test]
+fn repl_unbound_keys() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).build_cooked();
+
+    for key in [
+        Key::PageUp,
+        Key::PageDown,
+        Key::BackTab,
+        Key::Delete,
+        Key::Insert,
+        Key::F(1),
+        Key::Null,
+    ] {
+        assert!(repl.handle_key(key).is_ok(), "{:?}", key);
+    }
+}
+
+#[test]
+fn repl_feed_keys_delete() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).build_cooked();
+
+    type_keys(&mut repl, "abc");
+    repl.feed_keys(&[Key::Left, Key::Left, Key::Delete])
+        .unwrap();
+    assert_eq!(repl.current_input(), "ac");
+    assert_eq!(repl.cursor_position(), 1);
+
+    // Keys without a binding leave the input as is
+    repl.feed_keys(&[Key::PageUp, Key::F(1)]).unwrap();
+    assert_eq!(repl.current_input(), "ac");
+    assert_eq!(repl.cursor_position(), 1);
+
+    // Delete at the end of the input does nothing
+    repl.feed_keys(&[Key::End, Key::Delete]).unwrap();
+    assert_eq!(repl.current_input(), "ac");
+    assert_eq!(repl.cursor_position(), 2);
+}
+
+#[test]
+fn repl_continuation() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompts(Prompts {
+            primary: "> ".into(),
+            continuation: "... ".into(),
+            ..Default::default()
+        })
+        .with_command(
+            Command::new("echo", |ctx: FnContext<()>| {
+                Ok(Some(ctx.args().remainder().to_string()))
+            })
+            .with_remainder(true),
+        )
+        .build_cooked();
+
+    let type_line = |repl: &mut Repl<()>, line: &str| {
+        for c in line.chars().chain(['\n']) {
+            repl.handle_key(Key::Char(c)).unwrap();
+        }
+    };
+
+    type_line(&mut repl, "echo a \\");
+    assert!(repl.take_output().is_empty());
+    assert_eq!(repl.current_input(), "");
+    assert_eq!(repl.render_line(), "... ");
+    assert_eq!(repl.cursor_column(), 4);
+
+    type_line(&mut repl, "b '\\'\\");
+    type_line(&mut repl, "");
+    assert_eq!(repl.take_output(), vec!["a b '\\'"]);
+    assert_eq!(repl.render_line(), "> ");
+
+    type_line(&mut repl, "echo 'a\\");
+    assert_eq!(repl.take_output(), vec!["'a\\"]);
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_continuation_char(None)
+        .with_command(
+            Command::new("echo", |ctx: FnContext<()>| {
+                Ok(Some(ctx.args().remainder().to_string()))
+            })
+            .with_remainder(true),
+        )
+        .build_cooked();
+
+    type_line(&mut repl, "echo a \\");
+    assert_eq!(repl.take_output(), vec!["a \\"]);
+}
+
+#[test]
+fn repl_continuation_cancel() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompts(Prompts {
+            primary: "> ".into(),
+            continuation: "... ".into(),
+            ..Default::default()
+        })
+        .with_command(
+            Command::new("echo", |ctx: FnContext<()>| {
+                Ok(Some(ctx.args().remainder().to_string()))
+            })
+            .with_remainder(true),
+        )
+        .with_auto_pairs(true)
+        .build_cooked();
+
+    // The closing char follows the cursor, so the line ends with it and
+    // isn't continued
+    type_keys(&mut repl, "echo (a \\\n");
+    assert_eq!(repl.take_output(), vec!["(a \\)"]);
+
+    // Behind the closing char, the line is continued
+    type_keys(&mut repl, "echo (a");
+    repl.feed_keys(&[Key::End, Key::Char('\\'), Key::Char('\n')])
+        .unwrap();
+    assert_eq!(repl.render_line(), "... ");
+
+    repl.feed_keys(&[Key::Char('"')]).unwrap();
+    assert_eq!(repl.current_input(), "\"\"");
+
+    // Ctrl-C discards the continued lines as well
+    repl.handle_key(Key::Ctrl('c')).unwrap();
+    assert_eq!(repl.current_input(), "");
+    assert_eq!(repl.render_line(), "> ");
+
+    type_keys(&mut repl, "echo b\n");
+    assert_eq!(repl.take_output(), vec!["b"]);
+}
+
+#[test]
+fn repl_auto_pairs() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_auto_pairs(true)
+        .build_cooked();
+
+    // A single undo removes both chars of the pair
+    type_keys(&mut repl, "[");
+    assert_eq!(repl.current_input(), "[]");
+    assert_eq!(repl.cursor_position(), 1);
+    repl.handle_key(Key::Ctrl('z')).unwrap();
+    assert_eq!(repl.current_input(), "");
+    assert_eq!(repl.cursor_position(), 0);
+
+    // Typing the closing char moves over it
+    type_keys(&mut repl, "(a)");
+    assert_eq!(repl.current_input(), "(a)");
+    assert_eq!(repl.cursor_position(), 3);
+
+    type_keys(&mut repl, " \"");
+    assert_eq!(repl.current_input(), "(a) \"\"");
+    assert_eq!(repl.cursor_position(), 5);
+    type_keys(&mut repl, "b\"");
+    assert_eq!(repl.current_input(), "(a) \"b\"");
+    assert_eq!(repl.cursor_position(), 7);
+
+    // Quotes directly after a word are not paired
+    type_keys(&mut repl, " don't");
+    assert_eq!(repl.current_input(), "(a) \"b\" don't");
+}
+
+#[test]
+fn repl_inline_output() {
+    for (inline_output, expected) in [
+        (
+            false,
+            format!("\r\n> {}\rstatus\r\r\n> ", clear::CurrentLine),
+        ),
+        (true, format!("{}\rstatus\r\r\n> ", clear::CurrentLine)),
+    ] {
+        let screen = Screen::default();
+        let mut state = ();
+        let mut repl = Repl::builder(&mut state)
+            .with_prompt("> ")
+            .with_command(named("status"))
+            .with_inline_output(inline_output)
+            .build_with_writer(screen.clone());
+
+        type_keys(&mut repl, "status");
+        screen.take();
+
+        // Without inline output, the input line stays visible above the
+        // output
+        repl.handle_key(Key::Char('\n')).unwrap();
+        assert_eq!(screen.take(), expected);
+    }
+}
+
+/// Collects everything the REPL writes to the terminal.
+#[derive(Clone, Default)]
+struct Screen(Rc<RefCell<Vec<u8>>>);
+
+impl Screen {
+    fn take(&self) -> String {
+        String::from_utf8(self.0.take()).unwrap()
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn repl_backspace_mid_line() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .build_with_writer(screen.clone());
+
+    for c in "abcd".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    repl.feed_keys(&[Key::Left, Key::Left]).unwrap();
+    screen.take();
+
+    // The whole line is cleared before it is redrawn, so no stale chars of
+    // the longer line remain at its end
+    repl.handle_key(Key::Backspace).unwrap();
+    assert_eq!(repl.current_input(), "acd");
+    assert_eq!(
+        screen.take(),
+        format!("{}\r> acd\r{}", clear::CurrentLine, cursor::Right(3))
+    );
+}
+
+#[test]
+fn repl_vi_mode() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("service").with_subcommand(named("dns")))
+        .with_edit_mode(EditMode::Vi)
+        .build_cooked();
+
+    type_keys(&mut repl, "servce dns");
+    assert_eq!(repl.vi_mode(), Some(ViMode::Insert));
+
+    // The cursor moves onto the last char and stays on a char
+    repl.handle_key(Key::Esc).unwrap();
+    assert_eq!(repl.vi_mode(), Some(ViMode::Normal));
+    assert_eq!(repl.cursor_position(), 9);
+    type_keys(&mut repl, "l$");
+    assert_eq!(repl.cursor_position(), 9);
+
+    // Keys are commands in normal mode
+    type_keys(&mut repl, "0wbhllli");
+    assert_eq!(repl.cursor_position(), 3);
+    type_keys(&mut repl, "v");
+    assert_eq!(repl.current_input(), "servvce dns");
+
+    repl.handle_key(Key::Esc).unwrap();
+    type_keys(&mut repl, "xai");
+    assert_eq!(repl.current_input(), "service dns");
+
+    repl.handle_key(Key::Esc).unwrap();
+    type_keys(&mut repl, "$xx");
+    assert_eq!(repl.current_input(), "service d");
+    assert_eq!(repl.cursor_position(), 8);
+
+    // Submitting returns to insert mode
+    type_keys(&mut repl, "a");
+    type_keys(&mut repl, "ns\n");
+    assert_eq!(repl.take_output(), vec!["dns"]);
+    assert_eq!(repl.vi_mode(), Some(ViMode::Insert));
+
+    type_keys(&mut repl, "dns");
+    repl.handle_key(Key::Esc).unwrap();
+    repl.handle_key(Key::Char('\n')).unwrap();
+    assert_eq!(repl.vi_mode(), Some(ViMode::Insert));
+}
+
+#[test]
+fn repl_vi_mode_indicator() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_edit_mode(EditMode::Vi)
+        .build_cooked();
+
+    repl.handle_key(Key::Char('s')).unwrap();
+    assert_eq!(repl.render_line(), "[I] >> s");
+    assert_eq!(repl.cursor_column(), 8);
+    assert_eq!(repl.prompt_len(), 7);
+
+    repl.handle_key(Key::Esc).unwrap();
+    assert_eq!(repl.render_line(), "[N] >> s");
+    assert_eq!(repl.cursor_column(), 7);
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompts(Prompts {
+            primary: "> ".into(),
+            vi_insert: String::new(),
+            vi_normal: ": ".into(),
+            ..Default::default()
+        })
+        .with_edit_mode(EditMode::Vi)
+        .build_cooked();
+
+    assert_eq!(repl.render_line(), "> ");
+    repl.handle_key(Key::Esc).unwrap();
+    assert_eq!(repl.render_line(), ": > ");
+    assert_eq!(repl.prompt_len(), 4);
+
+    // The line of the output starts with the indicator as well
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .with_command(named("status"))
+        .with_edit_mode(EditMode::Vi)
+        .build_with_writer(screen.clone());
+
+    type_keys(&mut repl, "status");
+    screen.take();
+    repl.handle_key(Key::Char('\n')).unwrap();
+    assert_eq!(
+        screen.take(),
+        format!("\r\n[I] > {}\rstatus\r\r\n[I] > ", clear::CurrentLine)
+    );
+}
+
+#[test]
+fn repl_vi_history() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("service").with_subcommand(named("dns")))
+        .with_command(named("status"))
+        .with_edit_mode(EditMode::Vi)
+        .build_cooked();
+
+    for line in ["service dns\n", "status\n", "service\n"] {
+        type_keys(&mut repl, line);
+    }
+    repl.take_output();
+
+    // k and j navigate the history like Up and Down, the cursor stays on a char
+    type_keys(&mut repl, "sta");
+    repl.handle_key(Key::Esc).unwrap();
+    type_keys(&mut repl, "kk");
+    assert_eq!(repl.current_input(), "status");
+    assert_eq!(repl.cursor_position(), 5);
+    type_keys(&mut repl, "jj");
+    assert_eq!(repl.current_input(), "sta");
+
+    // Search recalls the most recent entry containing the pattern
+    type_keys(&mut repl, "/dn");
+    assert_eq!(repl.vi_mode(), Some(ViMode::Search));
+    assert_eq!(repl.current_input(), "sta");
+    repl.handle_key(Key::Char('\n')).unwrap();
+    assert_eq!(repl.vi_mode(), Some(ViMode::Normal));
+    assert_eq!(repl.current_input(), "service dns");
+    assert_eq!(repl.cursor_position(), 0);
+
+    // j continues from the recalled entry
+    type_keys(&mut repl, "j");
+    assert_eq!(repl.current_input(), "status");
+
+    // Without a match or when cancelled, the input stays as it is
+    type_keys(&mut repl, "/http\n");
+    assert_eq!(repl.current_input(), "status");
+    type_keys(&mut repl, "/s");
+    repl.feed_keys(&[Key::Backspace, Key::Backspace]).unwrap();
+    assert_eq!(repl.vi_mode(), Some(ViMode::Normal));
+    type_keys(&mut repl, "/s");
+    repl.handle_key(Key::Esc).unwrap();
+    assert_eq!(repl.vi_mode(), Some(ViMode::Normal));
+    assert_eq!(repl.current_input(), "status");
+}
+
+#[test]
+fn repl_prompt_markers() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .with_command(named("status"))
+        .with_prompt_markers(true)
+        .build_with_writer(screen.clone());
+
+    repl.handle_key(Key::Char('s')).unwrap();
+    assert_eq!(
+        screen.take(),
+        format!(
+            "{}\r\x1b]133;A\x07> \x1b]133;B\x07s\r{}",
+            clear::CurrentLine,
+            cursor::Right(3)
+        )
+    );
+
+    for (line, exit_code) in [("status", 0), ("reboot", 1)] {
+        repl.set_input("").unwrap();
+        type_keys(&mut repl, line);
+        screen.take();
+
+        repl.handle_key(Key::Char('\n')).unwrap();
+        let output = screen.take();
+        let executed = output.find("\x1b]133;C\x07").unwrap();
+        let finished = output
+            .find(&format!("\x1b]133;D;{}\x07", exit_code))
+            .unwrap();
+
+        assert!(executed < finished);
+        assert!(output.ends_with("\r\n\x1b]133;A\x07> \x1b]133;B\x07"));
+    }
+}
+
+#[test]
+fn repl_draw_prompt() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .build_with_writer(screen.clone());
+
+    repl.draw_prompt().unwrap();
+    assert_eq!(
+        screen.take(),
+        format!("{}\r> \r{}", clear::CurrentLine, cursor::Right(2))
+    );
+
+    repl.handle_key(Key::Char('a')).unwrap();
+    screen.take();
+    repl.draw_prompt().unwrap();
+    assert_eq!(
+        screen.take(),
+        format!("{}\r> a\r{}", clear::CurrentLine, cursor::Right(3))
+    );
+}
+
+#[test]
+fn repl_initial_input() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("service").with_subcommand(named("dns")))
+        .with_initial_input("service dsn")
+        .build_cooked();
+
+    assert_eq!(repl.current_input(), "service dsn");
+    assert_eq!(repl.cursor_position(), 11);
+
+    // The initial input is not an edit which can be undone
+    repl.handle_key(Key::Ctrl('z')).unwrap();
+    assert_eq!(repl.current_input(), "service dsn");
+
+    repl.feed_keys(&[
+        Key::Backspace,
+        Key::Backspace,
+        Key::Char('n'),
+        Key::Char('s'),
+    ])
+    .unwrap();
+    repl.handle_key(Key::Char('\n')).unwrap();
+    assert_eq!(repl.take_output(), vec!["dns"]);
+    assert_eq!(repl.current_input(), "");
+}
+
+#[test]
+fn repl_insert_mid_line() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .build_with_writer(screen.clone());
+
+    for c in "abd".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    repl.handle_key(Key::Left).unwrap();
+    screen.take();
+
+    // The cursor lands right after the inserted char, including the width
+    // of the prompt
+    repl.handle_key(Key::Char('c')).unwrap();
+    assert_eq!(repl.current_input(), "abcd");
+    assert_eq!(
+        screen.take(),
+        format!("{}\r> abcd\r{}", clear::CurrentLine, cursor::Right(5))
+    );
+
+    // Wide chars occupy two columns
+    repl.feed_keys(&[Key::Left, Key::Left]).unwrap();
+    screen.take();
+    repl.handle_key(Key::Char('界')).unwrap();
+    assert_eq!(
+        screen.take(),
+        format!("{}\r> a界bcd\r{}", clear::CurrentLine, cursor::Right(5))
+    );
+}
+
+#[test]
+fn repl_history_navigation() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("git").with_remainder(true))
+        .with_command(named("ls"))
+        .build_cooked();
+
+    for line in ["git status", "ls", "git log"] {
+        repl.feed_line(line);
+    }
+
+    repl.handle_key(Key::Up).unwrap();
+    assert_eq!(repl.current_input(), "git log");
+    repl.handle_key(Key::Up).unwrap();
+    assert_eq!(repl.current_input(), "ls");
+    repl.feed_keys(&[Key::Up, Key::Up]).unwrap();
+    assert_eq!(repl.current_input(), "git status");
+
+    repl.handle_key(Key::Down).unwrap();
+    assert_eq!(repl.current_input(), "ls");
+    repl.feed_keys(&[Key::Down, Key::Down]).unwrap();
+    assert_eq!(repl.current_input(), "");
+}
+
+#[test]
+fn repl_history_search() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_history_search(HistorySearchMode::Prefix)
+        .with_command(named("git").with_remainder(true))
+        .with_command(named("ls").with_remainder(true))
+        .build_cooked();
+
+    for line in ["git status", "ls git", "git log", "ls"] {
+        repl.feed_line(line);
+    }
+
+    repl.feed_keys(&[Key::Char('g'), Key::Up]).unwrap();
+    assert_eq!(repl.current_input(), "git log");
+    repl.feed_keys(&[Key::Up, Key::Up]).unwrap();
+    assert_eq!(repl.current_input(), "git status");
+
+    repl.feed_keys(&[Key::Down, Key::Down]).unwrap();
+    assert_eq!(repl.current_input(), "g");
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_history_search(HistorySearchMode::Substring)
+        .with_command(named("git").with_remainder(true))
+        .with_command(named("ls").with_remainder(true))
+        .build_cooked();
+
+    for line in ["git status", "ls git", "git log", "ls"] {
+        repl.feed_line(line);
+    }
+
+    repl.feed_keys(&[Key::Char('s'), Key::Up]).unwrap();
+    assert_eq!(repl.current_input(), "ls");
+    repl.handle_key(Key::Up).unwrap();
+    assert_eq!(repl.current_input(), "ls git");
+    repl.feed_keys(&[Key::Up, Key::Up]).unwrap();
+    assert_eq!(repl.current_input(), "git status");
+
+    // Editing the recalled entry starts a new search
+    repl.feed_keys(&[Key::Backspace; 7]).unwrap();
+    repl.handle_key(Key::Up).unwrap();
+    assert_eq!(repl.current_input(), "git log");
+}
+
+#[test]
+fn repl_help_version_exit_builtins() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("status").with_description("Prints the status"))
+        .with_command(named("service").with_subcommand(named("dns")))
+        .with_version("1.3.4")
+        .with_chaining(true)
+        .build_cooked();
+
+    let mut run = |line: &str| {
+        let execution = repl.feed_line(line).remove(0);
+        (execution.output, execution.status)
+    };
+
+    assert_eq!(
+        run("help"),
+        (
+            Some("service\nstatus   Prints the status".into()),
+            ExecStatus::Success
+        )
+    );
+    assert_eq!(
+        run("help service dns"),
+        (Some("Usage: service dns".into()), ExecStatus::Success)
+    );
+    assert_eq!(
+        run("help reboot"),
+        (None, ExecStatus::Failure("No such command: reboot".into()))
+    );
+    assert_eq!(run("version"), (Some("1.3.4".into()), ExecStatus::Success));
+
+    assert!(!repl.exit_requested());
+    assert_eq!(repl.feed_line("exit; status").len(), 1);
+    assert!(repl.exit_requested());
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).build_cooked();
+    // Scripts stop at `exit`, so `version` doesn't run
+    repl.run_script("exit\nversion\n".as_bytes()).unwrap();
+    assert!(repl.exit_requested());
+    assert_eq!(repl.take_output(), vec![""]);
+}
+
+#[test]
+fn repl_disabled_builtins() {
+    let mut counter = 1;
+    let mut repl = Repl::builder(&mut counter)
+        .with_state_reset(|| 0)
+        .with_builtin(Builtin::Reset, false)
+        .build_cooked();
+
+    assert_eq!(
+        repl.feed_line("reset")[0].status,
+        ExecStatus::Failure("No such command: reset".into())
+    );
+
+    let mut counter = 1;
+    let mut repl = Repl::builder(&mut counter)
+        .with_state_reset(|| 0)
+        .with_builtin(Builtin::Reset, false)
+        .with_builtin(Builtin::Reset, true)
+        .build_cooked();
+
+    assert_eq!(repl.feed_line("reset")[0].status, ExecStatus::Success);
+
+    let mut counter = 1;
+    let mut repl = Repl::builder(&mut counter)
+        .with_state_reset(|| 0)
+        .with_builtins(false)
+        .build_cooked();
+
+    assert_ne!(repl.feed_line("reset")[0].status, ExecStatus::Success);
+
+    // Commands replace builtins with the same name
+    let mut counter = 1;
+    let mut repl = Repl::builder(&mut counter)
+        .with_state_reset(|| 0)
+        .with_command(Command::new("reset", |_| Ok(Some("custom".into()))))
+        .build_cooked();
+
+    assert_eq!(repl.feed_line("reset")[0].output.as_deref(), Some("custom"));
+    drop(repl);
+    assert_eq!(counter, 1);
+
+    assert_eq!(
+        Builtin::from_name(Builtin::Wait.name()),
+        Some(Builtin::Wait)
+    );
+}
+
+#[test]
+fn repl_render_line() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("日本>")
+        .with_highlighter(|input| format!("[{}]", input))
+        .build_cooked();
+
+    assert_eq!(repl.render_line(), "日本> []");
+    assert_eq!(repl.cursor_column(), 6);
+
+    for c in "abc".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    repl.handle_key(Key::Left).unwrap();
+
+    assert_eq!(repl.render_line(), "日本> [abc]");
+    assert_eq!(repl.cursor_column(), 8);
+}
+
+#[test]
+fn repl_highlighter_cursor() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .with_highlighter(|input| {
+            format!(
+                "{}{}{}",
+                color::Fg(color::Blue),
+                input,
+                color::Fg(color::Reset)
+            )
+        })
+        .build_with_writer(screen.clone());
+
+    for c in "ac".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    repl.handle_key(Key::Left).unwrap();
+    screen.take();
+
+    // The escape codes of the highlighter don't move the cursor
+    repl.handle_key(Key::Char('b')).unwrap();
+    assert_eq!(repl.cursor_column(), 4);
+    assert_eq!(
+        screen.take(),
+        format!(
+            "{}\r> {}abc{}\r{}",
+            clear::CurrentLine,
+            color::Fg(color::Blue),
+            color::Fg(color::Reset),
+            cursor::Right(4)
+        )
+    );
+}
+
+#[test]
+fn repl_prompts() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompts(Prompts {
+            primary: "λ ".into(),
+            output: "=> ".into(),
+            ..Default::default()
+        })
+        .with_command(Command::new("hi", |_| Ok(Some("hello".into()))))
+        .build_cooked();
+
+    assert_eq!(repl.render_line(), "λ ");
+    assert_eq!(repl.prompt_len(), 2);
+
+    for c in "hi\n".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    assert_eq!(repl.take_output(), vec!["=> hello"]);
+
+    let prompts = Prompts::default();
+    assert_eq!(prompts.primary, ">> ");
+    assert_eq!(prompts.continuation, ".. ");
+    assert_eq!(prompts.output, "");
+}
+
+fn named(name: &'static str) -> Command<()> {
+    Command::new(name, move |_| Ok(Some(name.into())))
+}
+
+fn type_keys(repl: &mut Repl<()>, input: &str) {
+    repl.feed_keys(&input.chars().map(Key::Char).collect::<Vec<_>>())
+        .unwrap();
+}
+
+fn prefix_repl(state: &mut ()) -> ReplBuilder<'_, ()> {
+    Repl::builder(state)
+        .with_command(
+            named("service")
+                .with_subcommand(named("dns"))
+                .with_subcommand(named("dhcp")),
+        )
+        .with_command(named("status"))
+        .with_command(named("stats"))
+}
+
+#[test]
+fn repl_prefix_matching() {
+    let mut state = ();
+    let mut repl = prefix_repl(&mut state).build();
+    assert!(matches!(
+        repl.exec_and_exit("ser"),
+        Err(ReplError::NoSuchCommandError(_))
+    ));
+
+    let mut state = ();
+    let mut repl = prefix_repl(&mut state).with_prefix_matching(true).build();
+    assert_eq!(
+        repl.exec_and_exit("ser").unwrap().as_deref(),
+        Some("service")
+    );
+    assert_eq!(repl.exec_and_exit("se dn").unwrap().as_deref(), Some("dns"));
+    assert_eq!(
+        repl.exec_and_exit("status").unwrap().as_deref(),
+        Some("status")
+    );
+
+    match repl.exec_and_exit("stat") {
+        Err(ReplError::ParserError(ParserError::AmbiguousCommand { input, candidates })) => {
+            assert_eq!(input, "stat");
+            assert_eq!(candidates, vec!["stats", "status"]);
+        }
+        res => panic!("unexpected result: {:?}", res),
+    }
+
+    let err = repl.exec_and_exit("service d").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Parser error: Ambiguous command 'd', candidates: dhcp, dns"
+    );
+}
+
+#[test]
+fn repl_vars() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::new("set", |mut ctx: FnContext<()>| {
+                let name: String = ctx.args().get("name")?;
+                let value: String = ctx.args().get("value")?;
+                ctx.vars_mut().set(name, value);
+                Ok(None)
+            })
+            .with_arg("name", false)
+            .with_arg("value", false),
+        )
+        .with_command(
+            Command::new("get", |ctx: FnContext<()>| {
+                let name: String = ctx.args().get("name")?;
+                Ok(ctx.vars().get(&name).map(String::from))
+            })
+            .with_arg("name", false),
+        )
+        .build();
+
+    repl.vars_mut().set("HOST", "localhost");
+    assert_eq!(
+        repl.exec_and_exit("get --name HOST").unwrap().as_deref(),
+        Some("localhost")
+    );
+
+    repl.exec_and_exit("set --name PORT --value 53").unwrap();
+    assert_eq!(repl.vars().get("PORT"), Some("53"));
+
+    let vars: Vec<_> = repl.vars().iter().collect();
+    assert_eq!(vars, vec![("HOST", "localhost"), ("PORT", "53")]);
+}
+
+#[test]
+fn repl_variable_expansion() {
+    let echo = || {
+        Command::new("echo", |ctx: FnContext<()>| {
+            Ok(Some(ctx.args().remainder().to_string()))
+        })
+        .with_remainder(true)
+    };
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).with_command(echo()).build();
+    repl.vars_mut().set("NAME", "rupl");
+    assert_eq!(
+        repl.exec_and_exit("echo $NAME").unwrap().as_deref(),
+        Some("$NAME")
+    );
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(echo())
+        .with_variable_expansion(true)
+        .build();
+    repl.vars_mut().set("NAME", "rupl");
+    repl.vars_mut().set("CMD", "echo");
+    assert_eq!(
+        repl.exec_and_exit("$CMD hi ${NAME} '$NAME'")
+            .unwrap()
+            .as_deref(),
+        Some("hi rupl '$NAME'")
+    );
+}
+
+#[test]
+fn repl_stats() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(Command::new("hi", |_| Ok(Some("hello".into()))))
+        .build_cooked();
+
+    assert_eq!(repl.stats(), ReplStats::default());
+
+    for c in "hi\nho\n".chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+    repl.exec_and_exit("hi").unwrap();
+
+    let stats = repl.stats();
+    assert_eq!(stats.commands, 3);
+    assert_eq!(stats.failed, 1);
+    assert_eq!(stats.keys, 6);
+}
+
+#[test]
+fn repl_optional_args() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::new("dns", |ctx: FnContext<()>| {
+                let port: Option<u16> = ctx.args().get("port")?;
+                Ok(Some(format!("{:?}", port)))
+            })
+            .with_arg("port", false),
+        )
+        .build();
+
+    let output = repl.exec_and_exit("dns --port 53").unwrap();
+    assert_eq!(output.as_deref(), Some("Some(53)"));
+
+    let output = repl.exec_and_exit("dns").unwrap();
+    assert_eq!(output.as_deref(), Some("None"));
+}
+
+#[test]
+fn repl_chaining() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_chaining(true)
+        .with_command(named("ok"))
+        .with_command(named("other"))
+        .with_command(Command::new("fail", |_| {
+            Err(ReplError::EditorError("failed".into()))
+        }))
+        .build_cooked();
+
+    let mut run = |line: &str| {
+        for c in line.chars().chain(['\n']) {
+            repl.handle_key(Key::Char(c)).unwrap();
+        }
+        repl.take_output()
+    };
+
+    assert_eq!(run("ok && other"), vec!["ok", "other"]);
+    assert_eq!(
+        run("fail && other"),
+        vec!["Unrecoverable readline error: failed"]
+    );
+    assert_eq!(
+        run("fail || other; ok;"),
+        vec!["Unrecoverable readline error: failed", "other", "ok"]
+    );
+    assert_eq!(run("ok || other && ok"), vec!["ok", "ok"]);
+    assert_eq!(run("ok '&&' other"), vec!["Invalid arguments\nUsage: ok"]);
+    assert_eq!(run("ok &&"), vec!["Missing command in chain"]);
+    assert_eq!(run("; ok"), vec!["Missing command in chain"]);
+}
+
+#[cfg(feature = "background")]
+#[test]
+fn repl_background_jobs() {
+    use std::sync::{mpsc, Arc, Mutex};
+
+    let (tx, rx) = mpsc::channel::<()>();
+    let rx = Arc::new(Mutex::new(rx));
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("ok"))
+        .with_command(named("work").with_background(move |_| {
+            rx.lock().unwrap().recv().unwrap();
+            Ok(Some("waited".into()))
+        }))
+        .build_cooked();
+
+    let mut run = |line: &str| {
+        for c in line.chars().chain(['\n']) {
+            repl.handle_key(Key::Char(c)).unwrap();
+        }
+        repl.take_output()
+    };
+
+    assert_eq!(run("jobs"), vec![""]);
+    assert_eq!(run("work &"), vec!["[job 1] started"]);
+    assert_eq!(run("jobs"), vec!["[1] running  work"]);
+    assert_eq!(
+        run("work &"),
+        vec!["Job error: A background job is already running"]
+    );
+    assert_eq!(run("ok"), vec!["ok"]);
+    assert_eq!(
+        run("ok &"),
+        vec!["Job error: Command 'ok' can't run in the background"]
+    );
+
+    tx.send(()).unwrap();
+    let output = loop {
+        let output = run("");
+        if !output.is_empty() {
+            break output;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+
+    assert_eq!(output, vec!["[job 1] done", "waited"]);
+    assert_eq!(run("jobs"), vec!["[1] done     work"]);
+    assert_eq!(run("wait 1"), vec!["waited"]);
+    assert_eq!(run("wait 2"), vec!["Job error: No such job: 2"]);
+    assert_eq!(run("wait"), vec!["Invalid number of args"]);
+    assert_eq!(
+        run("'work &'"),
+        vec!["Unknown command. Did you mean 'work'?"]
+    );
+}
+
+#[cfg(feature = "background")]
+#[test]
+fn repl_background_wait() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("sleep").with_background(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            Ok(Some("slept".into()))
+        }))
+        .build_cooked();
+
+    let mut run = |line: &str| {
+        for c in line.chars().chain(['\n']) {
+            repl.handle_key(Key::Char(c)).unwrap();
+        }
+        repl.take_output()
+    };
+
+    assert_eq!(run("sleep &"), vec!["[job 1] started"]);
+    assert_eq!(run("wait 1"), vec!["slept"]);
+    assert_eq!(run(""), Vec::<String>::new());
+    assert_eq!(run("sleep &"), vec!["[job 2] started"]);
+}
+
+#[cfg(feature = "background")]
+#[test]
+fn repl_background_checks() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            named("delete")
+                .dangerous(true)
+                .with_background(|_| Ok(None)),
+        )
+        .with_command(
+            named("sync")
+                .with_cooldown(Duration::from_secs(60))
+                .with_background(|_| Ok(None)),
+        )
+        .build_cooked();
+
+    let mut run = |line: &str| match repl.feed_line(line).remove(0).status {
+        ExecStatus::Success => None,
+        ExecStatus::Failure(err) => Some(err),
+    };
+
+    // Background jobs are confirmed and cooled down like foreground runs
+    assert_eq!(run("delete &"), Some("Skipped 'delete'".into()));
+    assert_eq!(run("sync &"), None);
+    assert_eq!(run("wait 1"), None);
+    assert_eq!(
+        run("sync &"),
+        Some("Command 'sync' can run again in 60s".into())
+    );
+    assert_eq!(
+        run("sync"),
+        Some("Command 'sync' can run again in 60s".into())
+    );
+}
+
+#[test]
+fn repl_on_tick() {
+    let mut ticks = 0;
+    let mut repl = Repl::builder(&mut ticks)
+        .with_on_tick(Duration::from_millis(500), |ticks| *ticks += 1)
+        .build_cooked();
+
+    // The interval didn't elapse yet. It is generous, so that a slow machine
+    // doesn't tick here already.
+    repl.tick().unwrap();
+
+    thread::sleep(Duration::from_millis(600));
+    repl.tick().unwrap();
+    repl.tick().unwrap();
+    drop(repl);
+
+    assert_eq!(ticks, 1);
+}
+
+#[test]
+fn repl_idle_timeout() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_idle_timeout(Duration::from_secs(60))
+        .with_exit_message("Bye!")
+        .build_with_writer(screen.clone());
+    screen.take();
+
+    assert!(!repl.check_idle(Duration::from_secs(59)).unwrap());
+    assert_eq!(screen.take(), "");
+
+    assert!(repl.check_idle(Duration::from_secs(60)).unwrap());
+    assert_eq!(
+        screen.take(),
+        "\r\nSession timed out due to inactivity\r\nBye!\r\n"
+    );
+}
+
+#[test]
+fn repl_notify() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .build_with_writer(screen.clone());
+
+    repl.feed_keys(&[Key::Char('a'), Key::Char('b'), Key::Left])
+        .unwrap();
+    screen.take();
+
+    repl.notify("job 1\ndone").unwrap();
+    assert_eq!(
+        screen.take(),
+        format!(
+            "{}\rjob 1\r\ndone\r\n{}\r> ab\r{}",
+            clear::CurrentLine,
+            clear::CurrentLine,
+            cursor::Right(3)
+        )
+    );
+    assert_eq!(repl.current_input(), "ab");
+    assert_eq!(repl.cursor_position(), 1);
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).build_cooked();
+    repl.notify("done").unwrap();
+    assert_eq!(repl.take_output(), vec!["done"]);
+}
+
+#[test]
+fn repl_output_handle() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state).build_cooked();
+    let output = repl.output_handle();
+
+    let handles: Vec<_> = (0..2)
+        .map(|n| {
+            let output = output.clone();
+            thread::spawn(move || output.print(format!("thread {}", n)))
+        })
+        .collect();
+    for handle in handles {
+        assert!(handle.join().unwrap());
+    }
+    assert!(output.print("main"));
+
+    repl.tick().unwrap();
+    let mut lines = repl.take_output();
+    assert_eq!(lines.pop().unwrap(), "main");
+    lines.sort();
+    assert_eq!(lines, vec!["thread 0", "thread 1"]);
+
+    // The queue is drained
+    repl.tick().unwrap();
+    assert!(repl.take_output().is_empty());
+
+    drop(repl);
+    assert!(!output.print("discarded"));
+}
+
+#[test]
+fn repl_exec_and_exit() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_welcome_message("Welcome")
+        .with_exec_welcome(true)
+        .with_command(named("status"))
+        .with_command(named("stats"))
+        .with_chaining(true)
+        .build_with_writer(screen.clone());
+
+    // Like a submitted line, chained commands run one after another and the
+    // result of the last one is returned
+    assert_eq!(
+        repl.exec_and_exit("status; reboot; stats")
+            .unwrap()
+            .as_deref(),
+        Some("stats")
+    );
+    assert_eq!(screen.take(), "Welcome\r\nstatus\nUnknown command\nstats\n");
+    assert_eq!(repl.stats().commands, 3);
+    assert_eq!(repl.stats().failed, 1);
+
+    assert!(matches!(
+        repl.exec_and_exit("status && reboot"),
+        Err(ReplError::NoSuchCommandError(_))
+    ));
+
+    // The welcome message is not printed by default
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_welcome_message("Welcome")
+        .with_command(named("status"))
+        .build_with_writer(screen.clone());
+
+    repl.exec_and_exit("status").unwrap();
+    assert_eq!(screen.take(), "status\n");
+}
+
+#[test]
+fn repl_redirect() {
+    let path = env::temp_dir().join(format!("rupl-redirect-{}.txt", process::id()));
+    let _ = fs::remove_file(&path);
+    let target = path.display();
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("status"))
+        .with_command(Command::new("quiet", |_| Ok(None)))
+        .with_pipes(true)
+        .build_cooked();
+
+    let mut run = |line: &str| {
+        let execution = repl.feed_line(line).remove(0);
+        (execution.output, execution.status)
+    };
+
+    assert_eq!(
+        run(&format!("status > {}", target)),
+        (None, ExecStatus::Success)
+    );
+    assert_eq!(
+        run(&format!("status >> '{}'", target)),
+        (None, ExecStatus::Success)
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "status\nstatus\n");
+
+    // Like in a shell, commands without output truncate the file
+    assert_eq!(
+        run(&format!("quiet > \"{}\"", target)),
+        (None, ExecStatus::Success)
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "");
+
+    assert_eq!(
+        run("status > "),
+        (
+            None,
+            ExecStatus::Failure("Parser error: Missing redirection target".into())
+        )
+    );
+    assert_eq!(
+        run(&format!("status > {} | cat", target)),
+        (
+            None,
+            ExecStatus::Failure("Parser error: Output can't be both redirected and piped".into())
+        )
+    );
+    // A quoted `>` is passed as an argument
+    assert_eq!(
+        run("status '>' x"),
+        (
+            None,
+            ExecStatus::Failure("Parser error: Invalid arguments".into())
+        )
+    );
+
+    // One-shot execution redirects the output as well
+    assert_eq!(
+        repl.exec_and_exit(&format!("status > {}", target)).unwrap(),
+        None
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "status\n");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn repl_redirect_error() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("log").with_output_prompt(Some("| ".into())))
+        .with_command(Command::new("broken", |_| {
+            Err(io::Error::other("terminal closed").into())
+        }))
+        .build_cooked();
+
+    // The command is resolved without the redirection, so its output prompt
+    // is used for the error
+    repl.feed_keys(
+        &"log > rupl-no-such-dir/out.txt"
+            .chars()
+            .map(Key::Char)
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    repl.handle_key(Key::Char('\n')).unwrap();
+    assert_eq!(
+        repl.take_output(),
+        vec!["| Redirect error: No such file or directory (os error 2)"]
+    );
+
+    // Fatal errors are returned instead of being reported as output
+    type_keys(&mut repl, "broken");
+    let err = repl.handle_key(Key::Char('\n')).unwrap_err();
+    assert!(err.is_fatal());
+    assert!(repl.take_output().is_empty());
+}
+
+#[test]
+fn repl_pipe() {
+    let path = env::temp_dir().join(format!("rupl-pipe-{}.txt", process::id()));
+    let _ = fs::remove_file(&path);
+    let target = path.display();
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("status"))
+        .with_pipes(true)
+        .build_cooked();
+
+    let mut run = |line: &str| {
+        let execution = repl.feed_line(line).remove(0);
+        (execution.output, execution.status)
+    };
+
+    assert_eq!(
+        run(&format!("status | tr a-z A-Z > {}", target)),
+        (None, ExecStatus::Success)
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "STATUS\n");
+
+    assert_eq!(
+        run("status | "),
+        (
+            None,
+            ExecStatus::Failure("Parser error: Missing pipe target".into())
+        )
+    );
+    assert_eq!(
+        run("status | rupl-no-such-program 2>/dev/null"),
+        (
+            None,
+            ExecStatus::Failure(
+                "Pipe error: 'rupl-no-such-program 2>/dev/null' exit status: 127".into()
+            )
+        )
+    );
+
+    // A quoted `|` is passed as an argument
+    assert_eq!(
+        run("status '|' cat"),
+        (
+            None,
+            ExecStatus::Failure("Parser error: Invalid arguments".into())
+        )
+    );
+
+    // One-shot execution pipes the output as well
+    assert_eq!(
+        repl.exec_and_exit(&format!("status | cat > {}", target))
+            .unwrap(),
+        None
+    );
+    assert_eq!(fs::read_to_string(&path).unwrap(), "status\n");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn repl_transcript_file() {
+    let path = env::temp_dir().join(format!("rupl-transcript-{}.log", process::id()));
+    let _ = fs::remove_file(&path);
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("service").with_subcommand(named("dns")))
+        .with_prompt(format!(
+            "{}>{}",
+            color::Fg(color::Blue),
+            color::Fg(color::Reset)
+        ))
+        .with_transcript_file(&path)
+        .build_cooked();
+
+    type_keys(&mut repl, "service dns\n");
+    repl.notify("job done").unwrap();
+    assert_eq!(repl.take_output(), vec!["dns", "job done"]);
+    drop(repl);
+
+    let transcript = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(transcript, "> service dns\ndns\njob done\n");
+
+    // A directory can't be opened as transcript, the error is reported once
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("dns"))
+        .with_transcript_file(env::temp_dir())
+        .with_transcript_timestamps(true)
+        .build_cooked();
+
+    repl.feed_keys(&[
+        Key::Char('d'),
+        Key::Char('n'),
+        Key::Char('s'),
+        Key::Char('\n'),
+    ])
+    .unwrap();
+    let outputs = repl.take_output();
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs[1].starts_with("Transcript error:"));
+
+    repl.feed_keys(&[
+        Key::Char('d'),
+        Key::Char('n'),
+        Key::Char('s'),
+        Key::Char('\n'),
+    ])
+    .unwrap();
+    assert_eq!(repl.take_output(), vec!["dns"]);
+}
+
+#[test]
+fn repl_transcript_notify_error() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_transcript_file(env::temp_dir())
+        .build_cooked();
+
+    // A failed transcript write neither fails nor swallows the message
+    let output = repl.output_handle();
+    output.print("job done");
+    repl.tick().unwrap();
+    let outputs = repl.take_output();
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs[0], "job done");
+    assert!(outputs[1].starts_with("Transcript error:"));
+
+    repl.notify("job failed").unwrap();
+    assert_eq!(repl.take_output(), vec!["job failed"]);
+}
+
+#[test]
+fn repl_command_output_prompt() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_output_prompt("#")
+        .with_command(named("status"))
+        .with_command(named("dump").with_output_prompt(Some(String::new())))
+        .with_command(named("log").with_output_prompt(Some("| ".into())))
+        .build_cooked();
+
+    for (line, output) in [("status", "# status"), ("dump", "dump"), ("log", "| log")] {
+        type_keys(&mut repl, line);
+        repl.handle_key(Key::Char('\n')).unwrap();
+        assert_eq!(repl.take_output(), vec![output]);
+    }
+}
+
+#[test]
+fn repl_error_formatter() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_output_prompt("#")
+        .with_command(named("status"))
+        .with_error_formatter(|err| format!("error: {}", err))
+        .build_cooked();
+
+    for (line, output) in [
+        ("status", "# status"),
+        ("reboot", "# error: No such command: reboot"),
+    ] {
+        type_keys(&mut repl, line);
+        repl.handle_key(Key::Char('\n')).unwrap();
+        assert_eq!(repl.take_output(), vec![output]);
+    }
+}
+
+#[test]
+fn repl_finder() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            named("service")
+                .with_subcommand(named("dns"))
+                .with_subcommand(named("dhcp")),
+        )
+        .with_command(named("status"))
+        .build_cooked();
+
+    repl.feed_keys(&[
+        Key::Ctrl('p'),
+        Key::Char('s'),
+        Key::Char('d'),
+        Key::Char('n'),
+        Key::Char('\n'),
+    ])
+    .unwrap();
+    assert_eq!(repl.current_input(), "service dns ");
+
+    // The arrow keys select the next match
+    repl.set_input("").unwrap();
+    repl.feed_keys(&[
+        Key::Ctrl('p'),
+        Key::Char('s'),
+        Key::Down,
+        Key::Down,
+        Key::Up,
+    ])
+    .unwrap();
+    repl.handle_key(Key::Char('\n')).unwrap();
+    assert_eq!(repl.current_input(), "service dhcp ");
+
+    // Escape closes the finder without inserting a command
+    repl.feed_keys(&[Key::Ctrl('p'), Key::Char('x'), Key::Esc])
+        .unwrap();
+    assert_eq!(repl.current_input(), "service dhcp ");
+    assert!(repl.take_output().is_empty());
+
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("status"))
+        .with_finder_key(Some(Key::Ctrl('o')))
+        .build_with_writer(screen.clone());
+
+    repl.handle_key(Key::Ctrl('o')).unwrap();
+    let drawn = screen.take();
+    assert!(drawn.starts_with(&screen::ToAlternateScreen.to_string()));
+    assert!(drawn.contains(&format!("search: \r\n{}status", style::Invert)));
+
+    repl.handle_key(Key::Char('\n')).unwrap();
+    assert!(screen.take().starts_with(&screen::ToMainScreen.to_string()));
+    assert_eq!(repl.current_input(), "status ");
+}
+
+#[test]
+fn repl_fuzzy_matching() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            named("service")
+                .with_subcommand(named("dns").with_arg("port", false))
+                .with_subcommand(named("dhcp")),
+        )
+        .with_command(named("status"))
+        .with_fuzzy_matching(true)
+        .build_cooked();
+
+    let mut run = |line: &str| {
+        let execution = repl.feed_line(line).remove(0);
+        match execution.status {
+            ExecStatus::Success => execution.output.unwrap_or_default(),
+            ExecStatus::Failure(err) => err,
+        }
+    };
+
+    assert_eq!(run("sdn --port 53"), "dns");
+    assert_eq!(run("stts"), "status");
+
+    // The parent wins over its equally matching subcommands
+    assert_eq!(run("svc"), "service");
+
+    assert_eq!(
+        run("sd"),
+        "Parser error: Ambiguous command 'sd', candidates: service dhcp, service dns"
+    );
+    assert_eq!(run("xyz"), "No such command: xyz");
+
+    // The chars of `ts` are scattered across `status`
+    assert_eq!(run("ts"), "No such command: ts");
+
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("status"))
+        .build_cooked();
+    assert_ne!(repl.feed_line("stts")[0].status, ExecStatus::Success);
+}
+
+#[test]
+fn repl_dangerous_command() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("delete").dangerous(true))
+        .with_command(named("list"))
+        .with_chaining(true)
+        .build_cooked();
+
+    // Without a terminal to ask, dangerous commands are declined
+    repl.feed_keys(
+        &"delete && list\n"
+            .chars()
+            .map(Key::Char)
+            .collect::<Vec<_>>(),
+    )
+    .unwrap();
+    assert_eq!(
+        repl.take_output(),
+        vec!["Run 'delete'? [y/N]", "Skipped 'delete'"]
+    );
+
+    let script = "delete\nyes\ndelete\nn\nlist\n";
+    repl.run_script(script.as_bytes()).unwrap();
+    assert_eq!(
+        repl.take_output(),
+        vec![
+            "Run 'delete'? [y/N]",
+            "delete",
+            "Run 'delete'? [y/N]",
+            "Skipped 'delete'",
+            "list"
+        ]
+    );
+}
+
+#[test]
+fn repl_dangerous_command_raw() {
+    let screen = Screen::default();
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_prompt("> ")
+        .with_command(named("delete").dangerous(true))
+        .build_with_writer(screen.clone());
+
+    type_keys(&mut repl, "delete");
+    screen.take();
+
+    // The question starts at the first column, the answer on the next line
+    repl.handle_key(Key::Char('\n')).unwrap();
+    let output = screen.take();
+    assert!(output.contains(&format!("{}\rRun 'delete'? [y/N]", clear::CurrentLine)));
+    assert!(!output.contains("[y/N]\n"));
+}
+
+#[test]
+fn repl_command_cooldown() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(named("sync").with_cooldown(Duration::from_millis(50)))
+        .with_command(named("service").with_subcommand(named("sync")))
+        .build_cooked();
+
+    let mut run = |line: &str| match repl.feed_line(line).remove(0).status {
+        ExecStatus::Success => None,
+        ExecStatus::Failure(err) => Some(err),
+    };
+
+    // The first invocation always runs
+    assert_eq!(run("sync"), None);
+    assert_eq!(
+        run("sync"),
+        Some("Command 'sync' can run again in 1s".into())
+    );
+
+    // Commands with the same name on other levels have no cooldown
+    assert_eq!(run("service sync"), None);
+
+    thread::sleep(Duration::from_millis(60));
+    assert_eq!(run("sync"), None);
+}
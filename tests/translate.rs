@@ -0,0 +1,117 @@
+use rupl::{
+    command::Command,
+    error::ReplError,
+    format::Messages,
+    translate::{English, Text, Translator},
+    ParserError, Repl,
+};
+use termion::event::Key;
+
+struct German;
+
+impl Translator for German {
+    fn translate(&self, text: Text<'_>) -> String {
+        match text {
+            Text::Error(ReplError::NoSuchCommandError(_)) => "Unbekannter Befehl".into(),
+            Text::Error(ReplError::ParserError(ParserError::MissingSubcommand)) => {
+                "Fehlender Unterbefehl".into()
+            }
+            Text::DidYouMean {
+                message,
+                suggestion,
+            } => format!("{}. Meinten Sie '{}'?", message, suggestion),
+            Text::Usage {
+                command,
+                subcommands,
+            } => format!("Aufruf: {} <{}>", command, subcommands.join("|")),
+            Text::DocsTitle => "Befehle".into(),
+            Text::DocsParameters => "Parameter".into(),
+            Text::DocsRemainder => "die restliche Eingabe".into(),
+            text => English.translate(text),
+        }
+    }
+}
+
+fn type_line(repl: &mut Repl<()>, input: &str) -> Vec<String> {
+    for c in input.chars() {
+        repl.handle_key(Key::Char(c)).unwrap();
+    }
+
+    repl.handle_key(Key::Char('\n')).unwrap();
+    repl.take_output()
+}
+
+#[test]
+fn translate_english() {
+    let err = ReplError::NoSuchCommandError("servic".into());
+    assert_eq!(English.translate(Text::Error(&err)), "Unknown command");
+
+    let err = ParserError::InvalidArgs.into();
+    assert_eq!(English.translate(Text::Error(&err)), "Invalid arguments");
+
+    assert_eq!(
+        English.translate(Text::Usage {
+            command: "service",
+            subcommands: &["dns", "http"],
+        }),
+        "Usage: service <dns|http>"
+    );
+    assert_eq!(
+        English.translate(Text::Usage {
+            command: "service",
+            subcommands: &[],
+        }),
+        "Usage: service"
+    );
+    assert_eq!(English.translate(Text::JobStarted(2)), "[job 2] started");
+    assert_eq!(
+        English.translate(Text::IdleTimeout),
+        "Session timed out due to inactivity"
+    );
+}
+
+#[test]
+fn translate_messages() {
+    let messages = Messages {
+        invalid_args: "Ungültige Argumente".into(),
+        ..Default::default()
+    };
+
+    let err = ParserError::InvalidArgs.into();
+    assert_eq!(messages.translate(Text::Error(&err)), "Ungültige Argumente");
+    assert_eq!(messages.translate(Text::JobDone(1)), "[job 1] done");
+}
+
+#[test]
+fn translate_repl() {
+    let mut state = ();
+    let mut repl = Repl::builder(&mut state)
+        .with_command(
+            Command::namespace("service").with_subcommand(Command::new("dns", |_| Ok(None))),
+        )
+        .with_translator(Box::new(German))
+        .build_cooked();
+
+    assert_eq!(
+        type_line(&mut repl, "servce"),
+        vec!["Unbekannter Befehl. Meinten Sie 'service'?"]
+    );
+    assert_eq!(
+        type_line(&mut repl, "service"),
+        vec!["Fehlender Unterbefehl\nAufruf: service <dns>"]
+    );
+    assert_eq!(type_line(&mut repl, "hello"), vec!["Unbekannter Befehl"]);
+}
+
+#[test]
+fn translate_docs() {
+    let mut state = ();
+    let repl = Repl::builder(&mut state)
+        .with_command(Command::new("echo", |_| Ok(None)).with_remainder(true))
+        .with_translator(Box::new(German))
+        .build_cooked();
+
+    let docs = repl.generate_docs();
+    assert!(docs.starts_with("# Befehle\n"));
+    assert!(docs.contains("\nParameter:\n\n- `...`: die restliche Eingabe\n"));
+}
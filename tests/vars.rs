@@ -0,0 +1,48 @@
+use rupl::vars::{UnknownVariable, Variables};
+
+fn vars() -> Variables {
+    let mut vars = Variables::new();
+    vars.set("HOST", "localhost");
+    vars.set("PORT_1", "53");
+    vars
+}
+
+#[test]
+fn vars_expand_unquoted() {
+    let vars = vars();
+
+    assert_eq!(
+        vars.expand("ping $HOST:$PORT_1", UnknownVariable::Keep),
+        "ping localhost:53"
+    );
+    assert_eq!(
+        vars.expand("ping ${HOST}s ${PORT_1}0", UnknownVariable::Keep),
+        "ping localhosts 530"
+    );
+    assert_eq!(
+        vars.expand("echo $ $1 ${} ${HOST $5$", UnknownVariable::Keep),
+        "echo $ $1 ${} ${HOST $5$"
+    );
+    assert_eq!(
+        vars.expand("echo $MODE ${MODE}!", UnknownVariable::Keep),
+        "echo $MODE ${MODE}!"
+    );
+    assert_eq!(
+        vars.expand("echo $MODE ${MODE}!", UnknownVariable::Empty),
+        "echo  !"
+    );
+}
+
+#[test]
+fn vars_expand_quoted() {
+    let vars = vars();
+
+    assert_eq!(
+        vars.expand("echo '$HOST' \"$HOST\"", UnknownVariable::Keep),
+        "echo '$HOST' \"localhost\""
+    );
+    assert_eq!(
+        vars.expand("echo \"it's $HOST\" '\"$HOST\"'", UnknownVariable::Empty),
+        "echo \"it's localhost\" '\"$HOST\"'"
+    );
+}